@@ -0,0 +1,256 @@
+use std::{collections::VecDeque, f64::consts::PI, str::FromStr};
+
+use druid::{Data, Lens};
+use serde::{Deserialize, Serialize};
+
+use super::image_generator::Pixel;
+
+/// the most samples along each side of a pixel, as supersampling needs `samples` squared
+/// times the memory of the image
+pub const MAX_SAMPLES: usize = 16;
+
+/// how many samples are taken for each pixel of a full render
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Data, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AntialiasingMode {
+    /// a single sample at the corner of each pixel
+    Off,
+    /// `samples` x `samples` samples for every pixel, combined by the downsample filter
+    /// around the point that `Off` samples
+    Supersample,
+    /// `samples` x `samples` samples only for the pixels that differ from their
+    /// neighbors or lie close to the boundary of the fractal, averaged around the
+    /// point that `Off` samples
+    Adaptive,
+}
+
+impl FromStr for AntialiasingMode {
+    type Err = String;
+
+    /// parses the same lowercase names that are used in scene files
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(AntialiasingMode::Off),
+            "supersample" => Ok(AntialiasingMode::Supersample),
+            "adaptive" => Ok(AntialiasingMode::Adaptive),
+            _ => Err(format!("unknown anti-aliasing mode '{}'", s)),
+        }
+    }
+}
+
+/// how the samples of a supersampled image are combined into its pixels
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Data, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownsampleFilter {
+    /// the average of the samples inside the pixel
+    Box,
+    /// the samples within a pixel of its center, weighted by their distance to it
+    Tent,
+    /// the samples within two pixels of its center, weighted by a Lanczos window,
+    /// which is sharper than the others but can ring at hard edges
+    Lanczos,
+}
+
+impl DownsampleFilter {
+    /// the weight of a sample `distance` pixels from the center of a pixel
+    fn weight(self, distance: f64) -> f64 {
+        let distance = distance.abs();
+        let sinc = |x: f64| {
+            if x == 0.0 {
+                1.0
+            } else {
+                (PI * x).sin() / (PI * x)
+            }
+        };
+        match self {
+            DownsampleFilter::Box if distance < 0.5 => 1.0,
+            // with an even number of samples, the samples on the edges of the pixel are
+            // shared with its neighbors
+            DownsampleFilter::Box if distance == 0.5 => 0.5,
+            DownsampleFilter::Tent => (1.0 - distance).max(0.0),
+            DownsampleFilter::Lanczos if distance < 2.0 => sinc(distance) * sinc(distance / 2.0),
+            _ => 0.0,
+        }
+    }
+
+    /// the offsets of the samples which contribute to a pixel along one axis, relative to
+    /// the sample at its corner, and their weights. That sample lies on the same point as
+    /// the single sample of an image without anti-aliasing, so the filter is centered on it.
+    pub fn kernel(self, samples: usize) -> Vec<(isize, f32)> {
+        let samples = samples as isize;
+        // two pixels to either side covers the widest filter
+        (-2 * samples..=2 * samples)
+            .filter_map(|offset| {
+                let distance = offset as f64 / samples as f64;
+                let weight = self.weight(distance);
+                (weight != 0.0).then_some((offset, weight as f32))
+            })
+            .collect()
+    }
+}
+
+impl FromStr for DownsampleFilter {
+    type Err = String;
+
+    /// parses the same lowercase names that are used in scene files
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "box" => Ok(DownsampleFilter::Box),
+            "tent" => Ok(DownsampleFilter::Tent),
+            "lanczos" => Ok(DownsampleFilter::Lanczos),
+            _ => Err(format!("unknown downsample filter '{}'", s)),
+        }
+    }
+}
+
+/// the anti-aliasing of an image, see `ImageGenerator::set_antialiasing`
+#[derive(Clone, Copy, Debug, PartialEq, Data, Lens, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Antialiasing {
+    pub mode: AntialiasingMode,
+    /// the number of samples along each side of a pixel, from 1 to `MAX_SAMPLES`
+    pub samples: usize,
+    /// only used for `Supersample`, adaptive samples are combined by the `Box` filter
+    pub filter: DownsampleFilter,
+    /// the difference of a color channel from a neighboring pixel, from 0 to 1,
+    /// above which `Adaptive` adds samples to a pixel
+    pub threshold: f64,
+}
+
+impl Default for Antialiasing {
+    fn default() -> Self {
+        Antialiasing {
+            mode: AntialiasingMode::Off,
+            samples: 3,
+            filter: DownsampleFilter::Tent,
+            threshold: 0.1,
+        }
+    }
+}
+
+/// filters the `width * samples` x `height * samples` image `from` down to `width` x
+/// `height` pixels. The filter is separable, so each row of samples is filtered
+/// horizontally once, and the rows are then combined for every row of pixels.
+pub fn downsample(
+    from: &[Pixel],
+    width: usize,
+    height: usize,
+    samples: usize,
+    filter: DownsampleFilter,
+) -> Vec<Pixel> {
+    let kernel = filter.kernel(samples);
+    let (from_width, from_height) = (width * samples, height * samples);
+    // the taps of the kernel around `start` that lie inside `0..length`
+    let taps = |start: usize, length: usize| {
+        kernel.iter().filter_map(move |&(offset, weight)| {
+            let position = start as isize + offset;
+            (0..length as isize)
+                .contains(&position)
+                .then_some((position as usize, weight))
+        })
+    };
+    let weighted_sum = |colors: &mut dyn Iterator<Item = (Pixel, f32)>| {
+        let mut sum = Pixel::default();
+        let mut total = 0.0;
+        for (color, weight) in colors {
+            for (sum, channel) in sum.iter_mut().zip(color) {
+                *sum += channel * weight;
+            }
+            total += weight;
+        }
+        sum.map(|channel| channel / total)
+    };
+    let filter_row = |y: usize| -> Vec<Pixel> {
+        let row = &from[y * from_width..];
+        (0..width)
+            .map(|x| {
+                weighted_sum(&mut taps(x * samples, from_width).map(|(x, weight)| (row[x], weight)))
+            })
+            .collect()
+    };
+
+    let mut pixels = vec![Pixel::default(); width * height];
+    // the horizontally filtered rows that the current row of pixels needs,
+    // starting with row `first`
+    let mut rows: VecDeque<Vec<Pixel>> = VecDeque::new();
+    let mut first = 0;
+    for y in 0..height {
+        let needed: Vec<_> = taps(y * samples, from_height).collect();
+        let (start, end) = (needed[0].0, needed[needed.len() - 1].0);
+        while first < start {
+            rows.pop_front();
+            first += 1;
+        }
+        while first + rows.len() <= end {
+            rows.push_back(filter_row(first + rows.len()));
+        }
+        for x in 0..width {
+            // negative lobes of the filter can push a channel below zero next to an edge
+            pixels[x + y * width] = weighted_sum(
+                &mut needed
+                    .iter()
+                    .map(|&(row, weight)| (rows[row - first][x], weight)),
+            )
+            .map(|channel| channel.max(0.0));
+        }
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kernels_are_centered_on_the_corner_sample() {
+        for filter in [
+            DownsampleFilter::Box,
+            DownsampleFilter::Tent,
+            DownsampleFilter::Lanczos,
+        ] {
+            for samples in 2..6 {
+                let kernel = filter.kernel(samples);
+                let reversed: Vec<_> = kernel
+                    .iter()
+                    .rev()
+                    .map(|&(offset, weight)| (-offset, weight))
+                    .collect();
+                assert_eq!(kernel, reversed, "{:?} with {} samples", filter, samples);
+            }
+        }
+        assert_eq!(
+            DownsampleFilter::Box.kernel(3),
+            vec![(-1, 1.0), (0, 1.0), (1, 1.0)]
+        );
+        assert_eq!(
+            DownsampleFilter::Box.kernel(2),
+            vec![(-1, 0.5), (0, 1.0), (1, 0.5)]
+        );
+    }
+
+    #[test]
+    fn downsampling_keeps_pixels_in_place() {
+        // a ramp whose samples hold their position in pixels, so each pixel away from
+        // the edges should get the position of its corner, like a single sample would
+        let (width, height) = (8, 4);
+        for samples in 2..5 {
+            let from: Vec<Pixel> = (0..height * samples)
+                .flat_map(|_| (0..width * samples).map(|x| [x as f32 / samples as f32; 4]))
+                .collect();
+            for filter in [DownsampleFilter::Box, DownsampleFilter::Tent] {
+                let pixels = downsample(&from, width, height, samples, filter);
+                for x in 1..width - 1 {
+                    let value = pixels[x + width][0];
+                    assert!(
+                        (value - x as f32).abs() < 1e-5,
+                        "{:?} with {} samples put pixel {} at {}",
+                        filter,
+                        samples,
+                        x,
+                        value
+                    );
+                }
+            }
+        }
+    }
+}
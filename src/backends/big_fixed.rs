@@ -0,0 +1,432 @@
+use std::{
+    fmt,
+    ops::{Add, Mul, Sub, SubAssign},
+    str::FromStr,
+};
+
+use druid::Data;
+use num_bigint::{BigInt, Sign};
+use num_traits::{Signed, ToPrimitive, Zero};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// the smallest number of fractional bits kept for any value, slightly more than an f64
+const MIN_BITS: u32 = 64;
+/// limits the precision that can be reached by repeatedly offsetting a value
+const MAX_BITS: u32 = 4096;
+/// the largest decimal exponent accepted when parsing, a little past what `MAX_BITS` can resolve
+const MAX_EXPONENT: i32 = 1300;
+
+/// An arbitrary-precision fixed-point number, used for coordinates that must
+/// stay exact past the precision of an f64 (deep zooms).
+///
+/// The value is `mantissa * 2^-bits`. The precision grows as needed to exactly
+/// represent parsed decimals and f64 offsets, and an f64 approximation is kept
+/// so the common case does not need any big-number arithmetic.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BigFixed {
+    mantissa: BigInt,
+    bits: u32,
+    approx: f64,
+}
+
+/// divides by `2^shift`, rounding to the nearest integer
+fn shr_round(value: BigInt, shift: u32) -> BigInt {
+    if shift == 0 {
+        return value;
+    }
+    (value + (BigInt::from(1) << (shift - 1))) >> shift
+}
+
+impl BigFixed {
+    fn new(mantissa: BigInt, bits: u32) -> Self {
+        let approx = Self::mantissa_to_f64(&mantissa, bits);
+        BigFixed {
+            mantissa,
+            bits,
+            approx,
+        }
+    }
+
+    fn mantissa_to_f64(mantissa: &BigInt, bits: u32) -> f64 {
+        // keep only the most significant bits so the conversion can not overflow
+        let shift = mantissa.bits().saturating_sub(64) as u32;
+        let truncated = (mantissa >> shift).to_f64().unwrap_or(0.0);
+        truncated * f64::powi(2.0, shift as i32 - bits as i32)
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.approx
+    }
+
+    /// the value as the unevaluated sum `hi + lo` of two f64, for double-double arithmetic
+    pub fn to_double_double(&self) -> (f64, f64) {
+        let hi = self.approx;
+        (hi, (self - &Self::from_f64_exact(hi)).to_f64())
+    }
+
+    /// the value as a fixed-point integer with `bits` fractional bits, rounded if needed
+    pub fn mantissa_with_bits(&self, bits: u32) -> BigInt {
+        if bits >= self.bits {
+            &self.mantissa << (bits - self.bits)
+        } else {
+            shr_round(self.mantissa.clone(), self.bits - bits)
+        }
+    }
+
+    /// rounds or extends the value to exactly `bits` fractional bits
+    pub fn with_bits(&self, bits: u32) -> Self {
+        Self::new(self.mantissa_with_bits(bits), bits)
+    }
+
+    /// converts an f64 without any rounding (up to the maximum precision)
+    pub fn from_f64_exact(value: f64) -> Self {
+        if !value.is_finite() || value == 0.0 {
+            return Self::new(BigInt::zero(), MIN_BITS);
+        }
+        let raw = value.to_bits();
+        let exponent = ((raw >> 52) & 0x7ff) as i32;
+        let fraction = raw & ((1 << 52) - 1);
+        let (significand, exponent) = if exponent == 0 {
+            (fraction, -1074)
+        } else {
+            (fraction | (1 << 52), exponent - 1075)
+        };
+        // the lowest set bit decides how many fractional bits are needed
+        let exponent = exponent + significand.trailing_zeros() as i32;
+        let significand = significand >> significand.trailing_zeros();
+        let bits = (-exponent).clamp(MIN_BITS as i32, MAX_BITS as i32) as u32;
+
+        let mut mantissa = BigInt::from(significand);
+        if exponent + bits as i32 >= 0 {
+            mantissa <<= (exponent + bits as i32) as u32;
+        } else {
+            mantissa = shr_round(mantissa, (-(exponent + bits as i32)) as u32);
+        }
+        if value < 0.0 {
+            mantissa = -mantissa;
+        }
+        Self::new(mantissa, bits)
+    }
+}
+
+impl From<f64> for BigFixed {
+    /// converts the shortest decimal representation of `value`, so `0.1` stays `0.1`
+    fn from(value: f64) -> Self {
+        value
+            .to_string()
+            .parse()
+            .unwrap_or_else(|_| Self::from_f64_exact(value))
+    }
+}
+
+impl Add for &BigFixed {
+    type Output = BigFixed;
+    fn add(self, other: &BigFixed) -> BigFixed {
+        let bits = self.bits.max(other.bits);
+        BigFixed::new(
+            self.mantissa_with_bits(bits) + other.mantissa_with_bits(bits),
+            bits,
+        )
+    }
+}
+
+impl Sub for &BigFixed {
+    type Output = BigFixed;
+    fn sub(self, other: &BigFixed) -> BigFixed {
+        let bits = self.bits.max(other.bits);
+        BigFixed::new(
+            self.mantissa_with_bits(bits) - other.mantissa_with_bits(bits),
+            bits,
+        )
+    }
+}
+
+impl Mul for &BigFixed {
+    type Output = BigFixed;
+    fn mul(self, other: &BigFixed) -> BigFixed {
+        let bits = self.bits.max(other.bits);
+        let product = self.mantissa_with_bits(bits) * other.mantissa_with_bits(bits);
+        BigFixed::new(shr_round(product, bits), bits)
+    }
+}
+
+impl SubAssign<f64> for BigFixed {
+    fn sub_assign(&mut self, other: f64) {
+        *self = &*self - &BigFixed::from_f64_exact(other);
+    }
+}
+
+impl Data for BigFixed {
+    fn same(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseBigFixedError;
+
+impl fmt::Display for ParseBigFixedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid decimal number")
+    }
+}
+
+impl std::error::Error for ParseBigFixedError {}
+
+impl FromStr for BigFixed {
+    type Err = ParseBigFixedError;
+
+    /// parses decimal numbers such as `-0.75`, `1.5e-3` or `12`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (number, exponent) = match s.find(['e', 'E']) {
+            Some(index) => (
+                &s[..index],
+                s[index + 1..]
+                    .parse::<i32>()
+                    .map_err(|_| ParseBigFixedError)?,
+            ),
+            None => (s, 0),
+        };
+        if exponent.unsigned_abs() > MAX_EXPONENT as u32 {
+            return Err(ParseBigFixedError);
+        }
+        let (integer, fraction) = number.split_once('.').unwrap_or((number, ""));
+        if integer.is_empty() && fraction.is_empty()
+            || !integer
+                .chars()
+                .chain(fraction.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return Err(ParseBigFixedError);
+        }
+
+        // value = digits * 10^-decimals
+        let digits = BigInt::parse_bytes(format!("0{}{}", integer, fraction).as_bytes(), 10)
+            .ok_or(ParseBigFixedError)?;
+        let decimals = i32::try_from(fraction.len())
+            .ok()
+            .and_then(|len| len.checked_sub(exponent))
+            .ok_or(ParseBigFixedError)?;
+
+        let (mantissa, bits) = if decimals <= 0 {
+            let bits = MIN_BITS;
+            (
+                (digits * BigInt::from(10).pow((-decimals) as u32)) << bits,
+                bits,
+            )
+        } else {
+            // enough bits that the decimal survives a round trip
+            let bits = ((decimals as f64 * std::f64::consts::LOG2_10).ceil() as u32 + 4)
+                .clamp(MIN_BITS, MAX_BITS);
+            let divisor = BigInt::from(10).pow(decimals as u32);
+            let scaled = digits << bits;
+            // round to the nearest representable value
+            ((scaled + (&divisor >> 1)) / divisor, bits)
+        };
+        let mantissa = if negative { -mantissa } else { mantissa };
+        Ok(Self::new(mantissa, bits))
+    }
+}
+
+impl fmt::Display for BigFixed {
+    /// writes the shortest decimal that still identifies this value at its precision
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let max_decimals = (self.bits as f64 * std::f64::consts::LOG10_2).ceil() as u32 + 1;
+        let mut decimals = 0;
+        let mut power = BigInt::from(1);
+        let mut scaled = shr_round(self.mantissa.clone(), self.bits);
+        while decimals < max_decimals {
+            // accept when the decimal is within half of the last stored bit
+            let exact = &self.mantissa * &power;
+            let error = ((&scaled << self.bits) - exact).abs() << 1;
+            if error < power {
+                break;
+            }
+            decimals += 1;
+            power *= 10;
+            scaled = shr_round(&self.mantissa * &power, self.bits);
+        }
+
+        let digits = scaled.magnitude().to_string();
+        let digits = format!("{:0>width$}", digits, width = decimals as usize + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - decimals as usize);
+        if scaled.sign() == Sign::Minus {
+            write!(f, "-")?;
+        }
+        if fraction.is_empty() {
+            write!(f, "{}", integer)
+        } else {
+            write!(f, "{}.{}", integer, fraction)
+        }
+    }
+}
+
+impl Serialize for BigFixed {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for BigFixed {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BigFixedVisitor;
+
+        impl<'de> de::Visitor<'de> for BigFixedVisitor {
+            type Value = BigFixed;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a number or a string containing a decimal number")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<BigFixed, E> {
+                value.parse().map_err(E::custom)
+            }
+
+            fn visit_f64<E: de::Error>(self, value: f64) -> Result<BigFixed, E> {
+                Ok(BigFixed::from(value))
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<BigFixed, E> {
+                Ok(BigFixed::from(value as f64))
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<BigFixed, E> {
+                Ok(BigFixed::from(value as f64))
+            }
+        }
+
+        deserializer.deserialize_any(BigFixedVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> BigFixed {
+        s.parse().unwrap()
+    }
+
+    /// `1` followed by a `digit` in the 100th decimal place
+    fn one_plus_tiny(digit: u32) -> String {
+        format!("1.{}{}", "0".repeat(99), digit)
+    }
+
+    #[test]
+    fn decimals_round_trip() {
+        for s in [
+            "0",
+            "1",
+            "12",
+            "0.1",
+            "-0.75",
+            "3.141592653589793238462643383279502884197",
+            "-0.743643887037158704752191506114774",
+            &one_plus_tiny(1),
+        ] {
+            assert_eq!(parse(s).to_string(), s);
+        }
+    }
+
+    #[test]
+    fn exponents_are_parsed() {
+        assert_eq!(parse("1.5e-3"), parse("0.0015"));
+        assert_eq!(parse("2.5E2").to_string(), "250");
+        let tiny = parse("1e-100");
+        assert_eq!(tiny.to_string(), format!("0.{}1", "0".repeat(99)));
+        assert_eq!(parse(&tiny.to_string()), tiny);
+    }
+
+    #[test]
+    fn signs() {
+        assert_eq!(parse("+2.5"), parse("2.5"));
+        assert_eq!(parse("-2.5").to_f64(), -2.5);
+        assert_eq!(parse("-0").to_string(), "0");
+        assert_eq!(
+            parse("-1e-100").to_string(),
+            format!("-0.{}1", "0".repeat(99))
+        );
+        assert_eq!(&parse("-0.25") + &parse("0.25"), parse("0"));
+        assert_eq!((&parse("-1.5") * &parse("-2")).to_string(), "3");
+        assert_eq!((&parse("-1.5") * &parse("2")).to_string(), "-3");
+    }
+
+    #[test]
+    fn invalid_numbers_are_rejected() {
+        for s in ["", "-", ".", "abc", "1.2.3", "1e", "1e1.5", "--1", "0x10"] {
+            assert!(s.parse::<BigFixed>().is_err(), "{:?} parsed", s);
+        }
+    }
+
+    #[test]
+    fn extreme_exponents_are_rejected() {
+        for s in [
+            "1e-999999999",
+            "1e999999999",
+            "1e-2147483648",
+            "1e2147483647",
+            "1e-1301",
+            "1e1301",
+        ] {
+            assert!(s.parse::<BigFixed>().is_err(), "{:?} parsed", s);
+        }
+        // the largest accepted exponents still parse
+        assert!("1e-1300".parse::<BigFixed>().is_ok());
+        assert_eq!(
+            parse("1e1300").to_string(),
+            format!("1{}", "0".repeat(1300))
+        );
+    }
+
+    #[test]
+    fn f64_conversions() {
+        assert_eq!(BigFixed::from(0.1).to_string(), "0.1");
+        let value = 1.0 / 3.0;
+        assert_eq!(BigFixed::from_f64_exact(value).to_f64(), value);
+        let (hi, lo) = parse(&one_plus_tiny(0)).to_double_double();
+        assert_eq!((hi, lo), (1.0, 0.0));
+        let (hi, lo) = parse("0.1").to_double_double();
+        assert_eq!(hi, 0.1);
+        // the error of 0.1 as an f64, to the 64 bits that 0.1 is parsed with
+        assert!((lo + 5.551115123125783e-18).abs() < 1e-19);
+    }
+
+    #[test]
+    fn products_keep_precision_at_1e_100() {
+        // parsed decimals are as precise as their digits, so the trailing zeros
+        // make these precise enough for their product
+        let a = parse(&format!("1.5{}e-50", "0".repeat(100)));
+        let b = parse(&format!("2.0{}e-50", "0".repeat(100)));
+        let product = parse(&format!("3.0{}e-100", "0".repeat(100)));
+        let error = (&(&a * &b) - &product).to_f64().abs();
+        assert!(error < 1e-140, "error {}", error);
+
+        // (1 + 1e-100)^2 = 1 + 2e-100 + 1e-200, where the last term is below the precision
+        let a = parse(&one_plus_tiny(1));
+        assert_eq!((&a * &a).to_string(), one_plus_tiny(2));
+        assert_eq!(
+            (&a * &parse("3")).to_string(),
+            format!("3.{}3", "0".repeat(99))
+        );
+    }
+
+    #[test]
+    fn shifting_precision() {
+        let value = parse(&one_plus_tiny(5));
+        // extending keeps the value, rounding drops the tiny part
+        assert_eq!((&value.with_bits(1000) - &value).to_string(), "0");
+        assert_eq!(value.with_bits(64).to_string(), "1");
+        assert_eq!(
+            parse("0.75").mantissa_with_bits(2),
+            BigInt::from(3),
+            "0.75 = 3 * 2^-2"
+        );
+        // 0.625 = 5 * 2^-3 rounds to 3 * 2^-2
+        assert_eq!(parse("0.625").mantissa_with_bits(2), BigInt::from(3));
+    }
+}
@@ -0,0 +1,449 @@
+use std::simd::Simd;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use druid::{Data, Lens};
+use serde::{Deserialize, Serialize};
+
+use super::image_generator::{GeneratorParameters, Pixel, PrepareContext, CHANNELS, LANES};
+use super::mandel::bulb_period;
+
+/// the number of orbits sampled from one seed, the unit of work shared between threads
+const CHUNK_SAMPLES: usize = 1 << 14;
+/// the chance of each sample to jump to a new point, instead of moving to one close to
+/// the current point
+const JUMP_CHANCE: f64 = 0.2;
+/// the share of the jumps that land in the view instead of anywhere in the square
+const VIEW_JUMP_CHANCE: f64 = 0.5;
+/// the largest distance of the moves to close points, relative to the width of the view
+const STEP_SIZE: f64 = 0.1;
+
+/// The Buddhabrot: the density of the orbits of `z = z^2 + c` for random values of c,
+/// plotted into a histogram instead of coloring each pixel on its own.
+///
+/// Each color channel plots the orbits that escape within its own iteration limit
+/// (the Nebulabrot), or with `anti` the orbits that do not escape (the Anti-Buddhabrot).
+#[derive(Clone, PartialEq, Data, Lens, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BuddhabrotParameters {
+    // image parameters
+    pub zoom: f64,
+    pub offset_x: f64,
+    pub offset_y: f64,
+    /// the number of orbits sampled for every pixel of the image
+    pub samples: f64,
+    pub seed: u64,
+    pub anti: bool,
+    pub red_iter: usize,
+    pub green_iter: usize,
+    pub blue_iter: usize,
+    // colors
+    pub exposure: f64,
+    pub gamma: f64,
+    // the density of orbits over the whole image, filled in by `prepare`
+    #[data(ignore)]
+    #[lens(ignore)]
+    #[serde(skip)]
+    histogram: Option<Arc<Histogram>>,
+}
+
+/// weighted orbit counts for every pixel of the full size image, one per color channel
+#[derive(PartialEq)]
+struct Histogram {
+    counts: Vec<[f32; 3]>,
+    width: usize,
+    height: usize,
+    /// the average count of each channel, which the tone mapping is relative to
+    mean: [f64; 3],
+}
+
+/// a small deterministic random number generator (SplitMix64), so the same seed
+/// always samples the same orbits
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// a uniformly distributed value in `[0, 1)`
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl BuddhabrotParameters {
+    fn limits(&self) -> [usize; 3] {
+        [self.red_iter, self.green_iter, self.blue_iter]
+    }
+
+    /// iterates the orbit of c and collects the histogram entries of the points that
+    /// are in the view, together with the channel that plots them
+    fn trace(
+        &self,
+        (c_real, c_imag): (f64, f64),
+        (width, height): (usize, usize),
+        (scale, scale_y): (f64, f64),
+        orbit: &mut Vec<(f64, f64)>,
+        hits: &mut Vec<(usize, usize)>,
+    ) {
+        hits.clear();
+        // the points are only sampled from the square around the whole set
+        if c_real.abs() > 2.0 || c_imag.abs() > 2.0 {
+            return;
+        }
+        if !self.anti && bulb_period(Simd::splat(c_real), Simd::splat(c_imag))[0] != 0.0 {
+            return;
+        }
+        let limits = self.limits();
+        let max_iter = limits.iter().copied().max().unwrap_or(0);
+
+        // iterate, according to z = z^2 + c, recording the orbit
+        orbit.clear();
+        let (mut z_real, mut z_imag) = (0.0, 0.0);
+        let mut escaped = false;
+        for _step in 0..max_iter {
+            let z_real_tmp = z_real * z_real - z_imag * z_imag + c_real;
+            z_imag = 2.0 * z_real * z_imag + c_imag;
+            z_real = z_real_tmp;
+            orbit.push((z_real, z_imag));
+            if z_real * z_real + z_imag * z_imag > 4.0 {
+                escaped = true;
+                break;
+            }
+        }
+
+        for (channel, &limit) in limits.iter().enumerate() {
+            // the part of the orbit that this channel plots, if any
+            let plotted = if self.anti {
+                if escaped && orbit.len() <= limit {
+                    continue;
+                }
+                &orbit[..limit.min(orbit.len())]
+            } else {
+                if !escaped || orbit.len() > limit {
+                    continue;
+                }
+                &orbit[..]
+            };
+            for &(real, imag) in plotted {
+                let x = ((real - self.offset_x) / scale + 0.5) * width as f64;
+                let y = ((imag - self.offset_y) / scale_y + 0.5) * height as f64;
+                if x >= 0.0 && y >= 0.0 && x < width as f64 && y < height as f64 {
+                    hits.push((x as usize + y as usize * width, channel));
+                }
+            }
+        }
+    }
+
+    /// samples every orbit of chunk `chunk` and adds the visible points to `counts`
+    ///
+    /// The points are sampled by a Metropolis-Hastings chain, which mostly moves to points
+    /// close to its current one and only sometimes jumps to a new one, and which prefers
+    /// the points with more visible hits. Zoomed in views, which nearly all orbits miss,
+    /// get about as many plotted samples as the whole set does.
+    fn plot_chunk(
+        &self,
+        chunk: usize,
+        samples: usize,
+        counts: &mut [[f32; 3]],
+        (width, height): (usize, usize),
+        orbit: &mut Vec<(f64, f64)>,
+    ) {
+        let scale = f64::powf(2.0, -self.zoom);
+        let scale_y = scale * (height as f64 / width as f64);
+        let step_size = scale * STEP_SIZE;
+        let mut random = SplitMix64(self.seed ^ (chunk as u64).wrapping_mul(0xD1B5_4A32_D192_ED03));
+        let in_view = |(c_real, c_imag): (f64, f64)| {
+            (c_real - self.offset_x).abs() < scale * 0.5
+                && (c_imag - self.offset_y).abs() < scale_y * 0.5
+        };
+        // the density of the jumps at c, which land in the square around the set or, since
+        // the first point of every orbit is c itself, in the view
+        let jump_density = |c| {
+            (1.0 - VIEW_JUMP_CHANCE) / 16.0
+                + if in_view(c) {
+                    VIEW_JUMP_CHANCE / (scale * scale_y)
+                } else {
+                    0.0
+                }
+        };
+
+        // the chain starts at the first jump that has any hits
+        let mut current = None;
+        let mut current_hits = Vec::new();
+        let mut candidate_hits = Vec::new();
+        for _sample in 0..samples {
+            let (candidate, jumped) = match current {
+                Some((c_real, c_imag)) if random.next_f64() >= JUMP_CHANCE => (
+                    (
+                        c_real + (random.next_f64() * 2.0 - 1.0) * step_size,
+                        c_imag + (random.next_f64() * 2.0 - 1.0) * step_size,
+                    ),
+                    false,
+                ),
+                _ if random.next_f64() < VIEW_JUMP_CHANCE => (
+                    (
+                        self.offset_x + (random.next_f64() - 0.5) * scale,
+                        self.offset_y + (random.next_f64() - 0.5) * scale_y,
+                    ),
+                    true,
+                ),
+                _ => (
+                    (random.next_f64() * 4.0 - 2.0, random.next_f64() * 4.0 - 2.0),
+                    true,
+                ),
+            };
+            self.trace(
+                candidate,
+                (width, height),
+                (scale, scale_y),
+                orbit,
+                &mut candidate_hits,
+            );
+            // move with a chance of the hits of the candidate relative to those of the
+            // current point, which samples the points in proportion to their hits. The
+            // moves to close points are as likely one way as the other, the jumps are
+            // weighed by how likely they are to land on either point.
+            let (candidate_density, current_density) = match current {
+                Some(current) if jumped => (jump_density(candidate), jump_density(current)),
+                _ => (1.0, 1.0),
+            };
+            if !candidate_hits.is_empty()
+                && random.next_f64() * (current_hits.len() as f64 * candidate_density)
+                    <= candidate_hits.len() as f64 * current_density
+            {
+                current = Some(candidate);
+                std::mem::swap(&mut current_hits, &mut candidate_hits);
+            }
+            if current_hits.is_empty() {
+                continue;
+            }
+            // each sample adds a weight of 1 spread over its hits, which undoes that
+            // preference: the density is the same as with uniformly sampled points,
+            // without the ones that miss the view
+            let weight = 1.0 / current_hits.len() as f32;
+            for &(index, channel) in current_hits.iter() {
+                counts[index][channel] += weight;
+            }
+        }
+    }
+}
+
+impl GeneratorParameters for BuddhabrotParameters {
+    /// the density of each channel, relative to its average
+    type Intermediate = [[f64; LANES]; 3];
+    /// looks up the density for LANES number of pixels from `[i,j]` to `[i,j+LANES]` of the image
+    fn calc_pixel_row(
+        &self,
+        _width: usize,
+        height: usize,
+        (i, j): (usize, usize),
+        step: usize,
+    ) -> Self::Intermediate {
+        let histogram = match &self.histogram {
+            Some(histogram) => histogram,
+            None => return [[0.0; LANES]; 3],
+        };
+        // the histogram has an entry for every pixel of the unpadded image: the padding
+        // at the end of the rows is left empty, and the samples of a supersampled image
+        // (which only differs from the histogram by a whole number of samples per pixel)
+        // use the entry of their pixel
+        let samples = (height / histogram.height).max(1);
+        let y = j / samples;
+        std::array::from_fn(|channel| {
+            std::array::from_fn(|v| {
+                let x = (i + v * step) / samples;
+                if x >= histogram.width || y >= histogram.height {
+                    return 0.0;
+                }
+                let count = histogram.counts[x + y * histogram.width][channel];
+                count as f64 / histogram.mean[channel]
+            })
+        })
+    }
+
+    fn shade_pixel_row(&self, parameters: Self::Intermediate) -> [Pixel; LANES] {
+        let mut row: [Pixel; LANES] = [[0.0; CHANNELS]; LANES];
+        for v in 0..LANES {
+            for channel in 0..3 {
+                let density = parameters[channel][v];
+                // exponential tone mapping, which compresses the very bright areas of the
+                // image while keeping faint orbits visible
+                let value = (1.0 - (-density * self.exposure * 0.1).exp()).powf(1.0 / self.gamma);
+                row[v][channel] = value.clamp(0.0, 1.0) as f32;
+            }
+            row[v][3] = 1.0;
+        }
+        row
+    }
+
+    fn needs_recompute(settings: &Self, old_settings: &Self) -> bool {
+        settings.zoom != old_settings.zoom
+            || settings.offset_y != old_settings.offset_y
+            || settings.offset_x != old_settings.offset_x
+            || settings.samples != old_settings.samples
+            || settings.seed != old_settings.seed
+            || settings.anti != old_settings.anti
+            || settings.red_iter != old_settings.red_iter
+            || settings.green_iter != old_settings.green_iter
+            || settings.blue_iter != old_settings.blue_iter
+    }
+
+    /// samples all orbits into the histogram, with each worker plotting into its own
+    /// histogram to avoid contention, which are added together once it runs out of work
+    fn prepare(&mut self, width: usize, height: usize, context: &PrepareContext) {
+        let total_samples = (self.samples * (width * height) as f64) as usize;
+        let chunks = total_samples.div_ceil(CHUNK_SAMPLES);
+        let next_chunk = Arc::new(AtomicUsize::new(0));
+        let merged = Arc::new(Mutex::new(vec![[0.0f32; 3]; width * height]));
+
+        let settings = self.clone();
+        let shared = (next_chunk, merged.clone(), context.clone());
+        context.run_on_workers(move || {
+            let (next_chunk, merged, context) = &shared;
+            // only allocated once there is a chunk to plot, workers that start
+            // after all chunks were taken leave the histogram as it is
+            let mut counts = None;
+            let mut orbit = Vec::new();
+            loop {
+                // chunks are seeded by their index, so the image does not depend
+                // on the number of threads or which thread takes which chunk
+                let chunk = next_chunk.fetch_add(1, Ordering::Relaxed);
+                if chunk >= chunks || context.is_canceled() {
+                    break;
+                }
+                let samples = CHUNK_SAMPLES.min(total_samples - chunk * CHUNK_SAMPLES);
+                settings.plot_chunk(
+                    chunk,
+                    samples,
+                    counts.get_or_insert_with(|| vec![[0.0f32; 3]; width * height]),
+                    (width, height),
+                    &mut orbit,
+                );
+                context.set_progress(chunk as f64 / chunks as f64);
+            }
+            if let Some(counts) = counts {
+                let mut merged = merged
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                for (total, count) in merged.iter_mut().zip(counts.iter()) {
+                    for channel in 0..3 {
+                        total[channel] += count[channel];
+                    }
+                }
+            }
+        });
+
+        let counts = std::mem::take(
+            &mut *merged
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        );
+        let mut mean = [0.0; 3];
+        for count in counts.iter() {
+            for channel in 0..3 {
+                mean[channel] += count[channel] as f64;
+            }
+        }
+        // keep empty channels at a density of 0 instead of dividing by 0
+        let mean = mean.map(|sum| (sum / counts.len().max(1) as f64).max(f64::MIN_POSITIVE));
+        self.histogram = Some(Arc::new(Histogram {
+            counts,
+            width,
+            height,
+            mean,
+        }));
+    }
+}
+
+impl Default for BuddhabrotParameters {
+    fn default() -> Self {
+        Self {
+            zoom: -1.5,
+            offset_x: -0.5,
+            offset_y: 0.0,
+            samples: 10.0,
+            seed: 0,
+            anti: false,
+            red_iter: 5000,
+            green_iter: 500,
+            blue_iter: 50,
+            exposure: 2.0,
+            gamma: 1.0,
+            histogram: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn padded_rows_use_the_histogram_entry_of_their_pixel() {
+        let (width, height) = (5, 2);
+        let counts = (0..width * height).map(|index| [index as f32; 3]).collect();
+        let parameters = BuddhabrotParameters {
+            histogram: Some(Arc::new(Histogram {
+                counts,
+                width,
+                height,
+                mean: [1.0; 3],
+            })),
+            ..BuddhabrotParameters::default()
+        };
+        // the image is padded to a whole number of lanes
+        let buffer_width = width + (LANES - width % LANES);
+        for j in 0..height {
+            for i in (0..buffer_width).step_by(LANES) {
+                let densities = parameters.calc_pixel_row(buffer_width, height, (i, j), 1)[0];
+                for (v, &density) in densities.iter().enumerate() {
+                    let x = i + v;
+                    let expected = if x < width {
+                        (x + j * width) as f64
+                    } else {
+                        0.0
+                    };
+                    assert_eq!(density, expected, "pixel ({}, {})", x, j);
+                }
+            }
+        }
+        // the samples of a supersampled image use their pixel's entry
+        let densities = parameters.calc_pixel_row(buffer_width * 3, height * 3, (9, 3), 1)[0];
+        assert_eq!(densities[0], (3 + width) as f64);
+    }
+
+    #[test]
+    fn zoomed_in_views_plot_most_samples() {
+        // a view a thousandth of the size of the set, which nearly all uniformly
+        // sampled orbits miss
+        let parameters = BuddhabrotParameters {
+            zoom: 8.0,
+            offset_x: -0.75,
+            offset_y: 0.1,
+            ..BuddhabrotParameters::default()
+        };
+        let (width, height) = (32, 32);
+        let mut counts = vec![[0.0f32; 3]; width * height];
+        parameters.plot_chunk(
+            0,
+            CHUNK_SAMPLES,
+            &mut counts,
+            (width, height),
+            &mut Vec::new(),
+        );
+        // every plotted sample adds a weight of 1
+        let plotted: f32 = counts.iter().flatten().sum();
+        assert!(
+            plotted > 0.9 * CHUNK_SAMPLES as f32,
+            "{} of {} samples plotted",
+            plotted,
+            CHUNK_SAMPLES
+        );
+    }
+}
@@ -0,0 +1,322 @@
+use std::f64::consts::PI;
+use std::simd::{Simd, SimdFloat, SimdPartialOrd, StdFloat};
+use std::str::FromStr;
+
+use druid::{Data, Lens};
+use serde::{Deserialize, Serialize};
+
+use super::image_generator::{GeneratorParameters, Pixel, SimdF64, CHANNELS, LANES};
+use super::utilities::{hsl2rgb, lane_coordinates, pixel_distances};
+
+/// the members of the Burning Ship family, which all iterate `z = z^2 + c`
+/// with `abs()` applied to some parts of z
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Data, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShipVariant {
+    /// `z = (|x| + i|y|)^2 + c`
+    BurningShip,
+    /// `z = |x^2 - y^2| + 2xyi + c`
+    Celtic,
+    /// `z = |x^2 - y^2| + |2xy|i + c`
+    Buffalo,
+    /// `z = (|x| - iy)^2 + c`
+    Perpendicular,
+}
+
+/// which parts of z are folded by `abs()` (or negated) before and after squaring
+struct Folds {
+    abs_x: bool,
+    abs_y: bool,
+    neg_y: bool,
+    abs_real: bool,
+    abs_imag: bool,
+}
+
+impl ShipVariant {
+    fn folds(self) -> Folds {
+        let none = Folds {
+            abs_x: false,
+            abs_y: false,
+            neg_y: false,
+            abs_real: false,
+            abs_imag: false,
+        };
+        match self {
+            ShipVariant::BurningShip => Folds {
+                abs_x: true,
+                abs_y: true,
+                ..none
+            },
+            ShipVariant::Celtic => Folds {
+                abs_real: true,
+                ..none
+            },
+            ShipVariant::Buffalo => Folds {
+                abs_real: true,
+                abs_imag: true,
+                ..none
+            },
+            ShipVariant::Perpendicular => Folds {
+                abs_x: true,
+                neg_y: true,
+                ..none
+            },
+        }
+    }
+}
+
+impl FromStr for ShipVariant {
+    type Err = String;
+
+    /// parses the same lowercase names that are used in scene files
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "burningship" => Ok(ShipVariant::BurningShip),
+            "celtic" => Ok(ShipVariant::Celtic),
+            "buffalo" => Ok(ShipVariant::Buffalo),
+            "perpendicular" => Ok(ShipVariant::Perpendicular),
+            _ => Err(format!("unknown variant '{}'", s)),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Data, Lens, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BurningShipParameters {
+    // image parameters
+    pub max_iter: usize,
+    pub zoom: f64,
+    pub offset_x: f64,
+    pub offset_y: f64,
+    pub variant: ShipVariant,
+    // colors
+    pub saturation: f64,
+    pub color_frequency: f64,
+    pub color_offset: f64,
+    pub glow_spread: f64,
+    pub glow_strength: f64,
+    pub brightness: f64,
+    pub internal_brightness: f64,
+}
+
+impl GeneratorParameters for BurningShipParameters {
+    type Intermediate = [[f64; LANES]; 4];
+    /// calculates the color for LANES number of pixels from `[i,j]` to `[i,j+LANES]` of the image
+    fn calc_pixel_row(
+        &self,
+        width: usize,
+        height: usize,
+        (i, j): (usize, usize),
+        step: usize,
+    ) -> Self::Intermediate {
+        let mm_ones: SimdF64 = Simd::splat(1.0);
+        let mm_zero: SimdF64 = Simd::splat(0.0);
+        let folds = self.variant.folds();
+        // initialize values
+        let scale = f64::powf(2.0, -self.zoom);
+
+        // c: complex number
+        let c_real = lane_coordinates(i, step, width, scale, self.offset_x);
+
+        let c_imag = Simd::splat(
+            (j as f64 / height as f64 - 0.5) * scale * (height as f64 / width as f64)
+                + self.offset_y,
+        );
+
+        // z: complex number
+        let mut z_real = mm_zero;
+        let mut z_imag = mm_zero;
+
+        // z': complex running derivative
+        let mut z_prime_r = mm_ones;
+        let mut z_prime_i = mm_zero;
+
+        // z^2: temporary value for optimized computation
+        let mut real_2 = mm_zero;
+        let mut imag_2 = mm_zero;
+
+        // value accumulators for coloring
+        let mut step_acc = mm_zero;
+        let mut orbit_acc = mm_ones;
+
+        for _step in 0..self.max_iter {
+            // iterate values, according to z = fold(fold(z)^2) + c
+            //
+            // folding only flips signs, so the squares of z are unchanged and the
+            // derivative is folded by the same signs as the value it belongs to:
+            //   w = fold(z), w' = sign * z'
+            //   z' = fold(2 * w * w') + 1
+
+            // fold z before squaring
+            let sign_x = if folds.abs_x {
+                z_real.signum()
+            } else {
+                mm_ones
+            };
+            let sign_y = if folds.abs_y {
+                z_imag.signum()
+            } else if folds.neg_y {
+                -mm_ones
+            } else {
+                mm_ones
+            };
+            let w_real = z_real * sign_x;
+            let w_imag = z_imag * sign_y;
+            let w_prime_r = z_prime_r * sign_x;
+            let w_prime_i = z_prime_i * sign_y;
+
+            // square, then fold the result
+            let square_real = real_2 - imag_2;
+            let square_imag = (w_real + w_real) * w_imag;
+            let sign_real = if folds.abs_real {
+                square_real.signum()
+            } else {
+                mm_ones
+            };
+            let sign_imag = if folds.abs_imag {
+                square_imag.signum()
+            } else {
+                mm_ones
+            };
+
+            let z_real_tmp = square_real * sign_real + c_real;
+            let z_imag_tmp = square_imag * sign_imag + c_imag;
+
+            // intermediate values for z'
+            let ac_bd = w_real * w_prime_r - w_imag * w_prime_i;
+            let bc_da = w_imag * w_prime_r + w_real * w_prime_i;
+
+            let z_prime_r_tmp = (ac_bd + ac_bd) * sign_real + mm_ones;
+            let z_prime_i_tmp = (bc_da + bc_da) * sign_imag;
+
+            let radius_2 = real_2 + imag_2;
+
+            // select lanes which have not escaped
+            // escape of 1000.0 used to smooth distance estimate
+            let mask = radius_2.simd_lt(Simd::splat(1000.0));
+
+            // conditionally iterate, only if the pixel has not escaped
+            z_real = mask.select(z_real_tmp, z_real);
+            z_imag = mask.select(z_imag_tmp, z_imag);
+            z_prime_i = mask.select(z_prime_i_tmp, z_prime_i);
+            z_prime_r = mask.select(z_prime_r_tmp, z_prime_r);
+
+            real_2 = z_real * z_real;
+            imag_2 = z_imag * z_imag;
+
+            step_acc = mask.select(mm_ones, mm_zero) + step_acc;
+            orbit_acc = orbit_acc.simd_min(real_2 + imag_2);
+
+            // finish if all pixels have escaped
+            if !mask.any() {
+                break;
+            }
+        }
+
+        // calculate the absolute value (radius) of z for distance estimation
+        let r = (real_2 + imag_2).sqrt();
+        let dr = (z_prime_r * z_prime_r + z_prime_i * z_prime_i).sqrt();
+
+        // extract values necessary for coloring
+        let extracted_step = step_acc.to_array();
+        let extracted_dr = dr.to_array();
+        let extracted_r = r.to_array();
+        let extracted_orbit = orbit_acc.sqrt().to_array();
+
+        [extracted_step, extracted_r, extracted_dr, extracted_orbit]
+    }
+
+    fn shade_pixel_row(&self, parameters: Self::Intermediate) -> [Pixel; LANES] {
+        let scale = f64::powf(2.0, -self.zoom);
+        let mut row: [Pixel; LANES] = [[0.0; CHANNELS]; LANES];
+        for v in 0..LANES {
+            let step = parameters[0][v];
+            let r = parameters[1][v];
+            let dr = parameters[2][v];
+            let orbit = parameters[3][v];
+            // distance estimation: 0.5 * log(r) * r/dr
+            let dist_est = 0.5 * (r).ln() * r / dr;
+            // a 'glow' effect based on distance (manually adjusted to taste and to adjust to zoom level)
+            let glow = (-(dist_est / scale).ln() + self.glow_spread) * self.glow_strength * 0.1;
+            // a smoothed version of the iteration count: i + (1 - ln(ln(r))/ln(2))
+            let smoothed_step = step + (1.0 - ((r).ln()).ln() / f64::ln(2.0));
+
+            if step as usize >= self.max_iter {
+                // color the inside using orbit trap method
+                row[v] = hsl2rgb(
+                    0.0,
+                    0.0,
+                    ((orbit)
+                        * self.brightness
+                        * self.internal_brightness
+                        * self.internal_brightness)
+                        .clamp(0.0, 1.0),
+                )
+            } else {
+                // color the outside
+                row[v] = hsl2rgb(
+                    // color hue based on an sinusoidal step counter, offset to a [0,1] range
+                    (((smoothed_step.ln() * self.color_frequency - self.color_offset * 2.0 * PI)
+                        .sin())
+                        * 0.5
+                        + 0.5)
+                        .clamp(0.0, 1.0),
+                    // saturation decreased when glow is high to hide noise when hue oscillates quickly
+                    (self.saturation * (1.0 - (glow * glow))).clamp(0.0, 1.0),
+                    // use glow around edges for brightness
+                    (glow * self.brightness).clamp(0.0, 1.0),
+                )
+            }
+        }
+        row
+    }
+
+    fn escape_steps(&self, parameters: &Self::Intermediate) -> Option<[f64; LANES]> {
+        Some(parameters[0])
+    }
+
+    fn interior(&self, parameters: &Self::Intermediate) -> Option<[bool; LANES]> {
+        Some(parameters[0].map(|step| step as usize >= self.max_iter))
+    }
+
+    fn boundary_distances(
+        &self,
+        width: usize,
+        parameters: &Self::Intermediate,
+    ) -> Option<[f64; LANES]> {
+        let scale = f64::powf(2.0, -self.zoom);
+        Some(pixel_distances(
+            [parameters[0], parameters[1], parameters[2]],
+            self.max_iter,
+            scale,
+            width,
+        ))
+    }
+
+    fn needs_recompute(settings: &Self, old_settings: &Self) -> bool {
+        settings.max_iter != old_settings.max_iter
+            || settings.zoom != old_settings.zoom
+            || settings.offset_y != old_settings.offset_y
+            || settings.offset_x != old_settings.offset_x
+            || settings.variant != old_settings.variant
+    }
+}
+
+impl Default for BurningShipParameters {
+    fn default() -> Self {
+        Self {
+            max_iter: 250,
+            zoom: -2.0,
+            offset_x: -0.4,
+            offset_y: -0.5,
+            variant: ShipVariant::BurningShip,
+            saturation: 1.0,
+            color_frequency: 1.0,
+            color_offset: 0.0,
+            glow_spread: 1.0,
+            glow_strength: 1.0,
+            brightness: 2.0,
+            internal_brightness: 1.0,
+        }
+    }
+}
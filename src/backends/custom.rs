@@ -0,0 +1,240 @@
+use std::f64::consts::PI;
+use std::simd::{Simd, SimdFloat, StdFloat};
+
+use druid::{Data, Lens};
+use serde::{Deserialize, Serialize};
+
+use super::expression::{Condition, DualSimd, Expression};
+use super::image_generator::{GeneratorParameters, Pixel, SimdF64, CHANNELS, LANES};
+use super::utilities::{hsl2rgb, lane_coordinates, pixel_distances};
+
+/// a fractal iterating a user-defined formula `z = f(z, c)` until the bailout condition
+/// holds, with the derivative for the distance estimate found by automatic differentiation.
+///
+/// In the parameter plane c is the pixel and z starts at `start`, which may depend on c;
+/// in the Julia plane z starts at the pixel and c is constant.
+#[derive(Clone, PartialEq, Data, Lens, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CustomParameters {
+    // image parameters
+    pub max_iter: usize,
+    pub zoom: f64,
+    pub offset_x: f64,
+    pub offset_y: f64,
+    pub formula: Expression,
+    pub start: Expression,
+    pub bailout: Condition,
+    pub julia: bool,
+    pub constant_real: f64,
+    pub constant_imag: f64,
+    // colors
+    pub saturation: f64,
+    pub color_frequency: f64,
+    pub color_offset: f64,
+    pub glow_spread: f64,
+    pub glow_strength: f64,
+    pub brightness: f64,
+    pub internal_brightness: f64,
+}
+
+impl GeneratorParameters for CustomParameters {
+    /// `[step, r, dr, orbit, r_prev]`, where `r_prev` is the radius one step before
+    /// escaping, used to estimate the degree of the formula for smoothing
+    type Intermediate = [[f64; LANES]; 5];
+    /// calculates the color for LANES number of pixels from `[i,j]` to `[i,j+LANES]` of the image
+    fn calc_pixel_row(
+        &self,
+        width: usize,
+        height: usize,
+        (i, j): (usize, usize),
+        step: usize,
+    ) -> Self::Intermediate {
+        let mm_ones: SimdF64 = Simd::splat(1.0);
+        let mm_zero: SimdF64 = Simd::splat(0.0);
+        // initialize values
+        let scale = f64::powf(2.0, -self.zoom);
+
+        // the pixel, as a complex number
+        let pixel_real = lane_coordinates(i, step, width, scale, self.offset_x);
+
+        let pixel_imag = Simd::splat(
+            (j as f64 / height as f64 - 0.5) * scale * (height as f64 / width as f64)
+                + self.offset_y,
+        );
+
+        // c and z, along with their derivatives with respect to the pixel
+        let (c, mut z) = if self.julia {
+            (
+                DualSimd::constant(
+                    Simd::splat(self.constant_real),
+                    Simd::splat(self.constant_imag),
+                ),
+                DualSimd::variable(pixel_real, pixel_imag),
+            )
+        } else {
+            let c = DualSimd::variable(pixel_real, pixel_imag);
+            (
+                c,
+                self.start
+                    .evaluate(&DualSimd::constant(mm_zero, mm_zero), &c),
+            )
+        };
+
+        // value accumulators for coloring
+        let mut step_acc = mm_zero;
+        let mut orbit_acc = mm_ones;
+        let mut r_prev = mm_zero;
+
+        for _step in 0..self.max_iter {
+            // select lanes which have not reached the bailout condition
+            let mask = !self.bailout.evaluate(&z, &c);
+
+            // finish if all pixels have escaped
+            if !mask.any() {
+                break;
+            }
+
+            // iterate values, according to z = f(z, c), which also carries z'
+            let z_tmp = self.formula.evaluate(&z, &c);
+
+            // conditionally iterate, only if the pixel has not escaped
+            r_prev = mask.select(z.norm_2().sqrt(), r_prev);
+            z = DualSimd::select(mask, z_tmp, z);
+
+            step_acc = mask.select(mm_ones, mm_zero) + step_acc;
+            orbit_acc = orbit_acc.simd_min(z.norm_2());
+        }
+
+        // calculate the absolute value (radius) of z for distance estimation
+        let r = z.norm_2().sqrt();
+        let dr = z.derivative_abs();
+
+        // extract values necessary for coloring
+        let extracted_step = step_acc.to_array();
+        let extracted_dr = dr.to_array();
+        let extracted_r = r.to_array();
+        let extracted_orbit = orbit_acc.sqrt().to_array();
+        let extracted_r_prev = r_prev.to_array();
+
+        [
+            extracted_step,
+            extracted_r,
+            extracted_dr,
+            extracted_orbit,
+            extracted_r_prev,
+        ]
+    }
+
+    fn shade_pixel_row(&self, parameters: Self::Intermediate) -> [Pixel; LANES] {
+        let scale = f64::powf(2.0, -self.zoom);
+        let mut row: [Pixel; LANES] = [[0.0; CHANNELS]; LANES];
+        for v in 0..LANES {
+            let step = parameters[0][v];
+            let r = parameters[1][v];
+            let dr = parameters[2][v];
+            let orbit = parameters[3][v];
+            let r_prev = parameters[4][v];
+            // distance estimation: 0.5 * log(r) * r/dr
+            let dist_est = 0.5 * (r).ln() * r / dr;
+            // a 'glow' effect based on distance (manually adjusted to taste and to adjust to zoom level)
+            let glow = (-(dist_est / scale).ln() + self.glow_spread) * self.glow_strength * 0.1;
+            // the degree of the formula is unknown, so it is estimated from how quickly
+            // the radius grew in the last step: r = r_prev^d
+            let degree = (r.ln() / r_prev.ln()).abs();
+            let degree_ln = if degree.is_finite() && degree > 1.0 {
+                degree.ln()
+            } else {
+                f64::ln(2.0)
+            };
+            // a smoothed version of the iteration count: i + (1 - ln(ln(r))/ln(d))
+            let smoothed_step = step + (1.0 - ((r).ln()).ln() / degree_ln);
+
+            if step as usize >= self.max_iter {
+                // color the inside using orbit trap method
+                row[v] = hsl2rgb(
+                    0.0,
+                    0.0,
+                    ((orbit)
+                        * self.brightness
+                        * self.internal_brightness
+                        * self.internal_brightness)
+                        .clamp(0.0, 1.0),
+                )
+            } else {
+                // color the outside
+                row[v] = hsl2rgb(
+                    // color hue based on an sinusoidal step counter, offset to a [0,1] range
+                    (((smoothed_step.ln() * self.color_frequency - self.color_offset * 2.0 * PI)
+                        .sin())
+                        * 0.5
+                        + 0.5)
+                        .clamp(0.0, 1.0),
+                    // saturation decreased when glow is high to hide noise when hue oscillates quickly
+                    (self.saturation * (1.0 - (glow * glow))).clamp(0.0, 1.0),
+                    // use glow around edges for brightness
+                    (glow * self.brightness).clamp(0.0, 1.0),
+                )
+            }
+        }
+        row
+    }
+
+    fn escape_steps(&self, parameters: &Self::Intermediate) -> Option<[f64; LANES]> {
+        Some(parameters[0])
+    }
+
+    fn interior(&self, parameters: &Self::Intermediate) -> Option<[bool; LANES]> {
+        Some(parameters[0].map(|step| step as usize >= self.max_iter))
+    }
+
+    fn boundary_distances(
+        &self,
+        width: usize,
+        parameters: &Self::Intermediate,
+    ) -> Option<[f64; LANES]> {
+        let scale = f64::powf(2.0, -self.zoom);
+        Some(pixel_distances(
+            [parameters[0], parameters[1], parameters[2]],
+            self.max_iter,
+            scale,
+            width,
+        ))
+    }
+
+    fn needs_recompute(settings: &Self, old_settings: &Self) -> bool {
+        settings.max_iter != old_settings.max_iter
+            || settings.zoom != old_settings.zoom
+            || settings.offset_y != old_settings.offset_y
+            || settings.offset_x != old_settings.offset_x
+            || settings.formula != old_settings.formula
+            || settings.start != old_settings.start
+            || settings.bailout != old_settings.bailout
+            || settings.julia != old_settings.julia
+            || settings.constant_real != old_settings.constant_real
+            || settings.constant_imag != old_settings.constant_imag
+    }
+}
+
+impl Default for CustomParameters {
+    fn default() -> Self {
+        Self {
+            max_iter: 250,
+            zoom: -2.0,
+            offset_x: -0.5,
+            offset_y: 0.0,
+            formula: "z^2 + c".parse().unwrap(),
+            start: "0".parse().unwrap(),
+            bailout: "|z| > 32".parse().unwrap(),
+            julia: false,
+            constant_real: -0.8,
+            constant_imag: 0.156,
+            saturation: 1.0,
+            color_frequency: 1.0,
+            color_offset: 0.0,
+            glow_spread: 1.0,
+            glow_strength: 1.0,
+            brightness: 2.0,
+            internal_brightness: 1.0,
+        }
+    }
+}
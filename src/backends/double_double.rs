@@ -0,0 +1,259 @@
+use std::ops::{Add, Mul, Sub};
+use std::simd::{Mask, Simd};
+use std::str::FromStr;
+
+use druid::Data;
+use serde::{Deserialize, Serialize};
+
+use super::image_generator::{SimdF64, LANES};
+
+/// the zoom after which f64 pixel coordinates start to collapse into blocks
+const DOUBLE_ZOOM_LIMIT: f64 = 40.0;
+
+/// the arithmetic used for the pixel iterations
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Data, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Precision {
+    /// picks the cheapest precision that can resolve pixels at the current zoom
+    Auto,
+    Double,
+    DoubleDouble,
+}
+
+impl Precision {
+    /// replaces `Auto` with the precision that will actually be used
+    pub fn resolve(self, zoom: f64) -> Precision {
+        match self {
+            Precision::Auto if zoom > DOUBLE_ZOOM_LIMIT => Precision::DoubleDouble,
+            Precision::Auto => Precision::Double,
+            precision => precision,
+        }
+    }
+}
+
+impl FromStr for Precision {
+    type Err = String;
+
+    /// parses the same lowercase names that are used in scene files
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Precision::Auto),
+            "double" => Ok(Precision::Double),
+            "doubledouble" => Ok(Precision::DoubleDouble),
+            _ => Err(format!("unknown precision '{}'", s)),
+        }
+    }
+}
+
+/// Dekker's splitting constant, 2^27 + 1
+const SPLITTER: f64 = 134217729.0;
+
+/// `a + b` as an exact sum `s + e`
+#[inline(always)]
+fn two_sum(a: SimdF64, b: SimdF64) -> (SimdF64, SimdF64) {
+    let s = a + b;
+    let bb = s - a;
+    (s, (a - (s - bb)) + (b - bb))
+}
+
+/// `a + b` as an exact sum `s + e`, assuming `|a| >= |b|`
+#[inline(always)]
+fn quick_two_sum(a: SimdF64, b: SimdF64) -> (SimdF64, SimdF64) {
+    let s = a + b;
+    (s, b - (s - a))
+}
+
+/// splits `a` into two halves with 26 significant bits each
+#[inline(always)]
+fn split(a: SimdF64) -> (SimdF64, SimdF64) {
+    let t = Simd::splat(SPLITTER) * a;
+    let hi = t - (t - a);
+    (hi, a - hi)
+}
+
+/// `a * b` as an exact sum `p + e`, without relying on FMA instructions
+#[inline(always)]
+fn two_prod(a: SimdF64, b: SimdF64) -> (SimdF64, SimdF64) {
+    let p = a * b;
+    let (a_hi, a_lo) = split(a);
+    let (b_hi, b_lo) = split(b);
+    let e = ((a_hi * b_hi - p) + a_hi * b_lo + a_lo * b_hi) + a_lo * b_lo;
+    (p, e)
+}
+
+/// LANES double-double numbers, each the unevaluated sum `hi + lo` of two f64,
+/// giving about 106 bits of precision with only f64 SIMD operations
+#[derive(Clone, Copy, Debug)]
+pub struct DoubleDoubleSimd {
+    pub hi: SimdF64,
+    pub lo: SimdF64,
+}
+
+impl DoubleDoubleSimd {
+    pub fn splat((hi, lo): (f64, f64)) -> Self {
+        let (hi, lo) = quick_two_sum(Simd::splat(hi), Simd::splat(lo));
+        DoubleDoubleSimd { hi, lo }
+    }
+
+    /// adds an f64 offset to every lane
+    pub fn add_f64(self, other: SimdF64) -> Self {
+        let (s, e) = two_sum(self.hi, other);
+        let (hi, lo) = quick_two_sum(s, e + self.lo);
+        DoubleDoubleSimd { hi, lo }
+    }
+
+    /// multiplies by two, which is exact
+    pub fn double(self) -> Self {
+        DoubleDoubleSimd {
+            hi: self.hi + self.hi,
+            lo: self.lo + self.lo,
+        }
+    }
+
+    /// keeps lanes from `new` where `mask` is set, and from `old` elsewhere
+    pub fn select(mask: Mask<i64, LANES>, new: Self, old: Self) -> Self {
+        DoubleDoubleSimd {
+            hi: mask.select(new.hi, old.hi),
+            lo: mask.select(new.lo, old.lo),
+        }
+    }
+}
+
+impl Add for DoubleDoubleSimd {
+    type Output = Self;
+    #[inline(always)]
+    fn add(self, other: Self) -> Self {
+        let (s, e) = two_sum(self.hi, other.hi);
+        let (hi, lo) = quick_two_sum(s, e + self.lo + other.lo);
+        DoubleDoubleSimd { hi, lo }
+    }
+}
+
+impl Sub for DoubleDoubleSimd {
+    type Output = Self;
+    #[inline(always)]
+    fn sub(self, other: Self) -> Self {
+        self + DoubleDoubleSimd {
+            hi: -other.hi,
+            lo: -other.lo,
+        }
+    }
+}
+
+impl Mul for DoubleDoubleSimd {
+    type Output = Self;
+    #[inline(always)]
+    fn mul(self, other: Self) -> Self {
+        let (p, e) = two_prod(self.hi, other.hi);
+        let (hi, lo) = quick_two_sum(p, e + self.hi * other.lo + self.lo * other.hi);
+        DoubleDoubleSimd { hi, lo }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::BigFixed;
+
+    fn exact(value: f64) -> BigFixed {
+        BigFixed::from_f64_exact(value)
+    }
+
+    /// the exact value of the first lane
+    fn value_of(x: DoubleDoubleSimd) -> BigFixed {
+        &exact(x.hi[0]) + &exact(x.lo[0])
+    }
+
+    fn assert_same(a: &BigFixed, b: &BigFixed) {
+        assert_eq!((a - b).to_string(), "0", "{} != {}", a, b);
+    }
+
+    const PAIRS: [(f64, f64); 6] = [
+        (1.0, 1e-20),
+        (0.1, 0.2),
+        (1e16, 1.0),
+        (-3.5, 1e-30),
+        (2.0 / 3.0, -1.0 / 7.0),
+        (123456.789, -123456.788),
+    ];
+
+    #[test]
+    fn two_sum_is_exact() {
+        for (a, b) in PAIRS {
+            let (s, e) = two_sum(Simd::splat(a), Simd::splat(b));
+            assert_eq!(s[0], a + b);
+            assert_same(&(&exact(s[0]) + &exact(e[0])), &(&exact(a) + &exact(b)));
+            if a.abs() >= b.abs() {
+                let (s, e) = quick_two_sum(Simd::splat(a), Simd::splat(b));
+                assert_same(&(&exact(s[0]) + &exact(e[0])), &(&exact(a) + &exact(b)));
+            }
+        }
+    }
+
+    #[test]
+    fn split_halves_are_exact() {
+        for a in [0.1, -2.0 / 3.0, 1e300, 123456.789, f64::EPSILON] {
+            let (hi, lo) = split(Simd::splat(a));
+            assert_eq!(hi[0] + lo[0], a);
+            // at most 26 significant bits in each half
+            for half in [hi[0], lo[0]] {
+                assert_eq!(half.to_bits() & ((1 << 26) - 1), 0, "{} of {}", half, a);
+            }
+        }
+    }
+
+    #[test]
+    fn two_prod_is_exact() {
+        for (a, b) in PAIRS {
+            let (p, e) = two_prod(Simd::splat(a), Simd::splat(b));
+            assert_eq!(p[0], a * b);
+            // enough bits for the exact product of the two
+            let product = &exact(a).with_bits(2400) * &exact(b).with_bits(2400);
+            assert_same(&(&exact(p[0]) + &exact(e[0])), &product);
+        }
+    }
+
+    #[test]
+    fn arithmetic_matches_big_fixed() {
+        let a: BigFixed = "0.1234567890123456789012345678901".parse().unwrap();
+        let b: BigFixed = "-1.987654321098765432109876543210".parse().unwrap();
+        let (dd_a, dd_b) = (
+            DoubleDoubleSimd::splat(a.to_double_double()),
+            DoubleDoubleSimd::splat(b.to_double_double()),
+        );
+        // about 106 bits, well past the 53 of an f64
+        let error = |dd: DoubleDoubleSimd, expected: BigFixed| {
+            (&value_of(dd) - &expected.with_bits(400)).to_f64().abs()
+        };
+        assert!(error(dd_a, a.clone()) < 1e-31);
+        assert!(error(dd_a + dd_b, &a + &b) < 1e-30);
+        assert!(error(dd_a - dd_b, &a - &b) < 1e-30);
+        assert!(error(dd_a * dd_b, &a.with_bits(400) * &b.with_bits(400)) < 1e-30);
+        assert!(error(dd_b.double(), &b + &b) < 1e-30);
+        assert!(error(dd_a.add_f64(Simd::splat(0.5)), &a + &exact(0.5)) < 1e-31);
+    }
+
+    #[test]
+    fn select_picks_lanes() {
+        let new = DoubleDoubleSimd::splat((1.0, 1e-20));
+        let old = DoubleDoubleSimd::splat((2.0, -1e-20));
+        let mask = Mask::from_array(std::array::from_fn(|lane| lane % 2 == 0));
+        let selected = DoubleDoubleSimd::select(mask, new, old);
+        for lane in 0..LANES {
+            let expected = if lane % 2 == 0 { new } else { old };
+            assert_eq!(
+                (selected.hi[lane], selected.lo[lane]),
+                (expected.hi[lane], expected.lo[lane])
+            );
+        }
+    }
+
+    #[test]
+    fn auto_precision_follows_zoom() {
+        assert_eq!(Precision::Auto.resolve(10.0), Precision::Double);
+        assert_eq!(Precision::Auto.resolve(45.0), Precision::DoubleDouble);
+        assert_eq!(Precision::Double.resolve(45.0), Precision::Double);
+        assert_eq!("doubledouble".parse(), Ok(Precision::DoubleDouble));
+        assert!("quad".parse::<Precision>().is_err());
+    }
+}
@@ -0,0 +1,882 @@
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::simd::{Mask, Simd, SimdFloat, SimdPartialEq, SimdPartialOrd, StdFloat};
+use std::{fmt, str::FromStr, sync::Arc};
+
+use druid::Data;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::image_generator::{SimdF64, LANES};
+
+/// LANES complex numbers together with their derivatives, for forward-mode automatic
+/// differentiation: every operation updates the derivative with the chain rule.
+///
+/// The derivative is taken along the real axis of the variable, which is the complex
+/// derivative for holomorphic functions and still a useful estimate for the others.
+#[derive(Clone, Copy, Debug)]
+pub struct DualSimd {
+    pub real: SimdF64,
+    pub imag: SimdF64,
+    pub d_real: SimdF64,
+    pub d_imag: SimdF64,
+}
+
+impl DualSimd {
+    /// a value that does not depend on the variable
+    pub fn constant(real: SimdF64, imag: SimdF64) -> Self {
+        DualSimd {
+            real,
+            imag,
+            d_real: Simd::splat(0.0),
+            d_imag: Simd::splat(0.0),
+        }
+    }
+
+    /// the variable itself, with a derivative of 1
+    pub fn variable(real: SimdF64, imag: SimdF64) -> Self {
+        DualSimd {
+            real,
+            imag,
+            d_real: Simd::splat(1.0),
+            d_imag: Simd::splat(0.0),
+        }
+    }
+
+    /// `|z|^2` of the value
+    pub fn norm_2(self) -> SimdF64 {
+        self.real * self.real + self.imag * self.imag
+    }
+
+    /// `|z'|` of the derivative
+    pub fn derivative_abs(self) -> SimdF64 {
+        (self.d_real * self.d_real + self.d_imag * self.d_imag).sqrt()
+    }
+
+    /// keeps lanes from `new` where `mask` is set, and from `old` elsewhere
+    pub fn select(mask: Mask<i64, LANES>, new: Self, old: Self) -> Self {
+        DualSimd {
+            real: mask.select(new.real, old.real),
+            imag: mask.select(new.imag, old.imag),
+            d_real: mask.select(new.d_real, old.d_real),
+            d_imag: mask.select(new.d_imag, old.d_imag),
+        }
+    }
+
+    /// `f(z)` given `f(z)` and `f'(z)` as (real, imag) for the value of every lane
+    fn chain(self, (real, imag): (SimdF64, SimdF64), (f_real, f_imag): (SimdF64, SimdF64)) -> Self {
+        DualSimd {
+            real,
+            imag,
+            d_real: f_real * self.d_real - f_imag * self.d_imag,
+            d_imag: f_real * self.d_imag + f_imag * self.d_real,
+        }
+    }
+
+    /// applies a scalar complex function to the value of every lane, for the functions
+    /// that have no SIMD equivalent
+    fn map_lanes(self, f: impl Fn(f64, f64) -> (f64, f64)) -> (SimdF64, SimdF64) {
+        let mut real = self.real.to_array();
+        let mut imag = self.imag.to_array();
+        for (re, im) in real.iter_mut().zip(imag.iter_mut()) {
+            (*re, *im) = f(*re, *im);
+        }
+        (Simd::from_array(real), Simd::from_array(imag))
+    }
+
+    pub fn exp(self) -> Self {
+        let value = self.map_lanes(|re, im| {
+            let modulus = re.exp();
+            (modulus * im.cos(), modulus * im.sin())
+        });
+        self.chain(value, value)
+    }
+
+    pub fn ln(self) -> Self {
+        let value = self.map_lanes(|re, im| (re.hypot(im).ln(), im.atan2(re)));
+        // 1/z = conj(z) / |z|^2
+        let norm_2 = self.norm_2();
+        self.chain(value, (self.real / norm_2, -self.imag / norm_2))
+    }
+
+    pub fn sqrt(self) -> Self {
+        let value = self.map_lanes(|re, im| {
+            let modulus = re.hypot(im).sqrt();
+            let angle = im.atan2(re) * 0.5;
+            (modulus * angle.cos(), modulus * angle.sin())
+        });
+        // 1 / (2 * sqrt(z))
+        let result = DualSimd::constant(value.0, value.1);
+        let half = DualSimd::constant(Simd::splat(0.5), Simd::splat(0.0)) / result;
+        self.chain(value, (half.real, half.imag))
+    }
+
+    pub fn sin(self) -> Self {
+        let value = self.map_lanes(|re, im| (re.sin() * im.cosh(), re.cos() * im.sinh()));
+        let derivative = self.map_lanes(|re, im| (re.cos() * im.cosh(), -re.sin() * im.sinh()));
+        self.chain(value, derivative)
+    }
+
+    pub fn cos(self) -> Self {
+        let value = self.map_lanes(|re, im| (re.cos() * im.cosh(), -re.sin() * im.sinh()));
+        let derivative = self.map_lanes(|re, im| (-re.sin() * im.cosh(), -re.cos() * im.sinh()));
+        self.chain(value, derivative)
+    }
+
+    pub fn sinh(self) -> Self {
+        let value = self.map_lanes(|re, im| (re.sinh() * im.cos(), re.cosh() * im.sin()));
+        let derivative = self.map_lanes(|re, im| (re.cosh() * im.cos(), re.sinh() * im.sin()));
+        self.chain(value, derivative)
+    }
+
+    pub fn cosh(self) -> Self {
+        let value = self.map_lanes(|re, im| (re.cosh() * im.cos(), re.sinh() * im.sin()));
+        let derivative = self.map_lanes(|re, im| (re.sinh() * im.cos(), re.cosh() * im.sin()));
+        self.chain(value, derivative)
+    }
+
+    /// `|z|`, with `d|z| = Re(conj(z) * z') / |z|`
+    pub fn abs(self) -> Self {
+        let modulus = self.norm_2().sqrt();
+        let zero = modulus.simd_eq(Simd::splat(0.0));
+        let d_real = (self.real * self.d_real + self.imag * self.d_imag) / modulus;
+        DualSimd {
+            real: modulus,
+            imag: Simd::splat(0.0),
+            d_real: zero.select(Simd::splat(0.0), d_real),
+            d_imag: Simd::splat(0.0),
+        }
+    }
+
+    pub fn conj(self) -> Self {
+        DualSimd {
+            real: self.real,
+            imag: -self.imag,
+            d_real: self.d_real,
+            d_imag: -self.d_imag,
+        }
+    }
+
+    pub fn re(self) -> Self {
+        DualSimd {
+            real: self.real,
+            imag: Simd::splat(0.0),
+            d_real: self.d_real,
+            d_imag: Simd::splat(0.0),
+        }
+    }
+
+    pub fn im(self) -> Self {
+        DualSimd {
+            real: self.imag,
+            imag: Simd::splat(0.0),
+            d_real: self.d_imag,
+            d_imag: Simd::splat(0.0),
+        }
+    }
+
+    /// `z^n` by binary exponentiation
+    pub fn powi(self, n: i32) -> Self {
+        let mut result = DualSimd::constant(Simd::splat(1.0), Simd::splat(0.0));
+        let mut base = self;
+        let mut remaining = n.unsigned_abs();
+        while remaining > 0 {
+            if remaining & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            remaining >>= 1;
+        }
+        if n < 0 {
+            DualSimd::constant(Simd::splat(1.0), Simd::splat(0.0)) / result
+        } else {
+            result
+        }
+    }
+
+    /// `z^w = exp(w * ln(z))`, keeping lanes where z is 0 at 0
+    pub fn pow(self, exponent: Self) -> Self {
+        let zero = self.norm_2().simd_eq(Simd::splat(0.0));
+        let result = (exponent * self.ln()).exp();
+        DualSimd::select(
+            zero,
+            DualSimd::constant(Simd::splat(0.0), Simd::splat(0.0)),
+            result,
+        )
+    }
+}
+
+impl Add for DualSimd {
+    type Output = Self;
+    #[inline(always)]
+    fn add(self, other: Self) -> Self {
+        DualSimd {
+            real: self.real + other.real,
+            imag: self.imag + other.imag,
+            d_real: self.d_real + other.d_real,
+            d_imag: self.d_imag + other.d_imag,
+        }
+    }
+}
+
+impl Sub for DualSimd {
+    type Output = Self;
+    #[inline(always)]
+    fn sub(self, other: Self) -> Self {
+        self + -other
+    }
+}
+
+impl Neg for DualSimd {
+    type Output = Self;
+    #[inline(always)]
+    fn neg(self) -> Self {
+        DualSimd {
+            real: -self.real,
+            imag: -self.imag,
+            d_real: -self.d_real,
+            d_imag: -self.d_imag,
+        }
+    }
+}
+
+impl Mul for DualSimd {
+    type Output = Self;
+    /// `(ab)' = a'b + ab'`
+    #[inline(always)]
+    fn mul(self, other: Self) -> Self {
+        DualSimd {
+            real: self.real * other.real - self.imag * other.imag,
+            imag: self.real * other.imag + self.imag * other.real,
+            d_real: self.d_real * other.real - self.d_imag * other.imag + self.real * other.d_real
+                - self.imag * other.d_imag,
+            d_imag: self.d_real * other.imag
+                + self.d_imag * other.real
+                + self.real * other.d_imag
+                + self.imag * other.d_real,
+        }
+    }
+}
+
+impl Div for DualSimd {
+    type Output = Self;
+    /// `(a/b)' = (a' - (a/b) * b') / b`
+    #[inline(always)]
+    fn div(self, other: Self) -> Self {
+        // 1/b = conj(b) / |b|^2
+        let norm_2 = other.norm_2();
+        let inv_real = other.real / norm_2;
+        let inv_imag = -other.imag / norm_2;
+        let real = self.real * inv_real - self.imag * inv_imag;
+        let imag = self.real * inv_imag + self.imag * inv_real;
+        let num_real = self.d_real - (real * other.d_real - imag * other.d_imag);
+        let num_imag = self.d_imag - (real * other.d_imag + imag * other.d_real);
+        DualSimd {
+            real,
+            imag,
+            d_real: num_real * inv_real - num_imag * inv_imag,
+            d_imag: num_real * inv_imag + num_imag * inv_real,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Sinh,
+    Cosh,
+    Exp,
+    Ln,
+    Sqrt,
+    Abs,
+    Conj,
+    Re,
+    Im,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "sin" => Function::Sin,
+            "cos" => Function::Cos,
+            "tan" => Function::Tan,
+            "sinh" => Function::Sinh,
+            "cosh" => Function::Cosh,
+            "exp" => Function::Exp,
+            "ln" | "log" => Function::Ln,
+            "sqrt" => Function::Sqrt,
+            "abs" => Function::Abs,
+            "conj" => Function::Conj,
+            "re" => Function::Re,
+            "im" => Function::Im,
+            _ => return None,
+        })
+    }
+
+    fn apply(self, value: DualSimd) -> DualSimd {
+        match self {
+            Function::Sin => value.sin(),
+            Function::Cos => value.cos(),
+            Function::Tan => value.sin() / value.cos(),
+            Function::Sinh => value.sinh(),
+            Function::Cosh => value.cosh(),
+            Function::Exp => value.exp(),
+            Function::Ln => value.ln(),
+            Function::Sqrt => value.sqrt(),
+            Function::Abs => value.abs(),
+            Function::Conj => value.conj(),
+            Function::Re => value.re(),
+            Function::Im => value.im(),
+        }
+    }
+}
+
+/// the syntax tree of a parsed expression
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    /// a complex constant (real, imag)
+    Number(f64, f64),
+    Z,
+    C,
+    Neg(Box<Node>),
+    Add(Box<Node>, Box<Node>),
+    Sub(Box<Node>, Box<Node>),
+    Mul(Box<Node>, Box<Node>),
+    Div(Box<Node>, Box<Node>),
+    Pow(Box<Node>, Box<Node>),
+    Call(Function, Box<Node>),
+}
+
+impl Node {
+    fn evaluate(&self, z: &DualSimd, c: &DualSimd) -> DualSimd {
+        match self {
+            Node::Number(real, imag) => DualSimd::constant(Simd::splat(*real), Simd::splat(*imag)),
+            Node::Z => *z,
+            Node::C => *c,
+            Node::Neg(a) => -a.evaluate(z, c),
+            Node::Add(a, b) => a.evaluate(z, c) + b.evaluate(z, c),
+            Node::Sub(a, b) => a.evaluate(z, c) - b.evaluate(z, c),
+            Node::Mul(a, b) => a.evaluate(z, c) * b.evaluate(z, c),
+            Node::Div(a, b) => a.evaluate(z, c) / b.evaluate(z, c),
+            // small integer powers are exact and much faster than the logarithm
+            Node::Pow(a, b) => match **b {
+                Node::Number(n, imag) if imag == 0.0 && n.fract() == 0.0 && n.abs() <= 64.0 => {
+                    a.evaluate(z, c).powi(n as i32)
+                }
+                _ => a.evaluate(z, c).pow(b.evaluate(z, c)),
+            },
+            Node::Call(function, a) => function.apply(a.evaluate(z, c)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseExpressionError(String);
+
+impl fmt::Display for ParseExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ParseExpressionError {}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Symbol(char),
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, ParseExpressionError> {
+    let mut tokens = vec![];
+    let mut chars = s.char_indices().peekable();
+    while let Some(&(start, ch)) = chars.peek() {
+        if ch.is_whitespace() {
+            chars.next();
+        } else if ch.is_ascii_digit() || ch == '.' {
+            // a number, including an exponent such as `1e-3`
+            let mut end = start;
+            while let Some(&(index, ch)) = chars.peek() {
+                // `e` only continues the number when an exponent follows it, so `2exp(z)`
+                // and `2e` are still read as multiplications
+                let rest = s[index + ch.len_utf8()..].trim_start_matches(['-', '+']);
+                let exponent = (ch == 'e' || ch == 'E')
+                    && rest.starts_with(|c: char| c.is_ascii_digit())
+                    && !s[start..index].contains(['e', 'E']);
+                let exponent_sign =
+                    (ch == '-' || ch == '+') && s[start..index].ends_with(['e', 'E']);
+                if ch.is_ascii_digit() || ch == '.' || exponent || exponent_sign {
+                    end = index + ch.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            let number = s[start..end].parse().map_err(|_| {
+                ParseExpressionError(format!("invalid number '{}'", &s[start..end]))
+            })?;
+            tokens.push(Token::Number(number));
+        } else if ch.is_ascii_alphabetic() {
+            let mut end = start;
+            while let Some(&(index, ch)) = chars.peek() {
+                if ch.is_ascii_alphanumeric() {
+                    end = index + ch.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token::Name(s[start..end].to_lowercase()));
+        } else if "+-*/^()|<>".contains(ch) {
+            tokens.push(Token::Symbol(ch));
+            chars.next();
+        } else {
+            return Err(ParseExpressionError(format!(
+                "unexpected character '{}'",
+                ch
+            )));
+        }
+    }
+    Ok(tokens)
+}
+
+/// a recursive descent parser over the tokens of an expression:
+///
+/// ```text
+/// sum     = product (('+' | '-') product)*
+/// product = unary (('*' | '/')? unary)*
+/// unary   = '-' unary | power
+/// power   = primary ('^' unary)?
+/// primary = number | name | name '(' sum ')' | '(' sum ')' | '|' sum '|'
+/// ```
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next_is(&self, symbol: char) -> bool {
+        self.peek() == Some(&Token::Symbol(symbol))
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), ParseExpressionError> {
+        if self.next_is(symbol) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(ParseExpressionError(format!("expected '{}'", symbol)))
+        }
+    }
+
+    fn sum(&mut self) -> Result<Node, ParseExpressionError> {
+        let mut node = self.product()?;
+        loop {
+            if self.next_is('+') {
+                self.position += 1;
+                node = Node::Add(Box::new(node), Box::new(self.product()?));
+            } else if self.next_is('-') {
+                self.position += 1;
+                node = Node::Sub(Box::new(node), Box::new(self.product()?));
+            } else {
+                return Ok(node);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<Node, ParseExpressionError> {
+        let mut node = self.unary()?;
+        loop {
+            if self.next_is('*') {
+                self.position += 1;
+                node = Node::Mul(Box::new(node), Box::new(self.unary()?));
+            } else if self.next_is('/') {
+                self.position += 1;
+                node = Node::Div(Box::new(node), Box::new(self.unary()?));
+            } else if matches!(
+                self.peek(),
+                Some(Token::Number(_) | Token::Name(_) | Token::Symbol('('))
+            ) {
+                // implicit multiplication, such as `2z` or `3i`
+                node = Node::Mul(Box::new(node), Box::new(self.unary()?));
+            } else {
+                return Ok(node);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<Node, ParseExpressionError> {
+        if self.next_is('-') {
+            self.position += 1;
+            Ok(Node::Neg(Box::new(self.unary()?)))
+        } else {
+            self.power()
+        }
+    }
+
+    fn power(&mut self) -> Result<Node, ParseExpressionError> {
+        let base = self.primary()?;
+        if self.next_is('^') {
+            self.position += 1;
+            Ok(Node::Pow(Box::new(base), Box::new(self.unary()?)))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn primary(&mut self) -> Result<Node, ParseExpressionError> {
+        let token = self
+            .peek()
+            .cloned()
+            .ok_or_else(|| ParseExpressionError(String::from("unexpected end of expression")))?;
+        self.position += 1;
+        match token {
+            Token::Number(number) => Ok(Node::Number(number, 0.0)),
+            Token::Name(name) => match name.as_str() {
+                "z" => Ok(Node::Z),
+                "c" => Ok(Node::C),
+                "i" => Ok(Node::Number(0.0, 1.0)),
+                "pi" => Ok(Node::Number(std::f64::consts::PI, 0.0)),
+                "e" => Ok(Node::Number(std::f64::consts::E, 0.0)),
+                _ => {
+                    let function = Function::from_name(&name)
+                        .ok_or_else(|| ParseExpressionError(format!("unknown name '{}'", name)))?;
+                    self.expect('(')?;
+                    let argument = self.sum()?;
+                    self.expect(')')?;
+                    Ok(Node::Call(function, Box::new(argument)))
+                }
+            },
+            Token::Symbol('(') => {
+                let node = self.sum()?;
+                self.expect(')')?;
+                Ok(node)
+            }
+            Token::Symbol('|') => {
+                let node = self.sum()?;
+                self.expect('|')?;
+                Ok(Node::Call(Function::Abs, Box::new(node)))
+            }
+            Token::Symbol(symbol) => Err(ParseExpressionError(format!("unexpected '{}'", symbol))),
+        }
+    }
+
+    fn finish(&self) -> Result<(), ParseExpressionError> {
+        match self.peek() {
+            None => Ok(()),
+            Some(Token::Symbol(symbol)) => {
+                Err(ParseExpressionError(format!("unexpected '{}'", symbol)))
+            }
+            Some(_) => Err(ParseExpressionError(String::from(
+                "unexpected trailing input",
+            ))),
+        }
+    }
+}
+
+/// A complex expression in the variables `z` and `c`, such as `z^3 + c*sin(z)`.
+///
+/// Supports `+ - * / ^`, implicit multiplication, the constants `i`, `pi` and `e`,
+/// `|x|` for the absolute value and the functions `sin cos tan sinh cosh exp ln sqrt
+/// abs conj re im`. It is written as text both in the interface and in scene files.
+#[derive(Clone, Debug)]
+pub struct Expression {
+    source: Arc<str>,
+    root: Arc<Node>,
+}
+
+impl Expression {
+    /// evaluates the expression for every lane, along with its derivative
+    pub fn evaluate(&self, z: &DualSimd, c: &DualSimd) -> DualSimd {
+        self.root.evaluate(z, c)
+    }
+}
+
+impl PartialEq for Expression {
+    fn eq(&self, other: &Self) -> bool {
+        self.root == other.root
+    }
+}
+
+impl Data for Expression {
+    fn same(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.root, &other.root) || self == other
+    }
+}
+
+impl FromStr for Expression {
+    type Err = ParseExpressionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            position: 0,
+        };
+        let root = parser.sum()?;
+        parser.finish()?;
+        Ok(Expression {
+            source: Arc::from(s.trim()),
+            root: Arc::new(root),
+        })
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl Serialize for Expression {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Expression {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// A comparison of the real parts of two expressions, such as `|z| > 32`,
+/// used as the bailout condition of an iteration.
+#[derive(Clone, Debug)]
+pub struct Condition {
+    source: Arc<str>,
+    left: Arc<Node>,
+    greater: bool,
+    right: Arc<Node>,
+}
+
+impl Condition {
+    /// whether the condition holds, for every lane
+    pub fn evaluate(&self, z: &DualSimd, c: &DualSimd) -> Mask<i64, LANES> {
+        let left = self.left.evaluate(z, c).real;
+        let right = self.right.evaluate(z, c).real;
+        if self.greater {
+            left.simd_gt(right)
+        } else {
+            left.simd_lt(right)
+        }
+    }
+}
+
+impl PartialEq for Condition {
+    fn eq(&self, other: &Self) -> bool {
+        self.left == other.left && self.greater == other.greater && self.right == other.right
+    }
+}
+
+impl Data for Condition {
+    fn same(&self, other: &Self) -> bool {
+        (Arc::ptr_eq(&self.left, &other.left) && Arc::ptr_eq(&self.right, &other.right))
+            || self == other
+    }
+}
+
+impl FromStr for Condition {
+    type Err = ParseExpressionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            position: 0,
+        };
+        let left = parser.sum()?;
+        let greater = if parser.next_is('>') {
+            true
+        } else if parser.next_is('<') {
+            false
+        } else {
+            return Err(ParseExpressionError(String::from(
+                "expected a comparison with '<' or '>'",
+            )));
+        };
+        parser.position += 1;
+        let right = parser.sum()?;
+        parser.finish()?;
+        Ok(Condition {
+            source: Arc::from(s.trim()),
+            left: Arc::new(left),
+            greater,
+            right: Arc::new(right),
+        })
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl Serialize for Condition {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Condition {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dual(real: f64, imag: f64, variable: bool) -> DualSimd {
+        let (real, imag) = (Simd::splat(real), Simd::splat(imag));
+        if variable {
+            DualSimd::variable(real, imag)
+        } else {
+            DualSimd::constant(real, imag)
+        }
+    }
+
+    /// the value and derivative with respect to z of the first lane
+    fn evaluate(expression: &str, z: (f64, f64), c: (f64, f64)) -> [f64; 4] {
+        let expression: Expression = expression.parse().unwrap();
+        let result = expression.evaluate(&dual(z.0, z.1, true), &dual(c.0, c.1, false));
+        [
+            result.real[0],
+            result.imag[0],
+            result.d_real[0],
+            result.d_imag[0],
+        ]
+    }
+
+    fn value(expression: &str, z: (f64, f64)) -> (f64, f64) {
+        let [real, imag, _, _] = evaluate(expression, z, (0.0, 0.0));
+        (real, imag)
+    }
+
+    fn assert_close((real, imag): (f64, f64), (expected_real, expected_imag): (f64, f64)) {
+        assert!(
+            (real - expected_real).abs() < 1e-9 && (imag - expected_imag).abs() < 1e-9,
+            "{}+{}i != {}+{}i",
+            real,
+            imag,
+            expected_real,
+            expected_imag
+        );
+    }
+
+    #[test]
+    fn precedence_and_implicit_multiplication() {
+        let z = (3.0, 0.0);
+        assert_close(value("1 + 2 * 3", z), (7.0, 0.0));
+        assert_close(value("-z^2", z), (-9.0, 0.0));
+        assert_close(value("2^3^2", z), (512.0, 0.0));
+        assert_close(value("2z", z), (6.0, 0.0));
+        assert_close(value("2(1 + 1)z", z), (12.0, 0.0));
+        assert_close(value("3i", z), (0.0, 3.0));
+        assert_close(value("|3 - 4i|", z), (5.0, 0.0));
+        assert_close(value("1e-3 + 2E2", z), (200.001, 0.0));
+        assert_close(value("2exp(0)", z), (2.0, 0.0));
+        assert_close(value("2e", z), (2.0 * std::f64::consts::E, 0.0));
+        assert_close(value("re(1+2i) + im(1+2i) + conj(i)", z), (3.0, -1.0));
+        assert_close(value("Z * PI", z), (3.0 * std::f64::consts::PI, 0.0));
+    }
+
+    #[test]
+    fn derivatives_match_exact_results() {
+        let z = (1.0, 1.0);
+        let c = (0.5, -0.25);
+        // 3z^2 at 1+i
+        let [_, _, d_real, d_imag] = evaluate("z^3", z, c);
+        assert_close((d_real, d_imag), (0.0, 6.0));
+        // the derivative of z^2 + c does not depend on c
+        let [_, _, d_real, d_imag] = evaluate("z^2 + c", z, c);
+        assert_close((d_real, d_imag), (2.0, 2.0));
+        let [_, _, d_real, d_imag] = evaluate("c*z", z, c);
+        assert_close((d_real, d_imag), c);
+    }
+
+    #[test]
+    fn derivatives_match_finite_differences() {
+        let (z_real, z_imag) = (0.7, -0.4);
+        let h = 1e-6;
+        for expression in [
+            "z^3 + c*sin(z)",
+            "exp(z) / z",
+            "cosh(z) - tan(z) + sinh(z)",
+            "sqrt(z) * ln(z)",
+            "z^2.5",
+            "z^(1+i)",
+            "cos(z)^-2",
+        ] {
+            let c = (-0.3, 0.2);
+            let [_, _, d_real, d_imag] = evaluate(expression, (z_real, z_imag), c);
+            let [ahead_real, ahead_imag, _, _] = evaluate(expression, (z_real + h, z_imag), c);
+            let [behind_real, behind_imag, _, _] = evaluate(expression, (z_real - h, z_imag), c);
+            let (real, imag) = (
+                (ahead_real - behind_real) / (2.0 * h),
+                (ahead_imag - behind_imag) / (2.0 * h),
+            );
+            assert!(
+                (d_real - real).abs() < 1e-6 && (d_imag - imag).abs() < 1e-6,
+                "{}: {}+{}i != {}+{}i",
+                expression,
+                d_real,
+                d_imag,
+                real,
+                imag
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_expressions_are_rejected() {
+        for (expression, message) in [
+            ("(z + 1", "expected ')'"),
+            ("z + 1)", "unexpected ')'"),
+            ("|z", "expected '|'"),
+            ("foo(z)", "unknown name 'foo'"),
+            ("sin z", "expected '('"),
+            ("z +", "unexpected end of expression"),
+            ("", "unexpected end of expression"),
+            ("z $ 1", "unexpected character '$'"),
+            ("1.2.3", "invalid number '1.2.3'"),
+            ("zé", "unexpected character 'é'"),
+            ("z > 1", "unexpected '>'"),
+        ] {
+            match expression.parse::<Expression>() {
+                Ok(_) => panic!("{:?} parsed", expression),
+                Err(err) => assert_eq!(err.to_string(), message, "for {:?}", expression),
+            }
+        }
+    }
+
+    #[test]
+    fn conditions() {
+        let condition: Condition = " |z| > 2 ".parse().unwrap();
+        assert_eq!(condition.to_string(), "|z| > 2");
+        let c = dual(0.0, 0.0, false);
+        assert!(condition.evaluate(&dual(3.0, 0.0, true), &c).all());
+        assert!(!condition.evaluate(&dual(1.0, 1.0, true), &c).any());
+        let condition: Condition = "re(z) < re(c)".parse().unwrap();
+        assert!(condition
+            .evaluate(&dual(-1.0, 0.0, true), &dual(0.5, 0.0, false))
+            .all());
+        assert!("z + 1".parse::<Condition>().is_err());
+        assert!("z > 1 > 2".parse::<Condition>().is_err());
+    }
+
+    #[test]
+    fn display_keeps_the_source() {
+        let expression: Expression = "  z^2 + c ".parse().unwrap();
+        assert_eq!(expression.to_string(), "z^2 + c");
+        assert!(expression == "z^2+c".parse().unwrap());
+    }
+}
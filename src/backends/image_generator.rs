@@ -1,125 +1,478 @@
 use std::{
-    cell::UnsafeCell,
+    any::Any,
+    cell::{RefCell, UnsafeCell},
     ptr,
+    simd::Simd,
     sync::{
-        atomic::{AtomicBool, AtomicI32, Ordering},
-        Arc, Mutex,
+        atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering},
+        Arc, Condvar, Mutex,
     },
 };
 
-pub const LANES: usize = 4_usize;
-pub const BYTES_PER_PIXEL: usize = 3_usize;
+use super::antialiasing::{
+    downsample, Antialiasing, AntialiasingMode, DownsampleFilter, MAX_SAMPLES,
+};
+use super::output::{
+    check_output_format, save_pixels, to_u8, PixelFormat, Transparency, TransparencyMode,
+};
+use super::thread_pool::{Priority, ThreadPool};
+
+/// the number of pixels calculated at once, 8 with the `avx512` feature
+/// to fill the 512-bit registers, and 4 for 256-bit registers otherwise.
+/// This is fixed when compiling, so the AVX-512 instructions are only used by
+/// builds with the feature, which are slower than others on CPUs without them
+pub const LANES: usize = if cfg!(feature = "avx512") { 8 } else { 4 };
+/// the number of color channels of a `Pixel`
+pub const CHANNELS: usize = 4_usize;
+/// the size of the blocks of pixels calculated by the first, coarsest pass
+const FIRST_SCALE: usize = 16;
+/// the size of the square tiles handed out to threads,
+/// a multiple of every pass' block size
+const TILE_SIZE: usize = 64;
+/// the size, in pixels of the current pass, below which a rectangle with mixed
+/// escape steps on its border is calculated instead of subdivided further
+const MIN_SUBDIVIDE: usize = 2 * LANES;
+/// the scale of the pass which adds samples to the pixels of adaptive anti-aliasing,
+/// after the pass with a scale of 1
+const REFINE: usize = 0;
+/// the distance estimate, in pixels, below which adaptive anti-aliasing adds samples
+const BOUNDARY_DISTANCE: f64 = 1.0;
 
-pub type Pixel = [u8; BYTES_PER_PIXEL];
+/// the color of a pixel as red, green, blue and alpha, with channels from 0 to 1.
+/// The colors are premultiplied by alpha, so that averaging and blending pixels
+/// weighs them by their opacity
+pub type Pixel = [f32; CHANNELS];
 pub type PixelCoord = (usize, usize);
+/// one f64 for every lane
+pub type SimdF64 = Simd<f64, LANES>;
 
-pub trait GeneratorParameters: Clone + Send + 'static {
-    type Intermediate;
+pub trait GeneratorParameters: Clone + Send + Sync + 'static {
+    /// everything calculated for a row of LANES pixels that is needed to color it,
+    /// such as `[[f64; LANES]; 4]` for the step, radius, derivative and orbit trap
+    /// of escape-time fractals
+    type Intermediate: Clone + Send + 'static;
 
+    /// calculates the LANES pixels `[i + v * step, j]` of a `width` x `height` image,
+    /// where `(i, j)` is the `location` and `v` is the lane
     fn calc_pixel_row(
         &self,
         width: usize,
         height: usize,
         location: PixelCoord,
-    ) -> [Self::Intermediate; 4];
+        step: usize,
+    ) -> Self::Intermediate;
+
+    /// calculates the 2 * LANES pixels `[i + v * step, j]` as two rows of LANES pixels,
+    /// for backends that can calculate twice as many lanes at once in some cases,
+    /// such as with f32 instead of f64
+    fn calc_pixel_row_pair(
+        &self,
+        width: usize,
+        height: usize,
+        (i, j): PixelCoord,
+        step: usize,
+    ) -> [Self::Intermediate; 2] {
+        [
+            self.calc_pixel_row(width, height, (i, j), step),
+            self.calc_pixel_row(width, height, (i + LANES * step, j), step),
+        ]
+    }
+
+    fn shade_pixel_row(&self, parameters: Self::Intermediate) -> [Pixel; LANES];
 
-    fn shade_pixel_row(&self, parameters: [Self::Intermediate; 4]) -> [Pixel; 4];
+    /// the escape step of each lane of `parameters`, for fractals where it is a whole
+    /// number shared by the pixels of solid regions, such as the iteration count of
+    /// escape-time fractals. Only fractals which report it can be subdivided,
+    /// see `ImageGenerator::set_subdivide`
+    fn escape_steps(&self, _parameters: &Self::Intermediate) -> Option<[f64; LANES]> {
+        None
+    }
 
+    /// the distance of each lane of `parameters` to the boundary of the fractal, in pixels
+    /// of a `width` pixel wide image, for fractals with a distance estimate. Adaptive
+    /// anti-aliasing adds samples to the pixels close to it, see `Antialiasing`
+    fn boundary_distances(
+        &self,
+        _width: usize,
+        _parameters: &Self::Intermediate,
+    ) -> Option<[f64; LANES]> {
+        None
+    }
+
+    /// whether each lane of `parameters` lies inside the fractal, for fractals with an
+    /// iteration limit. Only fractals which report it can be made transparent,
+    /// see `ImageGenerator::set_transparency`
+    fn interior(&self, _parameters: &Self::Intermediate) -> Option<[bool; LANES]> {
+        None
+    }
+
+    /// whether the pixels of `old_settings` have to be calculated again for `settings`,
+    /// rather than only shaded again, because more than their colors changed
     fn needs_recompute(settings: &Self, old_settings: &Self) -> bool;
+
+    /// called once at the start of a computation, before any pixels are calculated,
+    /// to precompute data shared by every pixel row of a `width` x `height` image
+    fn prepare(&mut self, _width: usize, _height: usize, _context: &PrepareContext) {}
+}
+
+/// the parts of a running computation available to `GeneratorParameters::prepare`,
+/// for preparations that are slow enough to need threads, progress and cancellation
+#[derive(Clone)]
+pub struct PrepareContext {
+    pool: ThreadPool,
+    priority: Priority,
+    progress: Arc<AtomicI32>,
+    canceled: Arc<AtomicBool>,
+}
+
+impl PrepareContext {
+    /// calls `work` on this thread and in a job for every other worker of the pool, and
+    /// returns once all calls that have started are done. `work` should take its share
+    /// from a shared counter until there is none left: this thread does not wait for the
+    /// jobs that have not started yet, which may be queued behind the preparation, and
+    /// they only find that nothing is left once they do start
+    pub fn run_on_workers(&self, work: impl Fn() + Send + Sync + 'static) {
+        let work = Arc::new(work);
+        let running = Arc::new((Mutex::new(0_usize), Condvar::new()));
+        for _ in 1..self.pool.threads() {
+            let (work, running) = (work.clone(), running.clone());
+            self.pool.spawn(self.priority, move || {
+                let (count, done) = &*running;
+                *count
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()) += 1;
+                work();
+                *count
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()) -= 1;
+                done.notify_all();
+            });
+        }
+        work();
+        let (count, done) = &*running;
+        let count = count
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        drop(
+            done.wait_while(count, |count| *count > 0)
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        );
+    }
+
+    /// reports how much of the preparation is done, from 0 to 1
+    pub fn set_progress(&self, fraction: f64) {
+        self.progress
+            .store((fraction.clamp(0.0, 1.0) * 999.0) as i32, Ordering::Relaxed);
+    }
+
+    pub fn is_canceled(&self) -> bool {
+        self.canceled.load(Ordering::Acquire)
+    }
 }
 
 pub struct ImageRef {
+    /// the image as 8-bit RGB, for display
     pub data: Arc<Vec<u8>>,
+    /// the image at full precision, for saving
+    pub pixels: Arc<Vec<Pixel>>,
     pub width: usize,
     pub height: usize,
 }
 
 #[derive(Clone)]
 pub struct ImageGenerator {
-    pixels: Arc<Vec<u8>>,
+    pixels: Arc<Vec<Pixel>>,
     width: usize,
     height: usize,
-    scale: usize,
-    current_width: usize,
-    current_height: usize,
+    /// the width of `pixels`, padded to a multiple of LANES
+    buffer_width: usize,
     progress: Arc<AtomicI32>,
     canceled: Arc<AtomicBool>,
     image_ref: Arc<Mutex<ImageRef>>,
+    subdivide: bool,
+    antialiasing: Antialiasing,
+    transparency: Transparency,
+    cache_intermediates: bool,
+    /// what the last finished computation calculated, an `IntermediateCache`
+    /// of its `GeneratorParameters`
+    cache: Arc<Mutex<Option<Arc<dyn Any + Send + Sync>>>>,
 }
 
 impl ImageGenerator {
     pub fn new(width: usize, height: usize) -> Self {
-        let mut new_ig = ImageGenerator {
-            pixels: Arc::new(vec![]),
+        Self::with_buffer_width(width, width + (LANES - width % LANES), height)
+    }
+
+    fn with_buffer_width(width: usize, buffer_width: usize, height: usize) -> Self {
+        ImageGenerator {
+            pixels: Arc::new(vec![Pixel::default(); buffer_width * height]),
             width,
             height,
-            current_width: 0,
-            current_height: 0,
+            buffer_width,
             progress: Arc::new(AtomicI32::new(1000)),
             canceled: Arc::new(AtomicBool::new(false)),
-            scale: 16,
             image_ref: Arc::new(Mutex::new(ImageRef {
                 data: Arc::new(vec![]),
+                pixels: Arc::new(vec![]),
                 width: 0,
                 height: 0,
             })),
+            subdivide: false,
+            antialiasing: Antialiasing::default(),
+            transparency: Transparency::default(),
+            cache_intermediates: false,
+            cache: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// whether to fill in rectangles whose border pixels all have the same escape step
+    /// instead of calculating every pixel inside them (Mariani-Silver subdivision).
+    /// This is much faster for images with large solid regions, but misses any detail
+    /// that lies entirely inside such a rectangle.
+    pub fn set_subdivide(&mut self, subdivide: bool) {
+        self.subdivide = subdivide;
+    }
+
+    /// how many samples to take for each pixel. `Supersample` calculates the image at
+    /// `samples` times the resolution in each direction and filters it down, which
+    /// multiplies the time and memory needed by `samples` squared. `Adaptive` calculates
+    /// the image as usual, and then adds `samples` x `samples` samples to the pixels which
+    /// differ from a neighbor by more than `threshold`, or lie within a pixel of the
+    /// boundary of the fractal by its distance estimate. `samples` is limited to
+    /// `MAX_SAMPLES`.
+    pub fn set_antialiasing(&mut self, antialiasing: Antialiasing) {
+        self.antialiasing = Antialiasing {
+            samples: antialiasing.samples.clamp(1, MAX_SAMPLES),
+            ..antialiasing
         };
-        new_ig.swap_pixel_buf();
-        new_ig
     }
 
-    fn swap_pixel_buf(&mut self) {
-        let width = (self.width as f64 / self.scale as f64).ceil() as usize;
-        let width = width + (LANES - width % LANES);
-        let height = (self.height as f64 / self.scale as f64).ceil() as usize;
+    /// which pixels to make transparent, either those inside the fractal or those outside
+    /// it. With `soft_edges`, the opacity of the pixels outside fades with their distance
+    /// estimate over `edge_width` pixels instead, for fractals which have one. Saved
+    /// images then get an alpha channel, while the displayed image stays opaque.
+    pub fn set_transparency(&mut self, transparency: Transparency) {
+        self.transparency = transparency;
+    }
+
+    /// whether to keep what each computation calculates for every pixel, so that a
+    /// computation which only changes the colors can shade them again instead of
+    /// calculating them, see `GeneratorParameters::needs_recompute`. This takes far more
+    /// memory than the image itself, about 50 bytes per pixel for the Mandelbrot set.
+    pub fn set_cache_intermediates(&mut self, cache_intermediates: bool) {
+        self.cache_intermediates = cache_intermediates;
+        if !cache_intermediates {
+            *self
+                .cache
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+        }
+    }
+
+    /// the cache of the last finished computation, if it was calculated for settings
+    /// which only differ from `settings` in their colors
+    fn reusable_cache<D: GeneratorParameters>(
+        &self,
+        settings: &D,
+    ) -> Option<Arc<IntermediateCache<D>>> {
+        let cache = self
+            .cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()?
+            .downcast::<IntermediateCache<D>>()
+            .ok()?;
+        (cache.subdivide == self.subdivide && !D::needs_recompute(settings, &cache.settings))
+            .then_some(cache)
+    }
+
+    /// makes a copy of the pixels calculated so far available through `image_ref`
+    fn publish_pixel_buf(&self) {
         if let Ok(mut im_ref) = self.image_ref.lock() {
-            im_ref.data = std::mem::replace(
-                &mut self.pixels,
-                Arc::new(vec![0; width * height * BYTES_PER_PIXEL]),
+            im_ref.data = Arc::new(
+                self.pixels
+                    .iter()
+                    .flat_map(|pixel| &pixel[..3])
+                    .map(|&c| to_u8(c))
+                    .collect(),
             );
-            im_ref.width = self.current_width;
-            im_ref.height = self.current_height;
+            im_ref.pixels = Arc::new(self.pixels.as_ref().clone());
+            im_ref.width = self.buffer_width;
+            im_ref.height = self.height;
         }
-        self.current_width = width;
-        self.current_height = height;
     }
 
-    /// handles the dispatch of all threads
-    pub fn do_compute<D: GeneratorParameters>(&mut self, settings: D, threads: usize) {
+    /// starts computing the image for `settings` on `pool` and returns immediately,
+    /// calling `on_finish` once the computation is done or canceled
+    ///
+    /// The image is rendered in passes of decreasing scale, each of which calculates
+    /// one pixel for every `scale` x `scale` block and fills the whole block with it.
+    /// The pixels of the previous pass are kept, so each pass only calculates the
+    /// pixels that are new at its resolution. The passes are split into tiles, which
+    /// are calculated one per job, so that jobs of a higher priority can run in between.
+    ///
+    /// With `set_subdivide`, each tile of a pass is calculated as a rectangle which is
+    /// filled in if its border has a single escape step and split in two otherwise.
+    ///
+    /// With `set_cache_intermediates`, settings that only change the colors of the
+    /// last finished computation are shaded from its cache in a single pass instead.
+    ///
+    /// With `set_antialiasing`, supersampled images are calculated at a higher resolution
+    /// and filtered down, and adaptive ones get a last pass which adds samples to the
+    /// pixels that need them.
+    pub fn start_compute<D: GeneratorParameters>(
+        &self,
+        mut settings: D,
+        pool: &ThreadPool,
+        priority: Priority,
+        on_finish: impl FnOnce() + Send + 'static,
+    ) {
+        let Antialiasing { mode, samples, .. } = self.antialiasing;
+        if mode == AntialiasingMode::Supersample && samples > 1 {
+            // boxed, as the large image calls this again with a closure around it
+            self.start_supersampled(settings, pool, priority, Box::new(on_finish));
+            return;
+        }
         self.progress.store(0, Ordering::Relaxed);
         self.canceled.store(false, Ordering::Release);
-        while self.scale >= 1 {
-            (0..threads)
-                .into_iter()
-                .map(|t| {
-                    let passable_self = self.clone();
-                    let passable_settings = settings.clone();
-                    std::thread::spawn(move || unsafe {
-                        if is_x86_feature_detected!("avx2") {
-                            passable_self.calc_image_region_avx(passable_settings, threads, t);
-                        } else {
-                            passable_self.calc_image_region(passable_settings, threads, t);
-                        }
-                    })
+        let image = self.clone();
+        let pool = pool.clone();
+
+        if let Some(cache) = self.reusable_cache(&settings) {
+            pool.clone().spawn(priority, move || {
+                // a fresh record of the steps, for replaying the subdivision
+                let steps = cache
+                    .subdivided
+                    .then(|| vec![f32::NAN; image.buffer_width * image.height]);
+                let boundary = image.boundary_flags();
+                Arc::new(Computation {
+                    image,
+                    settings,
+                    steps,
+                    boundary,
+                    cache: Some(cache),
+                    reshading: true,
+                    pool,
+                    priority,
+                    next_tile: AtomicUsize::new(0),
+                    active_jobs: AtomicUsize::new(0),
+                    on_finish: Mutex::new(Some(Box::new(on_finish))),
                 })
-                // force the threads to start by consuming the iterator
-                .collect::<Vec<_>>()
-                .into_iter()
-                .for_each(|t| t.join().unwrap());
-            if self.scale == 1 || self.canceled.load(Ordering::Relaxed) {
-                break;
-            }
-            self.scale = self.scale / 2;
-            self.swap_pixel_buf();
+                .start_pass(1);
+            });
+            return;
         }
-        if self.scale == 16 || !self.canceled.load(Ordering::Relaxed) {
-            if let Ok(mut im_ref) = self.image_ref.lock() {
-                im_ref.data = self.pixels.clone();
-                im_ref.width = self.current_width;
-                im_ref.height = self.current_height;
+
+        pool.clone().spawn(priority, move || {
+            settings.prepare(
+                image.width,
+                image.height,
+                &PrepareContext {
+                    pool: pool.clone(),
+                    priority,
+                    progress: image.progress.clone(),
+                    canceled: image.canceled.clone(),
+                },
+            );
+            // a single row tells whether the fractal reports escape steps at all,
+            // the others are calculated pixel by pixel as usual
+            let steps = (image.subdivide
+                && settings
+                    .escape_steps(&settings.calc_pixel_row(
+                        image.buffer_width,
+                        image.height,
+                        (0, 0),
+                        1,
+                    ))
+                    .is_some())
+            .then(|| vec![f32::NAN; image.buffer_width * image.height]);
+            let boundary = image.boundary_flags();
+            let cache = image.cache_intermediates.then(|| {
+                Arc::new(IntermediateCache {
+                    settings: settings.clone(),
+                    subdivide: image.subdivide,
+                    subdivided: steps.is_some(),
+                    tiles: (0..image.tile_count()).map(|_| Mutex::default()).collect(),
+                })
+            });
+            Arc::new(Computation {
+                image,
+                settings,
+                steps,
+                boundary,
+                cache,
+                reshading: false,
+                pool,
+                priority,
+                next_tile: AtomicUsize::new(0),
+                active_jobs: AtomicUsize::new(0),
+                on_finish: Mutex::new(Some(Box::new(on_finish))),
+            })
+            .start_pass(FIRST_SCALE);
+        });
+    }
+
+    /// calculates the image at `samples` times its resolution in each direction, as a
+    /// separate image which shares its progress and cancellation, and filters it down
+    /// into this image once it is finished
+    fn start_supersampled<D: GeneratorParameters>(
+        &self,
+        settings: D,
+        pool: &ThreadPool,
+        priority: Priority,
+        on_finish: Box<dyn FnOnce() + Send>,
+    ) {
+        let Antialiasing {
+            samples, filter, ..
+        } = self.antialiasing;
+        // the padding is scaled as well, so that every sample keeps its place in the image
+        let mut large = ImageGenerator::with_buffer_width(
+            self.width * samples,
+            self.buffer_width * samples,
+            self.height * samples,
+        );
+        large.progress = self.progress.clone();
+        large.canceled = self.canceled.clone();
+        large.subdivide = self.subdivide;
+        // the soft edges keep their width in the pixels of this image
+        large.transparency = Transparency {
+            edge_width: self.transparency.edge_width * samples as f64,
+            ..self.transparency
+        };
+        let large_pixels = large.pixels.clone();
+        let image = self.clone();
+        large.start_compute(settings, pool, priority, move || {
+            if !image.canceled.load(Ordering::Acquire) {
+                let pixels = downsample(
+                    &large_pixels,
+                    image.buffer_width,
+                    image.height,
+                    samples,
+                    filter,
+                );
+                // the computation of this image is the only one writing to it
+                unsafe {
+                    ptr::copy_nonoverlapping(
+                        pixels.as_ptr(),
+                        image.pixels.as_ptr() as *mut Pixel,
+                        pixels.len(),
+                    );
+                }
+                image.publish_pixel_buf();
             }
-        }
-        self.progress.store(1000, Ordering::Release);
+            on_finish();
+        });
+    }
+
+    /// a flag for every pixel, set for those close to the boundary of the fractal,
+    /// when the image is refined by adaptive anti-aliasing
+    fn boundary_flags(&self) -> Option<Vec<AtomicBool>> {
+        let Antialiasing { mode, samples, .. } = self.antialiasing;
+        (mode == AntialiasingMode::Adaptive && samples > 1).then(|| {
+            (0..self.buffer_width * self.height)
+                .map(|_| AtomicBool::new(false))
+                .collect()
+        })
     }
 
     pub fn cancel_compute(&mut self) {
@@ -135,54 +488,1230 @@ impl ImageGenerator {
         &self.image_ref
     }
 
-    /// calculates one thread's portion of the image.
-    /// also prints progress in the first thread (id=0)
+    /// whether `save_image` can write this image to `filename` in the given `format`,
+    /// see `check_output_format`
+    pub fn check_save_format(&self, filename: &str, format: PixelFormat) -> Result<(), String> {
+        let alpha = self.transparency.mode != TransparencyMode::Off;
+        check_output_format(filename, format, alpha)
+    }
+
+    /// writes the most recently finished image to `filename` with samples in the given
+    /// `format`, and the file format chosen from the file extension
+    pub fn save_image(&self, filename: &str, format: PixelFormat) -> image::ImageResult<()> {
+        let image = self
+            .image_ref
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        // the padding of the rows is not part of the image
+        let pixels: Vec<Pixel> = image
+            .pixels
+            .chunks(image.width.max(1))
+            .flat_map(|row| &row[..self.width.min(row.len())])
+            .copied()
+            .collect();
+        let alpha = self.transparency.mode != TransparencyMode::Off;
+        save_pixels(filename, &pixels, self.width, image.height, format, alpha)
+    }
+
+    fn tile_count(&self) -> usize {
+        let tiles_x = self.buffer_width.div_ceil(TILE_SIZE);
+        let tiles_y = self.height.div_ceil(TILE_SIZE);
+        tiles_x * tiles_y
+    }
+
+    /// the first pixel of `tile`, and the end of the tile, exclusive
+    fn tile_bounds(&self, tile: usize) -> (PixelCoord, PixelCoord) {
+        let tiles_x = self.buffer_width.div_ceil(TILE_SIZE);
+        let (tile_x, tile_y) = ((tile % tiles_x) * TILE_SIZE, (tile / tiles_x) * TILE_SIZE);
+        let end_x = (tile_x + TILE_SIZE).min(self.buffer_width);
+        let end_y = (tile_y + TILE_SIZE).min(self.height);
+        ((tile_x, tile_y), (end_x, end_y))
+    }
+
+    /// calculates the pixels of `tile` that are new in the pass with the given `scale`,
+    /// by subdivision if the computation has `steps` to record the escape steps in, and
+    /// records what was calculated if the computation has a cache. The `REFINE` pass
+    /// adds samples to the pixels instead, see `refine_tile`.
     ///
     /// ### Safety
-    /// Assumes it is running in parallel with a unique thread_id,
-    /// calling multiple concurrent instances with the same thread_id
-    /// may lead to data races
-    unsafe fn calc_image_region<D: GeneratorParameters>(
-        mut self,
-        settings: D,
-        threads: usize,
-        thread_id: usize,
+    /// Assumes no other thread is writing to the same tile,
+    /// which would lead to data races
+    unsafe fn calc_tile<D: GeneratorParameters>(
+        &self,
+        computation: &Computation<D>,
+        scale: usize,
+        tile: usize,
+    ) {
+        if scale == REFINE {
+            self.refine_tile(computation, tile);
+            return;
+        }
+        let settings = &computation.settings;
+        let ((tile_x, tile_y), (tile_end, tile_end_y)) = self.tile_bounds(tile);
+        let mut entries = vec![];
+        if let Some(steps) = computation.steps.as_deref() {
+            let subdivision = Subdivision {
+                image: self,
+                settings,
+                steps,
+                boundary: computation.boundary.as_deref(),
+                scale,
+                end: (tile_end, tile_end_y),
+                record: computation.cache.as_ref().map(|_| RefCell::new(entries)),
+            };
+            // the last point of the pass in each direction, on the grid of its blocks
+            let last_x = tile_x + (tile_end - 1 - tile_x) / scale * scale;
+            let last_y = tile_y + (tile_end_y - 1 - tile_y) / scale * scale;
+            subdivision.calc_rect((tile_x, tile_y), (last_x, last_y));
+            entries = subdivision
+                .record
+                .map(RefCell::into_inner)
+                .unwrap_or_default();
+        } else {
+            for j in (tile_y..tile_end_y).step_by(scale) {
+                // every other row and every other pixel in the remaining rows
+                // were already calculated by the previous pass
+                let (start, step) = if scale == FIRST_SCALE || (j / scale) % 2 == 1 {
+                    (0, scale)
+                } else {
+                    (scale, 2 * scale)
+                };
+                // actual calculation, two rows of lanes at a time while both are in the tile
+                let mut i = tile_x + start;
+                while i < tile_end {
+                    let rows = if i + LANES * step < tile_end {
+                        let [first, second] = settings.calc_pixel_row_pair(
+                            self.buffer_width,
+                            self.height,
+                            (i, j),
+                            step,
+                        );
+                        [Some(first), Some(second)]
+                    } else {
+                        let row =
+                            settings.calc_pixel_row(self.buffer_width, self.height, (i, j), step);
+                        [Some(row), None]
+                    };
+                    for intermediate in rows.into_iter().flatten() {
+                        if let Some(boundary) = &computation.boundary {
+                            self.mark_boundary(
+                                settings,
+                                boundary,
+                                &intermediate,
+                                (i, j),
+                                step,
+                                tile_end - 1,
+                            );
+                        }
+                        if computation.cache.is_some() {
+                            entries.push(TileEntry::Row {
+                                location: (i, j),
+                                stride: step,
+                                last: tile_end - 1,
+                                parameters: intermediate.clone(),
+                            });
+                        }
+                        let pixels = self.shade_row(settings, intermediate, 1);
+                        for (v, pixel) in pixels.into_iter().enumerate() {
+                            // lanes past the edge of the tile belong to the next one
+                            if i + v * step < tile_end {
+                                self.fill_block(i + v * step, j, scale, pixel);
+                            }
+                        }
+                        i += LANES * step;
+                    }
+                }
+            }
+        }
+        if let Some(cache) = &computation.cache {
+            cache.tiles[tile]
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .append(&mut entries);
+        }
+    }
+
+    /// shades `tile` again from the entries of the computation's cache, in the order
+    /// they were calculated in, which gives the same pixels as calculating them
+    ///
+    /// ### Safety
+    /// Same as `calc_tile`
+    unsafe fn reshade_tile<D: GeneratorParameters>(
+        &self,
+        computation: &Computation<D>,
+        tile: usize,
+    ) {
+        let Some(cache) = &computation.cache else {
+            return;
+        };
+        let settings = &computation.settings;
+        let (_, end) = self.tile_bounds(tile);
+        let entries = cache.tiles[tile]
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for entry in entries.iter() {
+            match (entry, computation.steps.as_deref()) {
+                (
+                    TileEntry::Row {
+                        location: (x, y),
+                        stride,
+                        last,
+                        parameters,
+                    },
+                    None,
+                ) => {
+                    if let Some(boundary) = &computation.boundary {
+                        self.mark_boundary(
+                            settings,
+                            boundary,
+                            parameters,
+                            (*x, *y),
+                            *stride,
+                            *last,
+                        );
+                    }
+                    let pixels = self.shade_row(settings, parameters.clone(), 1);
+                    for (v, pixel) in pixels.into_iter().enumerate() {
+                        if x + v * stride <= *last {
+                            self.fill_block(x + v * stride, *y, 1, pixel);
+                        }
+                    }
+                }
+                // the blocks of the earlier passes are all covered again by later
+                // entries, so only the pixels themselves are shaded
+                (
+                    TileEntry::Row {
+                        location: (x, y),
+                        stride,
+                        last,
+                        parameters,
+                    },
+                    Some(steps),
+                ) => Subdivision::replay(self, computation, steps, 1, end).store(
+                    parameters.clone(),
+                    *x,
+                    *y,
+                    *stride,
+                    *last,
+                ),
+                (
+                    TileEntry::Fill {
+                        from,
+                        to,
+                        scale,
+                        step,
+                    },
+                    Some(steps),
+                ) => Subdivision::replay(self, computation, steps, *scale, end)
+                    .fill_rect(*from, *to, *step),
+                (TileEntry::Fill { .. }, None) => {}
+            }
+        }
+    }
+
+    /// the colors of `parameters`, premultiplied by the opacity `set_transparency` gives
+    /// them, for pixels of an image with `samples` times the resolution of this one
+    fn shade_row<D: GeneratorParameters>(
+        &self,
+        settings: &D,
+        parameters: D::Intermediate,
+        samples: usize,
+    ) -> [Pixel; LANES] {
+        if self.transparency.mode == TransparencyMode::Off {
+            return settings.shade_pixel_row(parameters);
+        }
+        let Some(interior) = settings.interior(&parameters) else {
+            return settings.shade_pixel_row(parameters);
+        };
+        let distances = self
+            .transparency
+            .soft_edges
+            .then(|| settings.boundary_distances(self.buffer_width * samples, &parameters))
+            .flatten()
+            .unwrap_or([f64::INFINITY; LANES]);
+        let mut pixels = settings.shade_pixel_row(parameters);
+        for (v, pixel) in pixels.iter_mut().enumerate() {
+            let alpha = self
+                .transparency
+                .opacity(interior[v], distances[v] / samples as f64);
+            *pixel = pixel.map(|channel| channel * alpha);
+            pixel[3] = alpha;
+        }
+        pixels
+    }
+
+    /// flags the lanes `[x + v * stride, y]` up to `last` whose distance estimate puts
+    /// them close to the boundary of the fractal, see `Computation::boundary`
+    fn mark_boundary<D: GeneratorParameters>(
+        &self,
+        settings: &D,
+        boundary: &[AtomicBool],
+        parameters: &D::Intermediate,
+        (x, y): PixelCoord,
+        stride: usize,
+        last: usize,
+    ) {
+        let Some(distances) = settings.boundary_distances(self.buffer_width, parameters) else {
+            return;
+        };
+        for (v, distance) in distances.into_iter().enumerate() {
+            let x = x + v * stride;
+            if x <= last && distance < BOUNDARY_DISTANCE {
+                boundary[x + y * self.buffer_width].store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// replaces the pixels of `tile` which differ from one of their neighbors by more than
+    /// the threshold, or are flagged as close to the boundary, by the average of
+    /// `samples` x `samples` samples spread over the pixel, centered on the point the
+    /// other pixels are sampled at. The samples are pixels of an
+    /// image with `samples` times the resolution, so that the backends calculate them
+    /// like any other pixel.
+    ///
+    /// ### Safety
+    /// Same as `calc_tile`
+    unsafe fn refine_tile<D: GeneratorParameters>(
+        &self,
+        computation: &Computation<D>,
+        tile: usize,
     ) {
-        for j in (thread_id..self.current_height).step_by(threads) {
-            // progress
-            self.progress
-                .store((j * 1000 / self.height) as i32, Ordering::Relaxed);
-            // actual calculation
-            for i in (0..self.current_width).step_by(LANES) {
-                // TODO: calculate only the *new* portion of the pixels,
-                // to prevent doubling the render time
-                // calculate new pixels
-                let intermediate =
-                    settings.calc_pixel_row(self.current_width, self.current_height, (i, j));
-                let pixel = settings.shade_pixel_row(intermediate);
-                self.write_pixel(i + j * self.current_width, pixel);
+        let settings = &computation.settings;
+        let Antialiasing {
+            samples, threshold, ..
+        } = self.antialiasing;
+        let threshold = threshold as f32;
+        let ((tile_x, tile_y), (tile_end, tile_end_y)) = self.tile_bounds(tile);
+        // the samples are averaged around the corner of their pixel, like `downsample`
+        // does, so refined pixels stay in place next to the others
+        let kernel = DownsampleFilter::Box.kernel(samples);
+        let reach = kernel.len() / 2;
+        let (sample_width, sample_height) = (self.buffer_width * samples, self.height * samples);
+        // the image as the last pass left it, which refining the other tiles does not
+        // change, so every pixel is compared to the same neighbors
+        let published = self
+            .image_ref
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .pixels
+            .clone();
+        if published.len() != self.pixels.len() {
+            return;
+        }
+        let pixel_at = |x: usize, y: usize| published[x + y * self.buffer_width];
+        let differs = |x: usize, y: usize| {
+            let pixel = pixel_at(x, y);
+            (y.saturating_sub(1)..(y + 2).min(self.height)).any(|y| {
+                (x.saturating_sub(1)..(x + 2).min(self.buffer_width)).any(|x| {
+                    pixel_at(x, y)
+                        .iter()
+                        .zip(pixel)
+                        .any(|(a, b)| (a - b).abs() > threshold)
+                })
+            })
+        };
+
+        for y in tile_y..tile_end_y {
+            let refined: Vec<bool> = (tile_x..tile_end)
+                .map(|x| {
+                    computation.boundary.as_ref().is_some_and(|boundary| {
+                        boundary[x + y * self.buffer_width].load(Ordering::Relaxed)
+                    }) || differs(x, y)
+                })
+                .collect();
+            if !refined.contains(&true) {
+                continue;
+            }
+            let mut sums = vec![Pixel::default(); tile_end - tile_x];
+            let mut totals = vec![0.0; tile_end - tile_x];
+            // the pixels of a run of refined pixels share some of their samples,
+            // which are calculated together
+            let mut x = tile_x;
+            while x < tile_end {
+                if !refined[x - tile_x] {
+                    x += 1;
+                    continue;
+                }
+                let run_start = x;
+                while x < tile_end && refined[x - tile_x] {
+                    x += 1;
+                }
+                let run_last = x - 1;
+                let first_sample = (run_start * samples).saturating_sub(reach);
+                let last_sample = (run_last * samples + reach).min(sample_width - 1);
+                for &(offset_y, weight_y) in &kernel {
+                    let sample_y = (y * samples) as isize + offset_y;
+                    if !(0..sample_height as isize).contains(&sample_y) {
+                        continue;
+                    }
+                    for sample_x in (first_sample..=last_sample).step_by(LANES) {
+                        let pixels = self.shade_row(
+                            settings,
+                            settings.calc_pixel_row(
+                                sample_width,
+                                sample_height,
+                                (sample_x, sample_y as usize),
+                                1,
+                            ),
+                            samples,
+                        );
+                        for (v, pixel) in pixels.into_iter().enumerate() {
+                            let sample_x = sample_x + v;
+                            if sample_x > last_sample {
+                                break;
+                            }
+                            // the pixels of the run whose kernel reaches this sample
+                            let first_x = sample_x.saturating_sub(reach).div_ceil(samples);
+                            let last_x = (sample_x + reach) / samples;
+                            for x in first_x.max(run_start)..=last_x.min(run_last) {
+                                let offset = sample_x + reach - x * samples;
+                                let weight = kernel[offset].1 * weight_y;
+                                for (sum, channel) in sums[x - tile_x].iter_mut().zip(pixel) {
+                                    *sum += channel * weight;
+                                }
+                                totals[x - tile_x] += weight;
+                            }
+                        }
+                    }
+                }
             }
-            if self.canceled.load(Ordering::Acquire) {
-                return;
+            for (x, (sum, total)) in (tile_x..tile_end).zip(sums.into_iter().zip(totals)) {
+                if refined[x - tile_x] {
+                    self.fill_block(x, y, 1, sum.map(|channel| channel / total));
+                }
             }
         }
     }
 
     #[target_feature(enable = "avx2")]
-    unsafe fn calc_image_region_avx<D: GeneratorParameters>(
-        self,
-        settings: D,
-        threads: usize,
-        thread_id: usize,
+    unsafe fn calc_tile_avx<D: GeneratorParameters>(
+        &self,
+        computation: &Computation<D>,
+        scale: usize,
+        tile: usize,
+    ) {
+        self.calc_tile(computation, scale, tile)
+    }
+
+    #[target_feature(enable = "avx512f")]
+    unsafe fn calc_tile_avx512<D: GeneratorParameters>(
+        &self,
+        computation: &Computation<D>,
+        scale: usize,
+        tile: usize,
     ) {
-        self.calc_image_region(settings, threads, thread_id)
+        self.calc_tile(computation, scale, tile)
     }
 
-    unsafe fn write_pixel(&mut self, x: usize, pixel: [Pixel; LANES]) {
-        let mut data = UnsafeCell::new(self.pixels.as_ptr() as *mut Pixel); // as *mut [RgbaPixel; 4]);
-        ptr::write(data.get_mut().offset(x as isize), pixel[0]);
-        ptr::write(data.get_mut().offset((x + 1) as isize), pixel[1]);
-        ptr::write(data.get_mut().offset((x + 2) as isize), pixel[2]);
-        ptr::write(data.get_mut().offset((x + 3) as isize), pixel[3]);
+    /// writes `pixel` to the `size` x `size` block starting at `[x, y]`,
+    /// clipped to the edges of the image
+    unsafe fn fill_block(&self, x: usize, y: usize, size: usize, pixel: Pixel) {
+        let mut data = UnsafeCell::new(self.pixels.as_ptr() as *mut Pixel);
+        for y in y..(y + size).min(self.height) {
+            for x in x..(x + size).min(self.buffer_width) {
+                ptr::write(data.get_mut().add(x + y * self.buffer_width), pixel);
+            }
+        }
+    }
+}
+
+/// one tile of a pass calculated by Mariani-Silver subdivision. The points of the pass
+/// are `scale` pixels apart, and `steps` records the escape step of every pixel that has
+/// been calculated or filled in, so that no pixel is calculated twice
+struct Subdivision<'a, D: GeneratorParameters> {
+    image: &'a ImageGenerator,
+    settings: &'a D,
+    steps: &'a [f32],
+    /// the flags of the pixels close to the boundary, see `Computation::boundary`
+    boundary: Option<&'a [AtomicBool]>,
+    scale: usize,
+    /// the end of the tile, which no pixel is written past
+    end: PixelCoord,
+    /// the rows and fills of the tile, when the computation keeps a cache
+    record: Option<RefCell<Vec<TileEntry<D::Intermediate>>>>,
+}
+
+impl<'a, D: GeneratorParameters> Subdivision<'a, D> {
+    /// a subdivision which only writes pixels, for shading cached entries again
+    fn replay(
+        image: &'a ImageGenerator,
+        computation: &'a Computation<D>,
+        steps: &'a [f32],
+        scale: usize,
+        end: PixelCoord,
+    ) -> Self {
+        Subdivision {
+            image,
+            settings: &computation.settings,
+            steps,
+            boundary: computation.boundary.as_deref(),
+            scale,
+            end,
+            record: None,
+        }
+    }
+
+    /// calculates the points of the rectangle from `[x0, y0]` to `[x1, y1]`, inclusive,
+    /// by calculating its border and filling it in if the border has a single escape step,
+    /// or splitting it in two along its longer side otherwise
+    ///
+    /// ### Safety
+    /// Same as `ImageGenerator::calc_tile`
+    unsafe fn calc_rect(&self, (x0, y0): PixelCoord, (x1, y1): PixelCoord) {
+        let scale = self.scale;
+        self.calc_span(y0, x0, x1);
+        self.calc_span(y1, x0, x1);
+        for y in (y0 + scale..y1).step_by(scale) {
+            // chunks of lanes which start on the border and continue inwards,
+            // so that the lanes past the border are not wasted
+            let stride = self.stride(y);
+            if self.step_at(x0, y).is_nan() {
+                self.calc_lanes(x0, y, stride, x1);
+            }
+            if self.step_at(x1, y).is_nan() {
+                let start = x1
+                    .saturating_sub((LANES - 1) * stride)
+                    .max(x0 + (x1 - x0) % stride);
+                self.calc_lanes(start, y, stride, x1);
+            }
+        }
+        if x1 - x0 <= scale || y1 - y0 <= scale {
+            // no points inside the border
+            return;
+        }
+
+        // the points inside which are already known, from earlier passes, have to match
+        // as well, so that the details they found are not filled over
+        let step = self.step_at(x0, y0);
+        let uniform = !step.is_nan()
+            && (y0..=y1).step_by(scale).all(|y| {
+                (x0..=x1).step_by(scale).all(|x| {
+                    let point = self.step_at(x, y);
+                    point == step || point.is_nan()
+                })
+            })
+            && self.border_is_uniform((x0, y0), (x1, y1), step);
+        if uniform {
+            self.fill_rect((x0, y0), (x1, y1), step);
+        } else if x1 - x0 <= MIN_SUBDIVIDE * scale && y1 - y0 <= MIN_SUBDIVIDE * scale {
+            for y in (y0 + scale..y1).step_by(scale) {
+                self.calc_span(y, x0 + scale, x1 - scale);
+            }
+        } else if x1 - x0 >= y1 - y0 {
+            let middle = x0 + (x1 - x0) / scale / 2 * scale;
+            self.calc_rect((x0, y0), (middle, y1));
+            self.calc_rect((middle, y0), (x1, y1));
+        } else {
+            let middle = y0 + (y1 - y0) / scale / 2 * scale;
+            self.calc_rect((x0, y0), (x1, middle));
+            self.calc_rect((x0, middle), (x1, y1));
+        }
+    }
+
+    /// whether every pixel on the border of the rectangle from `[x0, y0]` to `[x1, y1]`
+    /// has the escape step `step`, calculating the pixels between the points of the pass
+    /// until one differs. Filled pixels are never calculated again, so the points of the
+    /// pass alone would let filaments that cross the border between them be filled over.
+    ///
+    /// ### Safety
+    /// Same as `ImageGenerator::calc_tile`
+    unsafe fn border_is_uniform(
+        &self,
+        (x0, y0): PixelCoord,
+        (x1, y1): PixelCoord,
+        step: f32,
+    ) -> bool {
+        for y in [y0, y1] {
+            for x in (x0..=x1).step_by(LANES) {
+                let lanes = x..(x + LANES).min(x1 + 1);
+                if lanes.clone().any(|x| self.step_at(x, y).is_nan()) {
+                    self.calc_lanes(x, y, 1, x1);
+                }
+                if lanes.into_iter().any(|x| self.step_at(x, y) != step) {
+                    return false;
+                }
+            }
+        }
+        for y in y0 + 1..y1 {
+            // chunks of lanes which start on the border and continue inwards
+            for (x, start) in [(x0, x0), (x1, x1.saturating_sub(LANES - 1).max(x0))] {
+                if self.step_at(x, y).is_nan() {
+                    self.calc_lanes(start, y, 1, x1);
+                }
+                if self.step_at(x, y) != step {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// the distance between the points of row `y` which are new in this pass,
+    /// as every other point of the even rows is known from the previous one
+    fn stride(&self, y: usize) -> usize {
+        if self.scale != FIRST_SCALE && (y / self.scale).is_multiple_of(2) {
+            2 * self.scale
+        } else {
+            self.scale
+        }
+    }
+
+    /// calculates the unknown points of row `y` from `x0` to `x1`, inclusive,
+    /// skipping the chunks of lanes which are already known
+    unsafe fn calc_span(&self, y: usize, x0: usize, x1: usize) {
+        let stride = self.stride(y);
+        // start on the first of the new points
+        let mut x = if stride != self.scale && (x0 / self.scale).is_multiple_of(2) {
+            x0 + self.scale
+        } else {
+            x0
+        };
+        let has_unknown = |x: usize| {
+            (0..LANES)
+                .map(|v| x + v * stride)
+                .any(|x| x <= x1 && self.step_at(x, y).is_nan())
+        };
+        while x <= x1 {
+            let next = x + LANES * stride;
+            if !has_unknown(x) {
+                x = next;
+            } else if next + (LANES - 1) * stride <= x1 && has_unknown(next) {
+                let [first, second] = self.settings.calc_pixel_row_pair(
+                    self.image.buffer_width,
+                    self.image.height,
+                    (x, y),
+                    stride,
+                );
+                self.store(first, x, y, stride, x1);
+                self.store(second, next, y, stride, x1);
+                x = next + LANES * stride;
+            } else {
+                self.calc_lanes(x, y, stride, x1);
+                x = next;
+            }
+        }
+    }
+
+    /// calculates the LANES points `[x + v * stride, y]`, keeping those up to `x1`
+    unsafe fn calc_lanes(&self, x: usize, y: usize, stride: usize, x1: usize) {
+        let parameters = self.settings.calc_pixel_row(
+            self.image.buffer_width,
+            self.image.height,
+            (x, y),
+            stride,
+        );
+        self.store(parameters, x, y, stride, x1);
+    }
+
+    /// shades the points `[x + v * stride, y]` up to `x1` that are still unknown,
+    /// and records their escape steps
+    unsafe fn store(
+        &self,
+        parameters: D::Intermediate,
+        x: usize,
+        y: usize,
+        stride: usize,
+        x1: usize,
+    ) {
+        if let Some(record) = &self.record {
+            record.borrow_mut().push(TileEntry::Row {
+                location: (x, y),
+                stride,
+                last: x1,
+                parameters: parameters.clone(),
+            });
+        }
+        if let Some(boundary) = self.boundary {
+            self.image
+                .mark_boundary(self.settings, boundary, &parameters, (x, y), stride, x1);
+        }
+        let steps = self
+            .settings
+            .escape_steps(&parameters)
+            .unwrap_or([f64::NAN; LANES]);
+        let pixels = self.image.shade_row(self.settings, parameters, 1);
+        for v in 0..LANES {
+            let x = x + v * stride;
+            if x <= x1 && self.step_at(x, y).is_nan() {
+                // like `ImageGenerator::fill_block`, but without covering pixels that
+                // have already been found
+                for block_y in y..(y + self.scale).min(self.end.1) {
+                    for block_x in x..(x + self.scale).min(self.end.0) {
+                        if self.step_at(block_x, block_y).is_nan() {
+                            self.set_pixel(block_x, block_y, pixels[v]);
+                        }
+                    }
+                }
+                self.set_step(x, y, steps[v] as f32);
+            }
+        }
+    }
+
+    /// fills in the pixels inside the border of the rectangle from `[x0, y0]` to
+    /// `[x1, y1]`, by blending the colors along its border (a Coons patch), so that
+    /// smooth shading carries on across the filled region
+    unsafe fn fill_rect(&self, (x0, y0): PixelCoord, (x1, y1): PixelCoord, step: f32) {
+        if let Some(record) = &self.record {
+            record.borrow_mut().push(TileEntry::Fill {
+                from: (x0, y0),
+                to: (x1, y1),
+                scale: self.scale,
+                step,
+            });
+        }
+        let scale = self.scale;
+        // the colors along each side of the border, interpolated between the points of the pass
+        let side = |length: usize, color_at: &dyn Fn(usize) -> Pixel| -> Vec<Pixel> {
+            (0..=length)
+                .map(|along| {
+                    let before = along / scale * scale;
+                    let after = (before + scale).min(length);
+                    let t = (along - before) as f32 / scale as f32;
+                    let (a, b) = (color_at(before), color_at(after));
+                    std::array::from_fn(|c| a[c] * (1.0 - t) + b[c] * t)
+                })
+                .collect()
+        };
+        let top = side(x1 - x0, &|x| self.pixel_at(x0 + x, y0));
+        let bottom = side(x1 - x0, &|x| self.pixel_at(x0 + x, y1));
+        let left = side(y1 - y0, &|y| self.pixel_at(x0, y0 + y));
+        let right = side(y1 - y0, &|y| self.pixel_at(x1, y0 + y));
+        let corners = [top[0], top[x1 - x0], bottom[0], bottom[x1 - x0]];
+        for y in 1..y1 - y0 {
+            let v = y as f32 / (y1 - y0) as f32;
+            for x in 1..x1 - x0 {
+                if !self.step_at(x0 + x, y0 + y).is_nan() {
+                    continue;
+                }
+                let u = x as f32 / (x1 - x0) as f32;
+                let pixel = std::array::from_fn(|c| {
+                    let corner = corners[0][c] * (1.0 - u) * (1.0 - v)
+                        + corners[1][c] * u * (1.0 - v)
+                        + corners[2][c] * (1.0 - u) * v
+                        + corners[3][c] * u * v;
+                    (left[y][c] * (1.0 - u)
+                        + right[y][c] * u
+                        + top[x][c] * (1.0 - v)
+                        + bottom[x][c] * v
+                        - corner)
+                        .max(0.0)
+                });
+                self.set_pixel(x0 + x, y0 + y, pixel);
+                self.set_step(x0 + x, y0 + y, step);
+            }
+        }
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        x + y * self.image.buffer_width
+    }
+
+    unsafe fn step_at(&self, x: usize, y: usize) -> f32 {
+        ptr::read(self.steps.as_ptr().add(self.index(x, y)))
+    }
+
+    unsafe fn set_step(&self, x: usize, y: usize, step: f32) {
+        ptr::write(
+            (self.steps.as_ptr() as *mut f32).add(self.index(x, y)),
+            step,
+        );
+    }
+
+    unsafe fn pixel_at(&self, x: usize, y: usize) -> Pixel {
+        ptr::read((self.image.pixels.as_ptr() as *const Pixel).add(self.index(x, y)))
+    }
+
+    unsafe fn set_pixel(&self, x: usize, y: usize, pixel: Pixel) {
+        ptr::write(
+            (self.image.pixels.as_ptr() as *mut Pixel).add(self.index(x, y)),
+            pixel,
+        );
+    }
+}
+
+/// one row of lanes or filled rectangle of a tile, as it was calculated
+enum TileEntry<I> {
+    /// the lanes `[x + v * stride, y]` up to `last`
+    Row {
+        location: PixelCoord,
+        stride: usize,
+        last: usize,
+        parameters: I,
+    },
+    /// a rectangle filled in by subdivision, in a pass with the given `scale`
+    Fill {
+        from: PixelCoord,
+        to: PixelCoord,
+        scale: usize,
+        step: f32,
+    },
+}
+
+/// everything a computation calculated, kept to shade the image again with new colors
+struct IntermediateCache<D: GeneratorParameters> {
+    settings: D,
+    /// whether subdivision was requested, see `ImageGenerator::set_subdivide`
+    subdivide: bool,
+    /// whether the tiles were actually subdivided, and have to be replayed that way
+    subdivided: bool,
+    /// the entries of each tile, in the order they were calculated in
+    tiles: Vec<Mutex<Vec<TileEntry<D::Intermediate>>>>,
+}
+
+/// a computation in progress, shared by the jobs working on it
+struct Computation<D: GeneratorParameters> {
+    image: ImageGenerator,
+    settings: D,
+    /// the escape step of every pixel found so far, or NaN, when subdividing
+    steps: Option<Vec<f32>>,
+    /// whether each pixel is close to the boundary of the fractal, when the image is
+    /// refined by adaptive anti-aliasing in a last pass
+    boundary: Option<Vec<AtomicBool>>,
+    /// the cache being recorded, or the one being shaded again when `reshading`
+    cache: Option<Arc<IntermediateCache<D>>>,
+    reshading: bool,
+    pool: ThreadPool,
+    priority: Priority,
+    /// the index of the next tile of the current pass that has not been started
+    next_tile: AtomicUsize,
+    /// the number of jobs of the current pass that are still taking tiles
+    active_jobs: AtomicUsize,
+    on_finish: Mutex<Option<Box<dyn FnOnce() + Send>>>,
+}
+
+impl<D: GeneratorParameters> Computation<D> {
+    /// queues one job per worker for the pass with the given `scale`
+    fn start_pass(self: Arc<Self>, scale: usize) {
+        let jobs = self.pool.threads();
+        self.next_tile.store(0, Ordering::Relaxed);
+        self.active_jobs.store(jobs, Ordering::Relaxed);
+        for _ in 0..jobs {
+            let computation = self.clone();
+            self.pool
+                .spawn(self.priority, move || computation.calc_next_tile(scale));
+        }
+    }
+
+    /// calculates the next tile of the pass and queues the job again, so that threads
+    /// which get fast tiles simply take more of them. The last job to find no tiles left
+    /// finishes the pass.
+    fn calc_next_tile(self: Arc<Self>, scale: usize) {
+        let image = &self.image;
+        let tiles = image.tile_count();
+        let tile = self.next_tile.fetch_add(1, Ordering::Relaxed);
+        if tile < tiles && !image.canceled.load(Ordering::Acquire) {
+            // progress, from the share of all pixels calculated before and after this pass
+            let done = if scale == REFINE {
+                tile as f64 / tiles as f64
+            } else {
+                let done_before = if scale == FIRST_SCALE || self.reshading {
+                    0.0
+                } else {
+                    1.0 / (4 * scale * scale) as f64
+                };
+                let done_after = 1.0 / (scale * scale) as f64;
+                done_before + (done_after - done_before) * tile as f64 / tiles as f64
+            };
+            // refining takes about as long as calculating the image
+            let done = match (&self.boundary, scale) {
+                (None, _) => done,
+                (Some(_), REFINE) => 0.5 + done / 2.0,
+                (Some(_), _) => done / 2.0,
+            };
+            image
+                .progress
+                .store(((done * 1000.0) as i32).min(999), Ordering::Relaxed);
+
+            // every tile is handed out once per pass, and the next pass only starts
+            // once all jobs of this one are done
+            unsafe {
+                if self.reshading && scale != REFINE {
+                    image.reshade_tile(&self, tile);
+                } else if LANES == 8 && is_x86_feature_detected!("avx512f") {
+                    image.calc_tile_avx512(&self, scale, tile);
+                } else if is_x86_feature_detected!("avx2") {
+                    image.calc_tile_avx(&self, scale, tile);
+                } else {
+                    image.calc_tile(&self, scale, tile);
+                }
+            }
+            let computation = self.clone();
+            self.pool
+                .spawn(self.priority, move || computation.calc_next_tile(scale));
+        } else if self.active_jobs.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.finish_pass(scale);
+        }
+    }
+
+    fn finish_pass(self: Arc<Self>, scale: usize) {
+        let canceled = self.image.canceled.load(Ordering::Relaxed);
+        if scale == FIRST_SCALE || !canceled {
+            self.image.publish_pixel_buf();
+        }
+        let last = scale == REFINE || (scale == 1 && self.boundary.is_none());
+        if last || canceled {
+            if let (Some(cache), false, false) = (&self.cache, self.reshading, canceled) {
+                *self
+                    .image
+                    .cache
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(cache.clone());
+            }
+            self.image.progress.store(1000, Ordering::Release);
+            let on_finish = self
+                .on_finish
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .take();
+            if let Some(on_finish) = on_finish {
+                on_finish();
+            }
+        } else if scale == 1 {
+            self.start_pass(REFINE);
+        } else {
+            self.start_pass(scale / 2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, time::Instant};
+
+    use super::*;
+    use crate::backends::MandelParameters;
+
+    const WIDTH: usize = 1920;
+    const HEIGHT: usize = 1080;
+    const RUNS: usize = 5;
+
+    /// the scales of the passes, from the coarsest to single pixels
+    fn passes() -> impl Iterator<Item = usize> {
+        std::iter::successors(Some(FIRST_SCALE), |&scale| (scale > 1).then_some(scale / 2))
+    }
+
+    fn seconds(f: impl FnOnce()) -> f64 {
+        let start = Instant::now();
+        f();
+        start.elapsed().as_secs_f64()
+    }
+
+    fn median_seconds(mut f: impl FnMut()) -> f64 {
+        let mut times: Vec<f64> = (0..RUNS).map(|_| seconds(&mut f)).collect();
+        times.sort_by(f64::total_cmp);
+        times[RUNS / 2]
+    }
+
+    /// calculates the pixels of row `j` that are new in the pass with the given `scale`
+    /// across the whole width of the image, the way passes were calculated before they
+    /// were split into tiles
+    ///
+    /// ### Safety
+    /// Same as `ImageGenerator::calc_tile`, for the row
+    unsafe fn calc_row(
+        image: &ImageGenerator,
+        settings: &MandelParameters,
+        scale: usize,
+        j: usize,
+    ) {
+        let end = image.buffer_width;
+        let (start, step) = if scale == FIRST_SCALE || (j / scale) % 2 == 1 {
+            (0, scale)
+        } else {
+            (scale, 2 * scale)
+        };
+        let mut i = start;
+        while i < end {
+            let rows = if i + LANES * step < end {
+                let [first, second] =
+                    settings.calc_pixel_row_pair(image.buffer_width, image.height, (i, j), step);
+                [Some(first), Some(second)]
+            } else {
+                let row = settings.calc_pixel_row(image.buffer_width, image.height, (i, j), step);
+                [Some(row), None]
+            };
+            for intermediate in rows.into_iter().flatten() {
+                let pixels = image.shade_row(settings, intermediate, 1);
+                for (v, pixel) in pixels.into_iter().enumerate() {
+                    if i + v * step < end {
+                        image.fill_block(i + v * step, j, scale, pixel);
+                    }
+                }
+                i += LANES * step;
+            }
+        }
+    }
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn calc_row_avx(
+        image: &ImageGenerator,
+        settings: &MandelParameters,
+        scale: usize,
+        j: usize,
+    ) {
+        calc_row(image, settings, scale, j)
+    }
+
+    #[target_feature(enable = "avx512f")]
+    unsafe fn calc_row_avx512(
+        image: &ImageGenerator,
+        settings: &MandelParameters,
+        scale: usize,
+        j: usize,
+    ) {
+        calc_row(image, settings, scale, j)
+    }
+
+    /// `calc_row` with the widest instructions available, like `calc_next_tile`
+    unsafe fn calc_row_fastest(
+        image: &ImageGenerator,
+        settings: &MandelParameters,
+        scale: usize,
+        j: usize,
+    ) {
+        if LANES == 8 && is_x86_feature_detected!("avx512f") {
+            calc_row_avx512(image, settings, scale, j);
+        } else if is_x86_feature_detected!("avx2") {
+            calc_row_avx(image, settings, scale, j);
+        } else {
+            calc_row(image, settings, scale, j);
+        }
+    }
+
+    /// `ImageGenerator::calc_tile` with the widest instructions available
+    unsafe fn calc_tile_fastest(
+        computation: &Computation<MandelParameters>,
+        scale: usize,
+        tile: usize,
+    ) {
+        let image = &computation.image;
+        if LANES == 8 && is_x86_feature_detected!("avx512f") {
+            image.calc_tile_avx512(computation, scale, tile);
+        } else if is_x86_feature_detected!("avx2") {
+            image.calc_tile_avx(computation, scale, tile);
+        } else {
+            image.calc_tile(computation, scale, tile);
+        }
+    }
+
+    /// renders the image with the rows of each pass interleaved between the workers,
+    /// so that worker `t` calculates rows `t`, `t + threads`, ... of the pass
+    fn render_interleaved(settings: &MandelParameters, pool: &ThreadPool) {
+        let image = ImageGenerator::new(WIDTH, HEIGHT);
+        let threads = pool.threads();
+        for scale in passes() {
+            let (sender, receiver) = mpsc::channel();
+            for thread in 0..threads {
+                let (image, settings, sender) = (image.clone(), settings.clone(), sender.clone());
+                pool.spawn(Priority::Background, move || {
+                    for j in (thread * scale..image.height).step_by(threads * scale) {
+                        // the rows of the threads are disjoint, and the passes run one
+                        // after another
+                        unsafe { calc_row_fastest(&image, &settings, scale, j) };
+                    }
+                    sender.send(()).unwrap();
+                });
+            }
+            drop(sender);
+            // ends once every job has dropped its sender
+            receiver.iter().for_each(drop);
+            image.publish_pixel_buf();
+        }
+    }
+
+    fn render_tiled(settings: &MandelParameters, pool: &ThreadPool) {
+        let image = ImageGenerator::new(WIDTH, HEIGHT);
+        let (sender, receiver) = mpsc::channel();
+        image.start_compute(settings.clone(), pool, Priority::Background, move || {
+            sender.send(()).unwrap()
+        });
+        receiver.recv().unwrap();
+    }
+
+    /// a fractal whose escape step is 1 everywhere except on a single column of pixels
+    #[derive(Clone)]
+    struct Filament {
+        column: usize,
+    }
+
+    impl GeneratorParameters for Filament {
+        type Intermediate = [f64; LANES];
+
+        fn calc_pixel_row(
+            &self,
+            _width: usize,
+            _height: usize,
+            (i, _j): PixelCoord,
+            step: usize,
+        ) -> Self::Intermediate {
+            std::array::from_fn(|v| {
+                if i + v * step == self.column {
+                    2.0
+                } else {
+                    1.0
+                }
+            })
+        }
+
+        fn shade_pixel_row(&self, parameters: Self::Intermediate) -> [Pixel; LANES] {
+            parameters.map(|step| [step as f32 / 2.0, 0.0, 0.0, 1.0])
+        }
+
+        fn escape_steps(&self, parameters: &Self::Intermediate) -> Option<[f64; LANES]> {
+            Some(*parameters)
+        }
+
+        fn needs_recompute(_settings: &Self, _old_settings: &Self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn subdivision_keeps_filaments_between_the_points_of_a_pass() {
+        let (width, height) = (128, 128);
+        let mut image = ImageGenerator::new(width, height);
+        image.set_subdivide(true);
+        let (sender, receiver) = mpsc::channel();
+        // not on the points of any pass but the last
+        let settings = Filament { column: 37 };
+        image.start_compute(
+            settings,
+            &ThreadPool::new(2),
+            Priority::Background,
+            move || sender.send(()).unwrap(),
+        );
+        receiver.recv().unwrap();
+        let image = image.image_ref().lock().unwrap();
+        for y in 0..height {
+            assert_eq!(image.pixels[37 + y * image.width][0], 1.0, "row {}", y);
+            assert_eq!(image.pixels[36 + y * image.width][0], 0.5, "row {}", y);
+        }
+    }
+
+    #[test]
+    fn work_on_workers_is_done_before_returning() {
+        for threads in [1, 3] {
+            let pool = ThreadPool::new(threads);
+            let context = PrepareContext {
+                pool: pool.clone(),
+                priority: Priority::Background,
+                progress: Arc::new(AtomicI32::new(0)),
+                canceled: Arc::new(AtomicBool::new(false)),
+            };
+            // from a job, like the preparation, which must not wait for the jobs queued
+            // behind it on a single worker
+            let (sender, receiver) = mpsc::channel();
+            pool.spawn(Priority::Background, move || {
+                let next = Arc::new(AtomicUsize::new(0));
+                let done = Arc::new(AtomicUsize::new(0));
+                let shared = (next.clone(), done.clone());
+                context.run_on_workers(move || {
+                    let (next, done) = &shared;
+                    while next.fetch_add(1, Ordering::Relaxed) < 1000 {
+                        std::thread::yield_now();
+                        done.fetch_add(1, Ordering::Relaxed);
+                    }
+                });
+                sender.send(done.load(Ordering::Relaxed)).unwrap();
+            });
+            assert_eq!(receiver.recv().unwrap(), 1000);
+        }
+    }
+
+    /// the time the slowest of `threads` threads takes when thread `t` gets the units
+    /// `t`, `t + threads`, ...
+    fn interleaved_makespan(costs: &[f64], threads: usize) -> f64 {
+        (0..threads)
+            .map(|t| costs.iter().skip(t).step_by(threads).sum::<f64>())
+            .fold(0.0, f64::max)
+    }
+
+    /// the time the slowest of `threads` threads takes when each unit goes to the thread
+    /// that is free first, as with the shared tile counter
+    fn shared_counter_makespan(costs: &[f64], threads: usize) -> f64 {
+        let mut loads = vec![0.0_f64; threads];
+        for cost in costs {
+            *loads.iter_mut().min_by(|a, b| a.total_cmp(b)).unwrap() += cost;
+        }
+        loads.into_iter().fold(0.0, f64::max)
+    }
+
+    /// Compares the rows that were interleaved between threads before with the tiles
+    /// taken from a shared counter, on the default view of the Mandelbrot set. Both are
+    /// timed on all cores, and each row and tile is also timed on its own, to find how
+    /// long the slowest thread would take with more cores than this machine has.
+    ///
+    /// `cargo test --release -- --ignored --nocapture scheduling`
+    #[test]
+    #[ignore]
+    fn scheduling_benchmark() {
+        let settings = MandelParameters::default();
+        let pool = ThreadPool::new(num_cpus::get());
+
+        let rows = median_seconds(|| render_interleaved(&settings, &pool));
+        let tiles = median_seconds(|| render_tiled(&settings, &pool));
+        println!(
+            "{}x{} with {} threads: rows {:.3}s, tiles {:.3}s",
+            WIDTH,
+            HEIGHT,
+            pool.threads(),
+            rows,
+            tiles
+        );
+
+        let image = ImageGenerator::new(WIDTH, HEIGHT);
+        let row_costs: Vec<Vec<f64>> = passes()
+            .map(|scale| {
+                (0..image.height)
+                    .step_by(scale)
+                    .map(|j| seconds(|| unsafe { calc_row_fastest(&image, &settings, scale, j) }))
+                    .collect()
+            })
+            .collect();
+        let computation = Computation {
+            image: ImageGenerator::new(WIDTH, HEIGHT),
+            settings: settings.clone(),
+            steps: None,
+            boundary: None,
+            cache: None,
+            reshading: false,
+            pool: pool.clone(),
+            priority: Priority::Background,
+            next_tile: AtomicUsize::new(0),
+            active_jobs: AtomicUsize::new(0),
+            on_finish: Mutex::new(None),
+        };
+        let tile_costs: Vec<Vec<f64>> = passes()
+            .map(|scale| {
+                (0..computation.image.tile_count())
+                    .map(|tile| seconds(|| unsafe { calc_tile_fastest(&computation, scale, tile) }))
+                    .collect()
+            })
+            .collect();
+        let total = |costs: &[Vec<f64>]| costs.iter().flatten().sum::<f64>();
+        println!(
+            "work on one thread: rows {:.3}s, tiles {:.3}s",
+            total(&row_costs),
+            total(&tile_costs)
+        );
+        for threads in [2, 4, 8, 16, 32] {
+            // the passes run one after another
+            let rows: f64 = row_costs
+                .iter()
+                .map(|costs| interleaved_makespan(costs, threads))
+                .sum();
+            let tiles: f64 = tile_costs
+                .iter()
+                .map(|costs| shared_counter_makespan(costs, threads))
+                .sum();
+            println!(
+                "slowest of {} threads: rows {:.3}s ({:.0}% busy), tiles {:.3}s ({:.0}% busy)",
+                threads,
+                rows,
+                100.0 * total(&row_costs) / (threads as f64 * rows),
+                tiles,
+                100.0 * total(&tile_costs) / (threads as f64 * tiles)
+            );
+        }
     }
 }
@@ -1,20 +1,30 @@
 use std::f64::consts::PI;
-use std::simd::{f64x4, Simd, SimdFloat, SimdPartialOrd, StdFloat};
+use std::simd::{Mask, Simd, SimdFloat, SimdPartialOrd, StdFloat};
 
 use druid::{Data, Lens};
+use serde::{Deserialize, Serialize};
 
-use super::image_generator::{GeneratorParameters, Pixel, BYTES_PER_PIXEL, LANES};
-use super::utilities::hsl2rgb;
+use super::big_fixed::BigFixed;
+use super::double_double::{DoubleDoubleSimd, Precision};
+use super::image_generator::{GeneratorParameters, Pixel, SimdF64, CHANNELS, LANES};
+use super::utilities::{
+    complex_mul, complex_pow, degree_ln, hsl2rgb, lane_coordinates, period_tolerance,
+    pixel_distances, PeriodCheck, PERIOD_HUE_STEP,
+};
 
-#[derive(Clone, PartialEq, Data, Lens)]
+#[derive(Clone, PartialEq, Data, Lens, Serialize, Deserialize)]
+#[serde(default)]
 pub struct JuliaParameters {
     // image parameters
     pub max_iter: usize,
     pub zoom: f64,
-    pub offset_x: f64,
-    pub offset_y: f64,
+    pub offset_x: BigFixed,
+    pub offset_y: BigFixed,
     pub constant_real: f64,
     pub constant_imag: f64,
+    pub exponent_real: f64,
+    pub exponent_imag: f64,
+    pub precision: Precision,
     // colors
     pub saturation: f64,
     pub color_frequency: f64,
@@ -23,37 +33,286 @@ pub struct JuliaParameters {
     pub glow_strength: f64,
     pub brightness: f64,
     pub internal_brightness: f64,
+    pub period_coloring: bool,
+}
+
+impl JuliaParameters {
+    /// whether the iteration is the standard `z^2 + c`, which has faster and more
+    /// precise code paths than other exponents
+    fn is_quadratic(&self) -> bool {
+        self.exponent_real == 2.0 && self.exponent_imag == 0.0
+    }
+
+    /// calculates LANES pixels of the Julia set of `z = z^d + c` for any exponent `d`
+    fn calc_pixel_row_multi(
+        &self,
+        width: usize,
+        height: usize,
+        (i, j): (usize, usize),
+        step: usize,
+    ) -> [[f64; LANES]; 5] {
+        let mm_ones: SimdF64 = Simd::splat(1.0);
+        let mm_zero: SimdF64 = Simd::splat(0.0);
+        let exponent_minus_one = (self.exponent_real - 1.0, self.exponent_imag);
+        let exponent_simd = (
+            Simd::splat(self.exponent_real),
+            Simd::splat(self.exponent_imag),
+        );
+        // initialize values
+        let scale = f64::powf(2.0, -self.zoom);
+        let offset_x = self.offset_x.to_f64();
+        let offset_y = self.offset_y.to_f64();
+
+        // c: complex number
+        let c_real = Simd::splat(self.constant_real);
+        let c_imag = Simd::splat(self.constant_imag);
+
+        // z: complex number
+        let mut z_real = lane_coordinates(i, step, width, scale, offset_x);
+
+        let mut z_imag = Simd::splat(
+            (j as f64 / height as f64 - 0.5) * scale * (height as f64 / width as f64) + offset_y,
+        );
+
+        // z': complex running derivative
+        let mut z_prime_r = mm_ones;
+        let mut z_prime_i = mm_ones;
+
+        // value accumulators for coloring
+        let mut step_acc = mm_zero;
+        let mut orbit_acc = mm_ones;
+
+        // periodicity checking: the saved point of the orbit, and the period of the lanes
+        // which came back to it
+        let tolerance_2 = Simd::splat(period_tolerance(scale, width).powi(2));
+        let mut period_check = PeriodCheck::new();
+        let mut saved_real = z_real;
+        let mut saved_imag = z_imag;
+        let mut period_acc = mm_zero;
+        let mut periodic: Mask<i64, LANES> = Mask::splat(false);
+
+        for _step in 0..self.max_iter {
+            // iterate values, according to z = z^d + c
+            //
+            // z' follows from the power rule (z' = d * z^(d-1) * z'),
+            // and z^d is computed from z^(d-1) so both only need one power
+            let power_minus_one = complex_pow(z_real, z_imag, exponent_minus_one);
+            let (power_real, power_imag) = complex_mul(power_minus_one, (z_real, z_imag));
+
+            let z_real_tmp = power_real + c_real;
+            let z_imag_tmp = power_imag + c_imag;
+
+            let (z_prime_r_tmp, z_prime_i_tmp) = complex_mul(
+                complex_mul(exponent_simd, power_minus_one),
+                (z_prime_r, z_prime_i),
+            );
+
+            let radius_2 = z_real * z_real + z_imag * z_imag;
+
+            // select lanes which have not escaped, or been found to be in a cycle
+            // escape of 1000.0 used to smooth distance estimate
+            let mask = radius_2.simd_lt(Simd::splat(1000.0)) & !periodic;
+
+            // conditionally iterate, only if the pixel has not escaped
+            z_real = mask.select(z_real_tmp, z_real);
+            z_imag = mask.select(z_imag_tmp, z_imag);
+            z_prime_i = mask.select(z_prime_i_tmp, z_prime_i);
+            z_prime_r = mask.select(z_prime_r_tmp, z_prime_r);
+
+            step_acc = mask.select(mm_ones, mm_zero) + step_acc;
+            orbit_acc = orbit_acc.simd_min(z_real * z_real + z_imag * z_imag);
+
+            // lanes which came back to the saved point are in a cycle, and never escape
+            let since_saved = period_check.count();
+            let d_real = z_real - saved_real;
+            let d_imag = z_imag - saved_imag;
+            let cycled = (d_real * d_real + d_imag * d_imag).simd_lt(tolerance_2) & mask;
+            period_acc = cycled.select(Simd::splat(since_saved as f64), period_acc);
+            periodic |= cycled;
+            if period_check.should_save() {
+                saved_real = z_real;
+                saved_imag = z_imag;
+            }
+
+            // finish if all pixels have escaped
+            if !mask.any() {
+                break;
+            }
+        }
+
+        // calculate the absolute value (radius) of z for distance estimation
+        let r = (z_real * z_real + z_imag * z_imag).sqrt();
+        let dr = (z_prime_r * z_prime_r + z_prime_i * z_prime_i).sqrt();
+
+        // extract values necessary for coloring
+        let extracted_step = periodic
+            .select(Simd::splat(self.max_iter as f64), step_acc)
+            .to_array();
+        let extracted_dr = dr.to_array();
+        let extracted_r = r.to_array();
+        let extracted_orbit = orbit_acc.sqrt().to_array();
+        let extracted_period = period_acc.to_array();
+
+        [
+            extracted_step,
+            extracted_r,
+            extracted_dr,
+            extracted_orbit,
+            extracted_period,
+        ]
+    }
+
+    /// calculates LANES pixels with double-double arithmetic, for zooms where the
+    /// spacing between pixels is too small for an f64
+    fn calc_pixel_row_double_double(
+        &self,
+        width: usize,
+        height: usize,
+        (i, j): (usize, usize),
+        step: usize,
+    ) -> [[f64; LANES]; 5] {
+        let mm_ones: SimdF64 = Simd::splat(1.0);
+        let mm_zero: SimdF64 = Simd::splat(0.0);
+        // initialize values
+        let scale = f64::powf(2.0, -self.zoom);
+
+        // c: complex number
+        let c_real = DoubleDoubleSimd::splat((self.constant_real, 0.0));
+        let c_imag = DoubleDoubleSimd::splat((self.constant_imag, 0.0));
+
+        // z: complex number, the pixel offset added to the exact center
+        let mut z_real = DoubleDoubleSimd::splat(self.offset_x.to_double_double())
+            .add_f64(lane_coordinates(i, step, width, scale, 0.0));
+        let mut z_imag = DoubleDoubleSimd::splat(self.offset_y.to_double_double()).add_f64(
+            Simd::splat((j as f64 / height as f64 - 0.5) * scale * (height as f64 / width as f64)),
+        );
+
+        // z': complex running derivative, which only needs f64 precision
+        let mut z_prime_r = mm_ones;
+        let mut z_prime_i = mm_ones;
+
+        // z^2: temporary value for optimized computation
+        let mut real_2 = z_real * z_real;
+        let mut imag_2 = z_imag * z_imag;
+
+        // value accumulators for coloring
+        let mut step_acc = mm_zero;
+        let mut orbit_acc = mm_ones;
+
+        // periodicity checking: the saved point of the orbit, and the period of the lanes
+        // which came back to it
+        let tolerance_2 = Simd::splat(period_tolerance(scale, width).powi(2));
+        let mut period_check = PeriodCheck::new();
+        let mut saved_real = z_real;
+        let mut saved_imag = z_imag;
+        let mut period_acc = mm_zero;
+        let mut periodic: Mask<i64, LANES> = Mask::splat(false);
+
+        for _step in 0..self.max_iter {
+            // iterate values, according to z = z^2 + c, as in `calc_pixel_row`
+            let z_imag_tmp = (z_real * z_imag).double() + c_imag;
+            let z_real_tmp = real_2 - imag_2 + c_real;
+
+            // intermediate values for z', from the high part of z
+            let ac_bd = z_real.hi * z_prime_r - z_imag.hi * z_prime_i;
+            let bc_da = z_imag.hi * z_prime_r + z_real.hi * z_prime_i;
+
+            let z_prime_r_tmp = ac_bd + ac_bd;
+            let z_prime_i_tmp = bc_da + bc_da;
+
+            let radius_2 = real_2.hi + imag_2.hi;
+
+            // select lanes which have not escaped, or been found to be in a cycle
+            // escape of 1000.0 used to smooth distance estimate
+            let mask = radius_2.simd_lt(Simd::splat(1000.0)) & !periodic;
+
+            // conditionally iterate, only if the pixel has not escaped
+            z_real = DoubleDoubleSimd::select(mask, z_real_tmp, z_real);
+            z_imag = DoubleDoubleSimd::select(mask, z_imag_tmp, z_imag);
+            z_prime_i = mask.select(z_prime_i_tmp, z_prime_i);
+            z_prime_r = mask.select(z_prime_r_tmp, z_prime_r);
+
+            real_2 = z_real * z_real;
+            imag_2 = z_imag * z_imag;
+
+            step_acc = mask.select(mm_ones, mm_zero) + step_acc;
+            orbit_acc = orbit_acc.simd_min(real_2.hi + imag_2.hi);
+
+            // lanes which came back to the saved point are in a cycle, and never escape
+            let since_saved = period_check.count();
+            let d_real = (z_real - saved_real).hi;
+            let d_imag = (z_imag - saved_imag).hi;
+            let cycled = (d_real * d_real + d_imag * d_imag).simd_lt(tolerance_2) & mask;
+            period_acc = cycled.select(Simd::splat(since_saved as f64), period_acc);
+            periodic |= cycled;
+            if period_check.should_save() {
+                saved_real = z_real;
+                saved_imag = z_imag;
+            }
+
+            // finish if all pixels have escaped
+            if !mask.any() {
+                break;
+            }
+        }
+
+        // calculate the absolute value (radius) of z for distance estimation
+        let r = (real_2.hi + imag_2.hi).sqrt();
+        let dr = (z_prime_r * z_prime_r + z_prime_i * z_prime_i).sqrt();
+
+        // extract values necessary for coloring
+        let extracted_step = periodic
+            .select(Simd::splat(self.max_iter as f64), step_acc)
+            .to_array();
+        let extracted_dr = dr.to_array();
+        let extracted_r = r.to_array();
+        let extracted_orbit = orbit_acc.sqrt().to_array();
+        let extracted_period = period_acc.to_array();
+
+        [
+            extracted_step,
+            extracted_r,
+            extracted_dr,
+            extracted_orbit,
+            extracted_period,
+        ]
+    }
 }
 
 impl GeneratorParameters for JuliaParameters {
-    type Intermediate = [f64; 4];
+    /// `[step, r, dr, orbit, period]`, where `period` is the length of the cycle an
+    /// interior point was found to be in, or 0
+    type Intermediate = [[f64; LANES]; 5];
     /// calculates the color for LANES number of pixels from `[i,j]` to `[i,j+LANES]` of the image
     fn calc_pixel_row(
         &self,
         width: usize,
         height: usize,
         (i, j): (usize, usize),
-    ) -> [Self::Intermediate; LANES] {
-        let mm_ones: f64x4 = Simd::splat(1.0);
-        let mm_zero: f64x4 = Simd::splat(0.0);
+        step: usize,
+    ) -> Self::Intermediate {
+        if !self.is_quadratic() {
+            return self.calc_pixel_row_multi(width, height, (i, j), step);
+        }
+        if self.precision.resolve(self.zoom) == Precision::DoubleDouble {
+            return self.calc_pixel_row_double_double(width, height, (i, j), step);
+        }
+        let mm_ones: SimdF64 = Simd::splat(1.0);
+        let mm_zero: SimdF64 = Simd::splat(0.0);
         // initialize values
         let scale = f64::powf(2.0, -self.zoom);
+        let offset_x = self.offset_x.to_f64();
+        let offset_y = self.offset_y.to_f64();
 
         // c: complex number
         let c_real = Simd::splat(self.constant_real);
         let c_imag = Simd::splat(self.constant_imag);
 
         // z: complex number
-        let mut z_real = Simd::from_array([
-            ((i + 0) as f64 / width as f64 - 0.5) * scale + self.offset_x,
-            ((i + 1) as f64 / width as f64 - 0.5) * scale + self.offset_x,
-            ((i + 2) as f64 / width as f64 - 0.5) * scale + self.offset_x,
-            ((i + 3) as f64 / width as f64 - 0.5) * scale + self.offset_x,
-        ]);
+        let mut z_real = lane_coordinates(i, step, width, scale, offset_x);
 
         let mut z_imag = Simd::splat(
-            (j as f64 / height as f64 - 0.5) * scale * (height as f64 / width as f64)
-                + self.offset_y,
+            (j as f64 / height as f64 - 0.5) * scale * (height as f64 / width as f64) + offset_y,
         );
 
         // z': complex running derivative
@@ -68,6 +327,15 @@ impl GeneratorParameters for JuliaParameters {
         let mut step_acc = mm_zero;
         let mut orbit_acc = mm_ones;
 
+        // periodicity checking: the saved point of the orbit, and the period of the lanes
+        // which came back to it
+        let tolerance_2 = Simd::splat(period_tolerance(scale, width).powi(2));
+        let mut period_check = PeriodCheck::new();
+        let mut saved_real = z_real;
+        let mut saved_imag = z_imag;
+        let mut period_acc = mm_zero;
+        let mut periodic: Mask<i64, LANES> = Mask::splat(false);
+
         for _step in 0..self.max_iter {
             // iterate values, according to z = z^2 + c
             //
@@ -93,9 +361,9 @@ impl GeneratorParameters for JuliaParameters {
 
             let radius_2 = real_2 + imag_2;
 
-            // select lanes which have not escaped
+            // select lanes which have not escaped, or been found to be in a cycle
             // escape of 1000.0 used to smooth distance estimate
-            let mask = radius_2.simd_lt(Simd::splat(1000.0));
+            let mask = radius_2.simd_lt(Simd::splat(1000.0)) & !periodic;
 
             // conditionally iterate, only if the pixel has not escaped
             z_real = mask.select(z_real_tmp, z_real);
@@ -109,6 +377,18 @@ impl GeneratorParameters for JuliaParameters {
             step_acc = mask.select(mm_ones, mm_zero) + step_acc;
             orbit_acc = orbit_acc.simd_min(real_2 + imag_2);
 
+            // lanes which came back to the saved point are in a cycle, and never escape
+            let since_saved = period_check.count();
+            let d_real = z_real - saved_real;
+            let d_imag = z_imag - saved_imag;
+            let cycled = (d_real * d_real + d_imag * d_imag).simd_lt(tolerance_2) & mask;
+            period_acc = cycled.select(Simd::splat(since_saved as f64), period_acc);
+            periodic |= cycled;
+            if period_check.should_save() {
+                saved_real = z_real;
+                saved_imag = z_imag;
+            }
+
             // finish if all pixels have escaped
             if !mask.any() {
                 break;
@@ -120,34 +400,54 @@ impl GeneratorParameters for JuliaParameters {
         let dr = (z_prime_r * z_prime_r + z_prime_i * z_prime_i).sqrt();
 
         // extract values necessary for coloring
-        let extracted_step = step_acc.to_array();
+        let extracted_step = periodic
+            .select(Simd::splat(self.max_iter as f64), step_acc)
+            .to_array();
         let extracted_dr = dr.to_array();
         let extracted_r = r.to_array();
         let extracted_orbit = orbit_acc.sqrt().to_array();
+        let extracted_period = period_acc.to_array();
 
-        [extracted_step, extracted_r, extracted_dr, extracted_orbit]
+        [
+            extracted_step,
+            extracted_r,
+            extracted_dr,
+            extracted_orbit,
+            extracted_period,
+        ]
     }
 
-    fn shade_pixel_row(&self, parameters: [Self::Intermediate; LANES]) -> [Pixel; LANES] {
+    fn shade_pixel_row(&self, parameters: Self::Intermediate) -> [Pixel; LANES] {
         let scale = f64::powf(2.0, -self.zoom);
-        let mut row: [Pixel; LANES] = [[0; BYTES_PER_PIXEL]; LANES];
-        for v in 0..parameters.len() {
+        let ln_degree = degree_ln(self.exponent_real, self.exponent_imag);
+        let mut row: [Pixel; LANES] = [[0.0; CHANNELS]; LANES];
+        for v in 0..LANES {
             let step = parameters[0][v];
             let r = parameters[1][v];
             let dr = parameters[2][v];
             let orbit = parameters[3][v];
+            let period = parameters[4][v];
             // distance estimation: 0.5 * log(r) * r/dr
             let dist_est = 0.5 * (r).ln() * r / dr;
             // a 'glow' effect based on distance (manually adjusted to taste and to adjust to zoom level)
             let glow = (-(dist_est / scale).ln() + self.glow_spread) * self.glow_strength * 0.1;
-            // a smoothed version of the iteration count: i + (1 - ln(ln(r))/ln(2))
-            let smoothed_step = step + (1.0 - ((r).ln()).ln() / f64::ln(2.0));
+            // a smoothed version of the iteration count: i + (1 - ln(ln(r))/ln(d))
+            let smoothed_step = step + (1.0 - ((r).ln()).ln() / ln_degree);
 
             if step as usize >= self.max_iter {
-                // color the inside using orbit trap method
+                // color the inside using orbit trap method, with a hue for each period
+                // of the cycle the orbit settles into if there is one
+                let (hue, saturation) = if self.period_coloring && period > 0.0 {
+                    (
+                        (period * PERIOD_HUE_STEP + self.color_offset).fract(),
+                        self.saturation.clamp(0.0, 1.0),
+                    )
+                } else {
+                    (0.0, 0.0)
+                };
                 row[v] = hsl2rgb(
-                    0.0,
-                    0.0,
+                    hue,
+                    saturation,
                     ((orbit)
                         * self.brightness
                         * self.internal_brightness
@@ -173,13 +473,38 @@ impl GeneratorParameters for JuliaParameters {
         row
     }
 
+    fn escape_steps(&self, parameters: &Self::Intermediate) -> Option<[f64; LANES]> {
+        Some(parameters[0])
+    }
+
+    fn interior(&self, parameters: &Self::Intermediate) -> Option<[bool; LANES]> {
+        Some(parameters[0].map(|step| step as usize >= self.max_iter))
+    }
+
+    fn boundary_distances(
+        &self,
+        width: usize,
+        parameters: &Self::Intermediate,
+    ) -> Option<[f64; LANES]> {
+        let scale = f64::powf(2.0, -self.zoom);
+        Some(pixel_distances(
+            [parameters[0], parameters[1], parameters[2]],
+            self.max_iter,
+            scale,
+            width,
+        ))
+    }
+
     fn needs_recompute(settings: &Self, old_settings: &Self) -> bool {
         return settings.max_iter != old_settings.max_iter
             || settings.zoom != old_settings.zoom
             || settings.offset_y != old_settings.offset_y
             || settings.offset_x != old_settings.offset_x
             || settings.constant_real != old_settings.constant_real
-            || settings.constant_imag != old_settings.constant_imag;
+            || settings.constant_imag != old_settings.constant_imag
+            || settings.exponent_real != old_settings.exponent_real
+            || settings.exponent_imag != old_settings.exponent_imag
+            || settings.precision != old_settings.precision;
     }
 }
 
@@ -188,10 +513,13 @@ impl Default for JuliaParameters {
         Self {
             max_iter: 250,
             zoom: -2.0,
-            offset_x: 0.0,
-            offset_y: 0.0,
+            offset_x: BigFixed::from(0.0),
+            offset_y: BigFixed::from(0.0),
             constant_real: 0.15,
             constant_imag: -0.6,
+            exponent_real: 2.0,
+            exponent_imag: 0.0,
+            precision: Precision::Auto,
             saturation: 1.0,
             color_frequency: 1.0,
             color_offset: 0.0,
@@ -199,6 +527,7 @@ impl Default for JuliaParameters {
             glow_strength: 1.0,
             brightness: 2.0,
             internal_brightness: 1.0,
+            period_coloring: false,
         }
     }
 }
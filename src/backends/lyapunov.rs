@@ -0,0 +1,273 @@
+use std::simd::{Simd, SimdFloat};
+use std::{f64::consts::LN_2, fmt, str::FromStr, sync::Arc};
+
+use druid::{Data, Lens};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::image_generator::{GeneratorParameters, Pixel, SimdF64, CHANNELS, LANES};
+use super::utilities::{hsl2rgb, lane_coordinates};
+
+/// how many iterations are multiplied together before taking a logarithm
+const LOG_INTERVAL: usize = 32;
+/// the bits of the mantissa of an f64
+const MANTISSA_BITS: u64 = (1 << 52) - 1;
+/// the biased exponent of the f64s in [0.5, 1)
+const HALF_EXPONENT: u64 = 1022;
+
+/// The order in which the two rates drive the logistic map, written as a
+/// string of `A`s and `B`s such as `AABAB`, which repeats for every iteration.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sequence {
+    /// `true` where the sequence uses rate A
+    steps: Arc<Vec<bool>>,
+}
+
+impl Default for Sequence {
+    fn default() -> Self {
+        "AB".parse().unwrap()
+    }
+}
+
+impl Data for Sequence {
+    fn same(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.steps, &other.steps) || self == other
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseSequenceError;
+
+impl fmt::Display for ParseSequenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected a sequence of the letters A and B")
+    }
+}
+
+impl std::error::Error for ParseSequenceError {}
+
+impl FromStr for Sequence {
+    type Err = ParseSequenceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let steps = s
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| match c {
+                'A' | 'a' => Ok(true),
+                'B' | 'b' => Ok(false),
+                _ => Err(ParseSequenceError),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if steps.is_empty() {
+            return Err(ParseSequenceError);
+        }
+        Ok(Sequence {
+            steps: Arc::new(steps),
+        })
+    }
+}
+
+impl fmt::Display for Sequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for &step in self.steps.iter() {
+            write!(f, "{}", if step { 'A' } else { 'B' })?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Sequence {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Sequence {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// the Lyapunov fractal: the stability of the logistic map `x = r * x * (1 - x)`,
+/// where r alternates between the rates a (x axis) and b (y axis) following the sequence
+#[derive(Clone, PartialEq, Data, Lens, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LyapunovParameters {
+    // image parameters
+    pub max_iter: usize,
+    pub warmup: usize,
+    pub zoom: f64,
+    pub offset_x: f64,
+    pub offset_y: f64,
+    pub sequence: Sequence,
+    // colors
+    pub saturation: f64,
+    pub color_offset: f64,
+    pub contrast: f64,
+    pub brightness: f64,
+}
+
+impl GeneratorParameters for LyapunovParameters {
+    /// the Lyapunov exponent of each pixel
+    type Intermediate = [f64; LANES];
+    /// calculates the exponent for LANES number of pixels from `[i,j]` to `[i,j+LANES]` of the image
+    fn calc_pixel_row(
+        &self,
+        width: usize,
+        height: usize,
+        (i, j): (usize, usize),
+        step: usize,
+    ) -> Self::Intermediate {
+        let mm_ones: SimdF64 = Simd::splat(1.0);
+        let mm_twos: SimdF64 = Simd::splat(2.0);
+        // initialize values
+        let scale = f64::powf(2.0, -self.zoom);
+        let steps = self.sequence.steps.as_slice();
+
+        // a, b: the two rates
+        let rate_a = lane_coordinates(i, step, width, scale, self.offset_x);
+
+        let rate_b = Simd::splat(
+            (j as f64 / height as f64 - 0.5) * scale * (height as f64 / width as f64)
+                + self.offset_y,
+        );
+
+        let mut x: SimdF64 = Simd::splat(0.5);
+
+        // let the orbit settle before measuring it
+        for iteration in 0..self.warmup {
+            let r = if steps[iteration % steps.len()] {
+                rate_a
+            } else {
+                rate_b
+            };
+            x = r * x * (mm_ones - x);
+        }
+
+        // the exponent is the average of ln|dx_{n+1}/dx_n| = ln|r * (1 - 2x)|,
+        // accumulated as a product to only take the logarithm every LOG_INTERVAL steps.
+        // Like `frexp`, the product is kept as a mantissa in [0.5, 1) and its exponents
+        // are summed separately, so that small factors (orbits which are stable but not
+        // superstable) can not make it underflow to 0, and the factors (at most 4) can
+        // not make it overflow.
+        let mut log_acc = [0.0; LANES];
+        let mut product = mm_ones;
+        let mut exponent_acc: Simd<u64, LANES> = Simd::splat(0);
+        let mut rescaled_acc: Simd<u64, LANES> = Simd::splat(0);
+        for iteration in 0..self.max_iter {
+            let r = if steps[(self.warmup + iteration) % steps.len()] {
+                rate_a
+            } else {
+                rate_b
+            };
+            product *= (r * (mm_ones - mm_twos * x)).abs();
+            x = r * x * (mm_ones - x);
+
+            // zeros (superstable orbits), infinities and NaNs are kept as they are
+            let normal = product.is_normal();
+            let bits = product.to_bits();
+            exponent_acc += normal.select(bits >> Simd::splat(52), Simd::splat(0));
+            rescaled_acc += normal.select(Simd::splat(1), Simd::splat(0));
+            let mantissa = SimdF64::from_bits(
+                (bits & Simd::splat(MANTISSA_BITS)) | Simd::splat(HALF_EXPONENT << 52),
+            );
+            product = normal.select(mantissa, product);
+
+            if iteration % LOG_INTERVAL == LOG_INTERVAL - 1 || iteration + 1 == self.max_iter {
+                let extracted_product = product.to_array();
+                let extracted_exponent = exponent_acc.to_array();
+                let extracted_rescaled = rescaled_acc.to_array();
+                for v in 0..LANES {
+                    let exponent = extracted_exponent[v] as f64
+                        - (HALF_EXPONENT * extracted_rescaled[v]) as f64;
+                    log_acc[v] += extracted_product[v].ln() + exponent * LN_2;
+                }
+                product = mm_ones;
+                exponent_acc = Simd::splat(0);
+                rescaled_acc = Simd::splat(0);
+            }
+        }
+
+        log_acc.map(|acc| acc / self.max_iter.max(1) as f64)
+    }
+
+    fn shade_pixel_row(&self, parameters: Self::Intermediate) -> [Pixel; LANES] {
+        let mut row: [Pixel; LANES] = [[0.0; CHANNELS]; LANES];
+        for v in 0..LANES {
+            let exponent = parameters[v];
+            // a diverging palette: stable orbits (negative exponents) use the chosen hue,
+            // chaotic ones (positive exponents) its complement, both fading to black at 0
+            let (hue, strength) = if exponent > 0.0 {
+                (self.color_offset + 0.5, exponent)
+            } else {
+                (self.color_offset, -exponent)
+            };
+            // superstable orbits give an exponent of -infinity and orbits that diverge
+            // (rates outside of [0, 4]) one of +infinity, both saturating to full brightness
+            let value = if strength.is_nan() {
+                0.0
+            } else {
+                1.0 - (-strength * self.contrast).exp()
+            };
+            row[v] = hsl2rgb(
+                hue.rem_euclid(1.0),
+                self.saturation.clamp(0.0, 1.0),
+                (value * self.brightness).clamp(0.0, 1.0),
+            );
+        }
+        row
+    }
+
+    fn needs_recompute(settings: &Self, old_settings: &Self) -> bool {
+        settings.max_iter != old_settings.max_iter
+            || settings.warmup != old_settings.warmup
+            || settings.zoom != old_settings.zoom
+            || settings.offset_y != old_settings.offset_y
+            || settings.offset_x != old_settings.offset_x
+            || settings.sequence != old_settings.sequence
+    }
+}
+
+impl Default for LyapunovParameters {
+    fn default() -> Self {
+        Self {
+            max_iter: 200,
+            warmup: 50,
+            zoom: -1.0,
+            offset_x: 3.0,
+            offset_y: 3.0,
+            sequence: Sequence::default(),
+            saturation: 1.0,
+            color_offset: 0.12,
+            contrast: 2.0,
+            brightness: 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stable_orbits_with_small_derivatives_have_finite_exponents() {
+        // with both rates at r = 2 + 1e-12, the orbit settles on x = 1 - 1/r, where
+        // the derivative is 2 - r, small enough that a few of them multiplied underflow
+        let rate = 2.0 + 1e-12;
+        let parameters = LyapunovParameters {
+            zoom: 60.0,
+            offset_x: rate,
+            offset_y: rate,
+            ..LyapunovParameters::default()
+        };
+        for exponent in parameters.calc_pixel_row(64, 64, (32, 32), 1) {
+            assert!(
+                (exponent - 1e-12_f64.ln()).abs() < 0.1,
+                "exponent {}",
+                exponent
+            );
+        }
+    }
+}
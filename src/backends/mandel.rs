@@ -1,18 +1,59 @@
 use std::f64::consts::PI;
-use std::simd::{f64x4, Simd, SimdFloat, SimdPartialOrd, StdFloat};
+use std::simd::{Mask, Simd, SimdFloat, SimdPartialOrd, StdFloat};
+use std::sync::Arc;
 
 use druid::{Data, Lens};
+use serde::{Deserialize, Serialize};
 
-use super::image_generator::{GeneratorParameters, Pixel, BYTES_PER_PIXEL, LANES};
-use super::utilities::hsl2rgb;
+use super::big_fixed::BigFixed;
+use super::double_double::{DoubleDoubleSimd, Precision};
+use super::image_generator::{
+    GeneratorParameters, Pixel, PrepareContext, SimdF64, CHANNELS, LANES,
+};
+use super::perturbation::{ReferenceOrbit, SeriesApproximation};
+use super::utilities::{
+    complex_mul, complex_pow, degree_ln, hsl2rgb, lane_coordinates, period_tolerance,
+    pixel_distances, PeriodCheck, PERIOD_HUE_STEP,
+};
 
-#[derive(Clone, PartialEq, Data, Lens)]
+/// the number of f32 lanes of the fast path, which take the space of LANES f64
+const WIDE_LANES: usize = 2 * LANES;
+/// how many times the f32 rounding error the pixel spacing has to be for the fast path
+const F32_MARGIN: f64 = 256.0;
+/// how many iterations the points found inside the main cardioid or the period-2 bulb
+/// still take, for the orbit trap used to shade them
+const TRAP_ITERATIONS: usize = 64;
+
+/// the period of the lanes of `c` which lie inside the main cardioid (1) or the
+/// period-2 bulb (2), and 0 for the others, which have to be iterated to find out
+pub fn bulb_period(c_real: SimdF64, c_imag: SimdF64) -> SimdF64 {
+    let imag_2 = c_imag * c_imag;
+    // cardioid: q * (q + (x - 1/4)) < y^2 / 4, where q = (x - 1/4)^2 + y^2
+    let x = c_real - Simd::splat(0.25);
+    let q = x * x + imag_2;
+    let cardioid = (q * (q + x)).simd_lt(imag_2 * Simd::splat(0.25));
+    // bulb: the disk of radius 1/4 around -1
+    let x = c_real + Simd::splat(1.0);
+    let bulb = (x * x + imag_2).simd_lt(Simd::splat(0.0625));
+    cardioid.select(
+        Simd::splat(1.0),
+        bulb.select(Simd::splat(2.0), Simd::splat(0.0)),
+    )
+}
+
+#[derive(Clone, PartialEq, Data, Lens, Serialize, Deserialize)]
+#[serde(default)]
 pub struct MandelParameters {
     // image parameters
     pub max_iter: usize,
     pub zoom: f64,
-    pub offset_x: f64,
-    pub offset_y: f64,
+    pub offset_x: BigFixed,
+    pub offset_y: BigFixed,
+    pub exponent_real: f64,
+    pub exponent_imag: f64,
+    pub precision: Precision,
+    pub deep_zoom: bool,
+    pub series_tolerance: f64,
     // colors
     pub saturation: f64,
     pub color_frequency: f64,
@@ -21,33 +62,622 @@ pub struct MandelParameters {
     pub glow_strength: f64,
     pub brightness: f64,
     pub internal_brightness: f64,
+    pub period_coloring: bool,
+    // shared by every pixel during a deep zoom computation, filled in by `prepare`
+    #[data(ignore)]
+    #[lens(ignore)]
+    #[serde(skip)]
+    reference_orbit: Option<Arc<ReferenceOrbit>>,
+}
+
+impl MandelParameters {
+    /// whether the iteration is the standard `z^2 + c`, which has faster and more
+    /// precise code paths than other exponents
+    fn is_quadratic(&self) -> bool {
+        self.exponent_real == 2.0 && self.exponent_imag == 0.0
+    }
+
+    /// whether the pixels of a `width` pixel wide image are far enough apart for f32,
+    /// with room for the rounding errors that build up over the iterations
+    fn f32_is_enough(&self, width: usize) -> bool {
+        let scale = f64::powf(2.0, -self.zoom);
+        let magnitude = self.offset_x.to_f64().abs() + self.offset_y.to_f64().abs() + scale;
+        scale / width as f64 > magnitude * f32::EPSILON as f64 * F32_MARGIN
+    }
+
+    /// calculates 2 * LANES pixels of `z = z^2 + c` in f32, which fit in the same
+    /// registers as LANES f64, for shallow zooms where f32 can still resolve every pixel
+    fn calc_pixel_row_pair_f32(
+        &self,
+        width: usize,
+        height: usize,
+        (i, j): (usize, usize),
+        step: usize,
+    ) -> [[[f64; LANES]; 5]; 2] {
+        let mm_ones: Simd<f32, WIDE_LANES> = Simd::splat(1.0);
+        let mm_zero: Simd<f32, WIDE_LANES> = Simd::splat(0.0);
+        // initialize values
+        let scale = f64::powf(2.0, -self.zoom);
+        let offset_x = self.offset_x.to_f64();
+        let offset_y = self.offset_y.to_f64();
+
+        // c: complex number, calculated in f64 and rounded once
+        let c_real = Simd::from_array(std::array::from_fn(|v| {
+            (((i + v * step) as f64 / width as f64 - 0.5) * scale + offset_x) as f32
+        }));
+
+        let c_imag = Simd::splat(
+            ((j as f64 / height as f64 - 0.5) * scale * (height as f64 / width as f64) + offset_y)
+                as f32,
+        );
+
+        // z: complex number
+        let mut z_real = mm_zero;
+        let mut z_imag = mm_zero;
+
+        // z': complex running derivative
+        let mut z_prime_r = mm_ones;
+        let mut z_prime_i = mm_ones;
+
+        // z^2: temporary value for optimized computation
+        let mut real_2 = mm_zero;
+        let mut imag_2 = mm_zero;
+
+        // value accumulators for coloring
+        let mut step_acc = mm_zero;
+        let mut orbit_acc = mm_ones;
+
+        // periodicity checking, as in `calc_pixel_row`, with a tolerance of at least
+        // a few f32 rounding steps, since the orbit may never get closer than that
+        let tolerance = period_tolerance(scale, width).max(4.0 * f32::EPSILON as f64);
+        let tolerance_2 = Simd::splat((tolerance * tolerance) as f32);
+        let mut period_check = PeriodCheck::new();
+        let mut saved_real = z_real;
+        let mut saved_imag = z_imag;
+        let mut period_acc = mm_zero;
+        let mut periodic: Mask<i32, WIDE_LANES> = Mask::splat(false);
+
+        // the cardioid and bulb check of `calc_pixel_row`
+        let c_imag_f64 = Simd::splat(c_imag[0] as f64);
+        let halves = [i, i + LANES * step].map(|i| {
+            bulb_period(
+                lane_coordinates(i, step, width, scale, offset_x),
+                c_imag_f64,
+            )
+        });
+        let known_period: Simd<f32, WIDE_LANES> =
+            Simd::from_array(std::array::from_fn(|v| halves[v / LANES][v % LANES] as f32));
+        let known = known_period.simd_gt(mm_zero);
+
+        for iteration in 0..self.max_iter {
+            if iteration == TRAP_ITERATIONS {
+                periodic |= known;
+            }
+            // iterate values, the same as the f64 version in `calc_pixel_row`
+            let z_imag_tmp = (z_real + z_real) * z_imag + c_imag;
+            let z_real_tmp = real_2 - imag_2 + c_real;
+
+            let ac_bd = z_real * z_prime_r - z_imag * z_prime_i;
+            let bc_da = z_imag * z_prime_r + z_real * z_prime_i;
+
+            let z_prime_r_tmp = ac_bd + ac_bd + mm_ones;
+            let z_prime_i_tmp = bc_da + bc_da;
+
+            let radius_2 = real_2 + imag_2;
+
+            let mask = radius_2.simd_lt(Simd::splat(1000.0)) & !periodic;
+
+            z_real = mask.select(z_real_tmp, z_real);
+            z_imag = mask.select(z_imag_tmp, z_imag);
+            z_prime_i = mask.select(z_prime_i_tmp, z_prime_i);
+            z_prime_r = mask.select(z_prime_r_tmp, z_prime_r);
+
+            real_2 = z_real * z_real;
+            imag_2 = z_imag * z_imag;
+
+            step_acc = mask.select(mm_ones, mm_zero) + step_acc;
+            orbit_acc = orbit_acc.simd_min(real_2 + imag_2);
+
+            let since_saved = period_check.count();
+            let d_real = z_real - saved_real;
+            let d_imag = z_imag - saved_imag;
+            let cycled = (d_real * d_real + d_imag * d_imag).simd_lt(tolerance_2) & mask;
+            period_acc = cycled.select(Simd::splat(since_saved as f32), period_acc);
+            periodic |= cycled;
+            if period_check.should_save() {
+                saved_real = z_real;
+                saved_imag = z_imag;
+            }
+
+            if !mask.any() {
+                break;
+            }
+        }
+
+        let r = (real_2 + imag_2).sqrt();
+        let dr = (z_prime_r * z_prime_r + z_prime_i * z_prime_i).sqrt();
+
+        let periodic = periodic | known;
+        let period_acc = known.select(known_period, period_acc);
+
+        // split the lanes into two rows of f64 values for coloring
+        let step_acc = periodic.select(Simd::splat(self.max_iter as f32), step_acc);
+        let mut rows = [[[0.0; LANES]; 5]; 2];
+        let values = [step_acc, r, dr, orbit_acc.sqrt(), period_acc];
+        for (quantity, lanes) in values.iter().enumerate() {
+            for (v, &value) in lanes.as_array().iter().enumerate() {
+                rows[v / LANES][quantity][v % LANES] = value as f64;
+            }
+        }
+        // the derivative overflows f32 near the boundary, where an f64 derivative would be
+        // large enough to shade the same as an infinite one
+        for row in rows.iter_mut() {
+            for dr in row[2].iter_mut() {
+                if !dr.is_finite() {
+                    *dr = f64::INFINITY;
+                }
+            }
+        }
+        rows
+    }
+
+    /// calculates LANES pixels of the Multibrot set `z = z^d + c` for any exponent `d`
+    fn calc_pixel_row_multi(
+        &self,
+        width: usize,
+        height: usize,
+        (i, j): (usize, usize),
+        step: usize,
+    ) -> [[f64; LANES]; 5] {
+        let mm_ones: SimdF64 = Simd::splat(1.0);
+        let mm_zero: SimdF64 = Simd::splat(0.0);
+        let exponent_minus_one = (self.exponent_real - 1.0, self.exponent_imag);
+        let exponent_simd = (
+            Simd::splat(self.exponent_real),
+            Simd::splat(self.exponent_imag),
+        );
+        // initialize values
+        let scale = f64::powf(2.0, -self.zoom);
+        let offset_x = self.offset_x.to_f64();
+        let offset_y = self.offset_y.to_f64();
+
+        // c: complex number
+        let c_real = lane_coordinates(i, step, width, scale, offset_x);
+
+        let c_imag = Simd::splat(
+            (j as f64 / height as f64 - 0.5) * scale * (height as f64 / width as f64) + offset_y,
+        );
+
+        // z: complex number
+        let mut z_real = mm_zero;
+        let mut z_imag = mm_zero;
+
+        // z': complex running derivative
+        let mut z_prime_r = mm_ones;
+        let mut z_prime_i = mm_ones;
+
+        // value accumulators for coloring
+        let mut step_acc = mm_zero;
+        let mut orbit_acc = mm_ones;
+
+        // periodicity checking: the saved point of the orbit, and the period of the lanes
+        // which came back to it
+        let tolerance_2 = Simd::splat(period_tolerance(scale, width).powi(2));
+        let mut period_check = PeriodCheck::new();
+        let mut saved_real = z_real;
+        let mut saved_imag = z_imag;
+        let mut period_acc = mm_zero;
+        let mut periodic: Mask<i64, LANES> = Mask::splat(false);
+
+        for _step in 0..self.max_iter {
+            // iterate values, according to z = z^d + c
+            //
+            // z' follows from the power rule (z' = d * z^(d-1) * z' + 1),
+            // and z^d is computed from z^(d-1) so both only need one power
+            let power_minus_one = complex_pow(z_real, z_imag, exponent_minus_one);
+            let (power_real, power_imag) = complex_mul(power_minus_one, (z_real, z_imag));
+
+            let z_real_tmp = power_real + c_real;
+            let z_imag_tmp = power_imag + c_imag;
+
+            let (prime_real, prime_imag) = complex_mul(
+                complex_mul(exponent_simd, power_minus_one),
+                (z_prime_r, z_prime_i),
+            );
+            let z_prime_r_tmp = prime_real + mm_ones;
+            let z_prime_i_tmp = prime_imag;
+
+            let radius_2 = z_real * z_real + z_imag * z_imag;
+
+            // select lanes which have not escaped, or been found to be in a cycle
+            // escape of 1000.0 used to smooth distance estimate
+            let mask = radius_2.simd_lt(Simd::splat(1000.0)) & !periodic;
+
+            // conditionally iterate, only if the pixel has not escaped
+            z_real = mask.select(z_real_tmp, z_real);
+            z_imag = mask.select(z_imag_tmp, z_imag);
+            z_prime_i = mask.select(z_prime_i_tmp, z_prime_i);
+            z_prime_r = mask.select(z_prime_r_tmp, z_prime_r);
+
+            step_acc = mask.select(mm_ones, mm_zero) + step_acc;
+            orbit_acc = orbit_acc.simd_min(z_real * z_real + z_imag * z_imag);
+
+            // lanes which came back to the saved point are in a cycle, and never escape
+            let since_saved = period_check.count();
+            let d_real = z_real - saved_real;
+            let d_imag = z_imag - saved_imag;
+            let cycled = (d_real * d_real + d_imag * d_imag).simd_lt(tolerance_2) & mask;
+            period_acc = cycled.select(Simd::splat(since_saved as f64), period_acc);
+            periodic |= cycled;
+            if period_check.should_save() {
+                saved_real = z_real;
+                saved_imag = z_imag;
+            }
+
+            // finish if all pixels have escaped
+            if !mask.any() {
+                break;
+            }
+        }
+
+        // calculate the absolute value (radius) of z for distance estimation
+        let r = (z_real * z_real + z_imag * z_imag).sqrt();
+        let dr = (z_prime_r * z_prime_r + z_prime_i * z_prime_i).sqrt();
+
+        // extract values necessary for coloring
+        let extracted_step = periodic
+            .select(Simd::splat(self.max_iter as f64), step_acc)
+            .to_array();
+        let extracted_dr = dr.to_array();
+        let extracted_r = r.to_array();
+        let extracted_orbit = orbit_acc.sqrt().to_array();
+        let extracted_period = period_acc.to_array();
+
+        [
+            extracted_step,
+            extracted_r,
+            extracted_dr,
+            extracted_orbit,
+            extracted_period,
+        ]
+    }
+
+    /// calculates LANES pixels as offsets from the high-precision reference orbit,
+    /// which keeps full detail at zooms far beyond the precision of an f64
+    ///
+    /// Each lane iterates `dz = 2*Z*dz + dz^2 + dc`, where `Z` is the reference orbit and
+    /// `dc` the pixel's offset from the image center. When the full value `Z + dz` becomes
+    /// smaller than `dz`, or the reference orbit ends, the lane is rebased onto the start
+    /// of the reference orbit by setting `dz = Z + dz`.
+    ///
+    /// If the orbit has a series approximation, every lane starts at the end of it instead
+    /// of at zero.
+    fn calc_pixel_row_perturbed(
+        &self,
+        orbit: &ReferenceOrbit,
+        width: usize,
+        height: usize,
+        (i, j): (usize, usize),
+        step: usize,
+    ) -> [[f64; LANES]; 5] {
+        let mm_ones: SimdF64 = Simd::splat(1.0);
+        let mm_zero: SimdF64 = Simd::splat(0.0);
+        // initialize values
+        let scale = f64::powf(2.0, -self.zoom);
+        let orbit_end = orbit.z_real.len() - 1;
+
+        // dc: the offset of each pixel from the reference point (image center)
+        let dc_real = lane_coordinates(i, step, width, scale, 0.0);
+        let dc_imag =
+            Simd::splat((j as f64 / height as f64 - 0.5) * scale * (height as f64 / width as f64));
+
+        // dz: the offset of each lane from the reference orbit
+        let mut dz_real = mm_zero;
+        let mut dz_imag = mm_zero;
+        // the position in the reference orbit, per lane, which differs after rebasing
+        let mut ref_index = [0_usize; LANES];
+
+        // z: the full value of the complex number, Z + dz
+        let mut z_real = mm_zero;
+        let mut z_imag = mm_zero;
+
+        // z': complex running derivative
+        let mut z_prime_r = mm_ones;
+        let mut z_prime_i = mm_ones;
+
+        // value accumulators for coloring
+        let mut step_acc = mm_zero;
+        let mut orbit_acc = mm_ones;
+
+        let mut skip = 0;
+        if let Some(series) = &orbit.series {
+            skip = series.skip;
+            // u = dc / dc_max, with u^2 and u^3 for the series terms
+            let u_real = dc_real / Simd::splat(series.dc_max);
+            let u_imag = dc_imag / Simd::splat(series.dc_max);
+            let u2_real = u_real * u_real - u_imag * u_imag;
+            let u2_imag = Simd::splat(2.0) * u_real * u_imag;
+            let u3_real = u2_real * u_real - u2_imag * u_imag;
+            let u3_imag = u2_real * u_imag + u2_imag * u_real;
+            let (a_real, a_imag) = (Simd::splat(series.a.0), Simd::splat(series.a.1));
+            let (b_real, b_imag) = (Simd::splat(series.b.0), Simd::splat(series.b.1));
+            let (c_real, c_imag) = (Simd::splat(series.c.0), Simd::splat(series.c.1));
+
+            // dz = A*u + B*u^2 + C*u^3
+            dz_real = a_real * u_real - a_imag * u_imag + b_real * u2_real - b_imag * u2_imag
+                + c_real * u3_real
+                - c_imag * u3_imag;
+            dz_imag = a_real * u_imag
+                + a_imag * u_real
+                + b_real * u2_imag
+                + b_imag * u2_real
+                + c_real * u3_imag
+                + c_imag * u3_real;
+
+            // z' = d(dz)/dc = (A + 2*B*u + 3*C*u^2) / dc_max
+            let two = Simd::splat(2.0);
+            let three = Simd::splat(3.0);
+            let inv_dc_max = Simd::splat(1.0 / series.dc_max);
+            z_prime_r = (a_real
+                + two * (b_real * u_real - b_imag * u_imag)
+                + three * (c_real * u2_real - c_imag * u2_imag))
+                * inv_dc_max;
+            z_prime_i = (a_imag
+                + two * (b_real * u_imag + b_imag * u_real)
+                + three * (c_real * u2_imag + c_imag * u2_real))
+                * inv_dc_max;
+
+            ref_index = [skip; LANES];
+            z_real = Simd::splat(orbit.z_real[skip]) + dz_real;
+            z_imag = Simd::splat(orbit.z_imag[skip]) + dz_imag;
+            step_acc = Simd::splat(skip as f64);
+            orbit_acc = Simd::splat(series.orbit_min);
+        }
+
+        // z^2: temporary value for optimized computation
+        let mut real_2 = z_real * z_real;
+        let mut imag_2 = z_imag * z_imag;
+
+        for _step in skip..self.max_iter {
+            let ref_real = Simd::from_array(ref_index.map(|n| orbit.z_real[n]));
+            let ref_imag = Simd::from_array(ref_index.map(|n| orbit.z_imag[n]));
+
+            // dz = (2 * Z + dz) * dz + dc
+            let two_z_dz_real = ref_real + ref_real + dz_real;
+            let two_z_dz_imag = ref_imag + ref_imag + dz_imag;
+            let dz_real_tmp = two_z_dz_real * dz_real - two_z_dz_imag * dz_imag + dc_real;
+            let dz_imag_tmp = two_z_dz_real * dz_imag + two_z_dz_imag * dz_real + dc_imag;
+
+            // z' is calculated from the full value of z, as in `calc_pixel_row`
+            let ac_bd = z_real * z_prime_r - z_imag * z_prime_i;
+            let bc_da = z_imag * z_prime_r + z_real * z_prime_i;
+
+            let z_prime_r_tmp = ac_bd + ac_bd + mm_ones;
+            let z_prime_i_tmp = bc_da + bc_da;
+
+            let radius_2 = real_2 + imag_2;
+
+            // select lanes which have not escaped
+            // escape of 1000.0 used to smooth distance estimate
+            let mask = radius_2.simd_lt(Simd::splat(1000.0));
+
+            // conditionally iterate, only if the pixel has not escaped
+            dz_real = mask.select(dz_real_tmp, dz_real);
+            dz_imag = mask.select(dz_imag_tmp, dz_imag);
+            z_prime_i = mask.select(z_prime_i_tmp, z_prime_i);
+            z_prime_r = mask.select(z_prime_r_tmp, z_prime_r);
+            for (lane, index) in ref_index.iter_mut().enumerate() {
+                if mask.test(lane) {
+                    *index += 1;
+                }
+            }
+
+            let ref_real = Simd::from_array(ref_index.map(|n| orbit.z_real[n]));
+            let ref_imag = Simd::from_array(ref_index.map(|n| orbit.z_imag[n]));
+            z_real = ref_real + dz_real;
+            z_imag = ref_imag + dz_imag;
+
+            real_2 = z_real * z_real;
+            imag_2 = z_imag * z_imag;
+
+            // rebase lanes which are closer to zero than to the reference orbit,
+            // or which reached the end of it
+            let at_end = Mask::from_array(ref_index.map(|n| n == orbit_end));
+            let rebase =
+                ((real_2 + imag_2).simd_lt(dz_real * dz_real + dz_imag * dz_imag) | at_end) & mask;
+            if rebase.any() {
+                dz_real = rebase.select(z_real, dz_real);
+                dz_imag = rebase.select(z_imag, dz_imag);
+                for (lane, index) in ref_index.iter_mut().enumerate() {
+                    if rebase.test(lane) {
+                        *index = 0;
+                    }
+                }
+            }
+
+            step_acc = mask.select(mm_ones, mm_zero) + step_acc;
+            orbit_acc = orbit_acc.simd_min(real_2 + imag_2);
+
+            // finish if all pixels have escaped
+            if !mask.any() {
+                break;
+            }
+        }
+
+        // calculate the absolute value (radius) of z for distance estimation
+        let r = (real_2 + imag_2).sqrt();
+        let dr = (z_prime_r * z_prime_r + z_prime_i * z_prime_i).sqrt();
+
+        // extract values necessary for coloring
+        let extracted_step = step_acc.to_array();
+        let extracted_dr = dr.to_array();
+        let extracted_r = r.to_array();
+        let extracted_orbit = orbit_acc.sqrt().to_array();
+        // no periodicity checking, since the full value of z is only known to f64
+        // precision, far coarser than the spacing of the pixels, so orbits which pass
+        // close to a cycle would look the same as orbits in it
+        let extracted_period = [0.0; LANES];
+
+        [
+            extracted_step,
+            extracted_r,
+            extracted_dr,
+            extracted_orbit,
+            extracted_period,
+        ]
+    }
+
+    /// calculates LANES pixels with double-double arithmetic, for zooms where the
+    /// spacing between pixels is too small for an f64 but perturbation is not needed
+    fn calc_pixel_row_double_double(
+        &self,
+        width: usize,
+        height: usize,
+        (i, j): (usize, usize),
+        step: usize,
+    ) -> [[f64; LANES]; 5] {
+        let mm_ones: SimdF64 = Simd::splat(1.0);
+        let mm_zero: SimdF64 = Simd::splat(0.0);
+        let dd_zero = DoubleDoubleSimd::splat((0.0, 0.0));
+        // initialize values
+        let scale = f64::powf(2.0, -self.zoom);
+
+        // c: complex number, the pixel offset added to the exact center
+        let c_real = DoubleDoubleSimd::splat(self.offset_x.to_double_double())
+            .add_f64(lane_coordinates(i, step, width, scale, 0.0));
+        let c_imag = DoubleDoubleSimd::splat(self.offset_y.to_double_double()).add_f64(
+            Simd::splat((j as f64 / height as f64 - 0.5) * scale * (height as f64 / width as f64)),
+        );
+
+        // z: complex number
+        let mut z_real = dd_zero;
+        let mut z_imag = dd_zero;
+
+        // z': complex running derivative, which only needs f64 precision
+        let mut z_prime_r = mm_ones;
+        let mut z_prime_i = mm_ones;
+
+        // z^2: temporary value for optimized computation
+        let mut real_2 = dd_zero;
+        let mut imag_2 = dd_zero;
+
+        // value accumulators for coloring
+        let mut step_acc = mm_zero;
+        let mut orbit_acc = mm_ones;
+
+        // periodicity checking: the saved point of the orbit, and the period of the lanes
+        // which came back to it
+        let tolerance_2 = Simd::splat(period_tolerance(scale, width).powi(2));
+        let mut period_check = PeriodCheck::new();
+        let mut saved_real = z_real;
+        let mut saved_imag = z_imag;
+        let mut period_acc = mm_zero;
+        let mut periodic: Mask<i64, LANES> = Mask::splat(false);
+
+        for _step in 0..self.max_iter {
+            // iterate values, according to z = z^2 + c, as in `calc_pixel_row`
+            let z_imag_tmp = (z_real * z_imag).double() + c_imag;
+            let z_real_tmp = real_2 - imag_2 + c_real;
+
+            // intermediate values for z', from the high part of z
+            let ac_bd = z_real.hi * z_prime_r - z_imag.hi * z_prime_i;
+            let bc_da = z_imag.hi * z_prime_r + z_real.hi * z_prime_i;
+
+            let z_prime_r_tmp = ac_bd + ac_bd + mm_ones;
+            let z_prime_i_tmp = bc_da + bc_da;
+
+            let radius_2 = real_2.hi + imag_2.hi;
+
+            // select lanes which have not escaped, or been found to be in a cycle
+            // escape of 1000.0 used to smooth distance estimate
+            let mask = radius_2.simd_lt(Simd::splat(1000.0)) & !periodic;
+
+            // conditionally iterate, only if the pixel has not escaped
+            z_real = DoubleDoubleSimd::select(mask, z_real_tmp, z_real);
+            z_imag = DoubleDoubleSimd::select(mask, z_imag_tmp, z_imag);
+            z_prime_i = mask.select(z_prime_i_tmp, z_prime_i);
+            z_prime_r = mask.select(z_prime_r_tmp, z_prime_r);
+
+            real_2 = z_real * z_real;
+            imag_2 = z_imag * z_imag;
+
+            step_acc = mask.select(mm_ones, mm_zero) + step_acc;
+            orbit_acc = orbit_acc.simd_min(real_2.hi + imag_2.hi);
+
+            // lanes which came back to the saved point are in a cycle, and never escape
+            let since_saved = period_check.count();
+            let d_real = (z_real - saved_real).hi;
+            let d_imag = (z_imag - saved_imag).hi;
+            let cycled = (d_real * d_real + d_imag * d_imag).simd_lt(tolerance_2) & mask;
+            period_acc = cycled.select(Simd::splat(since_saved as f64), period_acc);
+            periodic |= cycled;
+            if period_check.should_save() {
+                saved_real = z_real;
+                saved_imag = z_imag;
+            }
+
+            // finish if all pixels have escaped
+            if !mask.any() {
+                break;
+            }
+        }
+
+        // calculate the absolute value (radius) of z for distance estimation
+        let r = (real_2.hi + imag_2.hi).sqrt();
+        let dr = (z_prime_r * z_prime_r + z_prime_i * z_prime_i).sqrt();
+
+        // extract values necessary for coloring
+        let extracted_step = periodic
+            .select(Simd::splat(self.max_iter as f64), step_acc)
+            .to_array();
+        let extracted_dr = dr.to_array();
+        let extracted_r = r.to_array();
+        let extracted_orbit = orbit_acc.sqrt().to_array();
+        let extracted_period = period_acc.to_array();
+
+        [
+            extracted_step,
+            extracted_r,
+            extracted_dr,
+            extracted_orbit,
+            extracted_period,
+        ]
+    }
 }
 
 impl GeneratorParameters for MandelParameters {
-    type Intermediate = [f64; 4];
+    /// `[step, r, dr, orbit, period]`, where `period` is the length of the cycle an
+    /// interior point was found to be in, or 0
+    type Intermediate = [[f64; LANES]; 5];
     /// calculates the color for LANES number of pixels from `[i,j]` to `[i,j+LANES]` of the image
     fn calc_pixel_row(
         &self,
         width: usize,
         height: usize,
         (i, j): (usize, usize),
-    ) -> [Self::Intermediate; LANES] {
-        let mm_ones: f64x4 = Simd::splat(1.0);
-        let mm_zero: f64x4 = Simd::splat(0.0);
+        step: usize,
+    ) -> Self::Intermediate {
+        if !self.is_quadratic() {
+            return self.calc_pixel_row_multi(width, height, (i, j), step);
+        }
+        if let Some(orbit) = &self.reference_orbit {
+            return self.calc_pixel_row_perturbed(orbit, width, height, (i, j), step);
+        }
+        if self.precision.resolve(self.zoom) == Precision::DoubleDouble {
+            return self.calc_pixel_row_double_double(width, height, (i, j), step);
+        }
+        let mm_ones: SimdF64 = Simd::splat(1.0);
+        let mm_zero: SimdF64 = Simd::splat(0.0);
         // initialize values
         let scale = f64::powf(2.0, -self.zoom);
+        let offset_x = self.offset_x.to_f64();
+        let offset_y = self.offset_y.to_f64();
 
         // c: complex number
-        let c_real = Simd::from_array([
-            ((i + 0) as f64 / width as f64 - 0.5) * scale + self.offset_x,
-            ((i + 1) as f64 / width as f64 - 0.5) * scale + self.offset_x,
-            ((i + 2) as f64 / width as f64 - 0.5) * scale + self.offset_x,
-            ((i + 3) as f64 / width as f64 - 0.5) * scale + self.offset_x,
-        ]);
+        let c_real = lane_coordinates(i, step, width, scale, offset_x);
 
         let c_imag = Simd::splat(
-            (j as f64 / height as f64 - 0.5) * scale * (height as f64 / width as f64)
-                + self.offset_y,
+            (j as f64 / height as f64 - 0.5) * scale * (height as f64 / width as f64) + offset_y,
         );
 
         // z: complex number
@@ -66,7 +696,24 @@ impl GeneratorParameters for MandelParameters {
         let mut step_acc = mm_zero;
         let mut orbit_acc = mm_ones;
 
-        for _step in 0..self.max_iter {
+        // periodicity checking: the saved point of the orbit, and the period of the lanes
+        // which came back to it
+        let tolerance_2 = Simd::splat(period_tolerance(scale, width).powi(2));
+        let mut period_check = PeriodCheck::new();
+        let mut saved_real = z_real;
+        let mut saved_imag = z_imag;
+        let mut period_acc = mm_zero;
+        let mut periodic: Mask<i64, LANES> = Mask::splat(false);
+
+        // lanes in the main cardioid or the period-2 bulb are known to never escape, so
+        // they are only iterated for long enough to find their orbit trap
+        let known_period = bulb_period(c_real, c_imag);
+        let known = known_period.simd_gt(mm_zero);
+
+        for iteration in 0..self.max_iter {
+            if iteration == TRAP_ITERATIONS {
+                periodic |= known;
+            }
             // iterate values, according to z = z^2 + c
             //
             // uses an optimized computation method from wikipedia for z:
@@ -91,9 +738,9 @@ impl GeneratorParameters for MandelParameters {
 
             let radius_2 = real_2 + imag_2;
 
-            // select lanes which have not escaped
+            // select lanes which have not escaped, or been found to be in a cycle
             // escape of 1000.0 used to smooth distance estimate
-            let mask = radius_2.simd_lt(Simd::splat(1000.0));
+            let mask = radius_2.simd_lt(Simd::splat(1000.0)) & !periodic;
 
             // conditionally iterate, only if the pixel has not escaped
             z_real = mask.select(z_real_tmp, z_real);
@@ -107,6 +754,18 @@ impl GeneratorParameters for MandelParameters {
             step_acc = mask.select(mm_ones, mm_zero) + step_acc;
             orbit_acc = orbit_acc.simd_min(real_2 + imag_2);
 
+            // lanes which came back to the saved point are in a cycle, and never escape
+            let since_saved = period_check.count();
+            let d_real = z_real - saved_real;
+            let d_imag = z_imag - saved_imag;
+            let cycled = (d_real * d_real + d_imag * d_imag).simd_lt(tolerance_2) & mask;
+            period_acc = cycled.select(Simd::splat(since_saved as f64), period_acc);
+            periodic |= cycled;
+            if period_check.should_save() {
+                saved_real = z_real;
+                saved_imag = z_imag;
+            }
+
             // finish if all pixels have escaped
             if !mask.any() {
                 break;
@@ -117,35 +776,80 @@ impl GeneratorParameters for MandelParameters {
         let r = (real_2 + imag_2).sqrt();
         let dr = (z_prime_r * z_prime_r + z_prime_i * z_prime_i).sqrt();
 
+        let periodic = periodic | known;
+        let period_acc = known.select(known_period, period_acc);
+
         // extract values necessary for coloring
-        let extracted_step = step_acc.to_array();
+        let extracted_step = periodic
+            .select(Simd::splat(self.max_iter as f64), step_acc)
+            .to_array();
         let extracted_dr = dr.to_array();
         let extracted_r = r.to_array();
         let extracted_orbit = orbit_acc.sqrt().to_array();
+        let extracted_period = period_acc.to_array();
 
-        [extracted_step, extracted_r, extracted_dr, extracted_orbit]
+        [
+            extracted_step,
+            extracted_r,
+            extracted_dr,
+            extracted_orbit,
+            extracted_period,
+        ]
     }
 
-    fn shade_pixel_row(&self, parameters: [Self::Intermediate; LANES]) -> [Pixel; LANES] {
+    fn calc_pixel_row_pair(
+        &self,
+        width: usize,
+        height: usize,
+        (i, j): (usize, usize),
+        step: usize,
+    ) -> [Self::Intermediate; 2] {
+        if self.is_quadratic()
+            && self.reference_orbit.is_none()
+            // an explicitly chosen precision is kept, only `Auto` may pick f32
+            && self.precision == Precision::Auto
+            && self.precision.resolve(self.zoom) == Precision::Double
+            && self.f32_is_enough(width)
+        {
+            return self.calc_pixel_row_pair_f32(width, height, (i, j), step);
+        }
+        [
+            self.calc_pixel_row(width, height, (i, j), step),
+            self.calc_pixel_row(width, height, (i + LANES * step, j), step),
+        ]
+    }
+
+    fn shade_pixel_row(&self, parameters: Self::Intermediate) -> [Pixel; LANES] {
         let scale = f64::powf(2.0, -self.zoom);
-        let mut row: [Pixel; LANES] = [[0; BYTES_PER_PIXEL]; LANES];
-        for v in 0..parameters.len() {
+        let ln_degree = degree_ln(self.exponent_real, self.exponent_imag);
+        let mut row: [Pixel; LANES] = [[0.0; CHANNELS]; LANES];
+        for v in 0..LANES {
             let step = parameters[0][v];
             let r = parameters[1][v];
             let dr = parameters[2][v];
             let orbit = parameters[3][v];
+            let period = parameters[4][v];
             // distance estimation: 0.5 * log(r) * r/dr
             let dist_est = 0.5 * (r).ln() * r / dr;
             // a 'glow' effect based on distance (manually adjusted to taste and to adjust to zoom level)
             let glow = (-(dist_est / scale).ln() + self.glow_spread) * self.glow_strength * 0.1;
-            // a smoothed version of the iteration count: i + (1 - ln(ln(r))/ln(2))
-            let smoothed_step = step + (1.0 - ((r).ln()).ln() / f64::ln(2.0));
+            // a smoothed version of the iteration count: i + (1 - ln(ln(r))/ln(d))
+            let smoothed_step = step + (1.0 - ((r).ln()).ln() / ln_degree);
 
             if step as usize >= self.max_iter {
-                // color the inside using orbit trap method
+                // color the inside using orbit trap method, with a hue for each period
+                // of the cycle the orbit settles into if there is one
+                let (hue, saturation) = if self.period_coloring && period > 0.0 {
+                    (
+                        (period * PERIOD_HUE_STEP + self.color_offset).fract(),
+                        self.saturation.clamp(0.0, 1.0),
+                    )
+                } else {
+                    (0.0, 0.0)
+                };
                 row[v] = hsl2rgb(
-                    0.0,
-                    0.0,
+                    hue,
+                    saturation,
                     ((orbit)
                         * self.brightness
                         * self.internal_brightness
@@ -171,11 +875,59 @@ impl GeneratorParameters for MandelParameters {
         row
     }
 
+    fn escape_steps(&self, parameters: &Self::Intermediate) -> Option<[f64; LANES]> {
+        Some(parameters[0])
+    }
+
+    fn interior(&self, parameters: &Self::Intermediate) -> Option<[bool; LANES]> {
+        Some(parameters[0].map(|step| step as usize >= self.max_iter))
+    }
+
+    fn boundary_distances(
+        &self,
+        width: usize,
+        parameters: &Self::Intermediate,
+    ) -> Option<[f64; LANES]> {
+        let scale = f64::powf(2.0, -self.zoom);
+        Some(pixel_distances(
+            [parameters[0], parameters[1], parameters[2]],
+            self.max_iter,
+            scale,
+            width,
+        ))
+    }
+
     fn needs_recompute(settings: &Self, old_settings: &Self) -> bool {
         return settings.max_iter != old_settings.max_iter
             || settings.zoom != old_settings.zoom
             || settings.offset_y != old_settings.offset_y
-            || settings.offset_x != old_settings.offset_x;
+            || settings.offset_x != old_settings.offset_x
+            || settings.precision != old_settings.precision
+            || settings.deep_zoom != old_settings.deep_zoom
+            || settings.series_tolerance != old_settings.series_tolerance
+            || settings.exponent_real != old_settings.exponent_real
+            || settings.exponent_imag != old_settings.exponent_imag;
+    }
+
+    fn prepare(&mut self, width: usize, height: usize, _context: &PrepareContext) {
+        // perturbation is only implemented for z^2 + c
+        self.reference_orbit = if self.deep_zoom && self.is_quadratic() {
+            let mut orbit =
+                ReferenceOrbit::compute(&self.offset_x, &self.offset_y, self.zoom, self.max_iter);
+            if self.series_tolerance > 0.0 {
+                // the distance from the center to a corner of the image
+                let aspect = height as f64 / width as f64;
+                let dc_max = f64::powf(2.0, -self.zoom) * 0.5 * (1.0 + aspect * aspect).sqrt();
+                orbit.series = Some(SeriesApproximation::compute(
+                    &orbit,
+                    dc_max,
+                    self.series_tolerance,
+                ));
+            }
+            Some(Arc::new(orbit))
+        } else {
+            None
+        };
     }
 }
 
@@ -184,8 +936,13 @@ impl Default for MandelParameters {
         Self {
             max_iter: 250,
             zoom: -2.0,
-            offset_x: -0.5,
-            offset_y: 0.0,
+            offset_x: BigFixed::from(-0.5),
+            offset_y: BigFixed::from(0.0),
+            exponent_real: 2.0,
+            exponent_imag: 0.0,
+            precision: Precision::Auto,
+            deep_zoom: false,
+            series_tolerance: 0.001,
             saturation: 1.0,
             color_frequency: 1.0,
             color_offset: 0.0,
@@ -193,6 +950,8 @@ impl Default for MandelParameters {
             glow_strength: 1.0,
             brightness: 2.0,
             internal_brightness: 1.0,
+            period_coloring: false,
+            reference_orbit: None,
         }
     }
 }
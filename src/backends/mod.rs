@@ -1,9 +1,40 @@
+mod antialiasing;
+mod big_fixed;
+mod buddhabrot;
+mod burning_ship;
+mod custom;
+mod double_double;
+mod expression;
 mod image_generator;
 mod julia;
+mod lyapunov;
 mod mandel;
+mod newton;
+mod nova;
+mod output;
+mod perturbation;
+mod phoenix;
+mod polynomial;
+mod thread_pool;
+mod tricorn;
+mod tricorn_julia;
 mod utilities;
 
+pub use antialiasing::{Antialiasing, AntialiasingMode, DownsampleFilter, MAX_SAMPLES};
+pub use big_fixed::BigFixed;
+pub use buddhabrot::BuddhabrotParameters;
+pub use burning_ship::{BurningShipParameters, ShipVariant};
+pub use custom::CustomParameters;
+pub use double_double::Precision;
 pub use julia::JuliaParameters;
+pub use lyapunov::LyapunovParameters;
 pub use mandel::MandelParameters;
+pub use newton::NewtonParameters;
 
 pub use self::image_generator::{GeneratorParameters, ImageGenerator};
+pub use nova::NovaParameters;
+pub use output::{PixelFormat, Transparency, TransparencyMode};
+pub use phoenix::PhoenixParameters;
+pub use thread_pool::{Priority, ThreadPool};
+pub use tricorn::TricornParameters;
+pub use tricorn_julia::TricornJuliaParameters;
@@ -0,0 +1,189 @@
+use std::simd::{Mask, Simd, SimdPartialOrd, StdFloat};
+
+use druid::{Data, Lens};
+use serde::{Deserialize, Serialize};
+
+use super::image_generator::{GeneratorParameters, Pixel, SimdF64, CHANNELS, LANES};
+use super::polynomial::Polynomial;
+use super::utilities::{hsl2rgb, lane_coordinates};
+
+/// a point closer than this to a root is considered to have converged to it
+const CONVERGENCE_RADIUS: f64 = 1e-6;
+
+#[derive(Clone, PartialEq, Data, Lens, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NewtonParameters {
+    // image parameters
+    pub max_iter: usize,
+    pub zoom: f64,
+    pub offset_x: f64,
+    pub offset_y: f64,
+    pub polynomial: Polynomial,
+    pub relaxation: f64,
+    // colors
+    pub saturation: f64,
+    pub color_frequency: f64,
+    pub color_offset: f64,
+    pub brightness: f64,
+}
+
+impl GeneratorParameters for NewtonParameters {
+    type Intermediate = [[f64; LANES]; 4];
+    /// calculates the color for LANES number of pixels from `[i,j]` to `[i,j+LANES]` of the image
+    fn calc_pixel_row(
+        &self,
+        width: usize,
+        height: usize,
+        (i, j): (usize, usize),
+        step: usize,
+    ) -> Self::Intermediate {
+        let mm_ones: SimdF64 = Simd::splat(1.0);
+        let mm_zero: SimdF64 = Simd::splat(0.0);
+        let radius_2 = Simd::splat(CONVERGENCE_RADIUS * CONVERGENCE_RADIUS);
+        let relaxation = Simd::splat(self.relaxation);
+        // initialize values
+        let scale = f64::powf(2.0, -self.zoom);
+
+        // z: complex number
+        let mut z_real = lane_coordinates(i, step, width, scale, self.offset_x);
+
+        let mut z_imag = Simd::splat(
+            (j as f64 / height as f64 - 0.5) * scale * (height as f64 / width as f64)
+                + self.offset_y,
+        );
+
+        // z of the previous step, for smoothing the iteration count
+        let mut z_prev_real = z_real;
+        let mut z_prev_imag = z_imag;
+
+        // value accumulators for coloring
+        let mut step_acc = mm_zero;
+        // the index of the root each lane converged to, or -1
+        let mut root_acc: SimdF64 = Simd::splat(-1.0);
+        let mut dist_acc = mm_zero;
+        let mut dist_prev_acc = mm_zero;
+        let mut converged: Mask<i64, LANES> = Mask::splat(false);
+
+        for _step in 0..self.max_iter {
+            // iterate values, according to z = z - a * p(z) / p'(z)
+            //
+            // with p given by its roots, p'(z) / p(z) = sum(1 / (z - r_k)),
+            // which avoids expanding the polynomial into its coefficients
+            let mut sum_real = mm_zero;
+            let mut sum_imag = mm_zero;
+            for (k, &(root_real, root_imag)) in self.polynomial.roots().iter().enumerate() {
+                let d_real = z_real - Simd::splat(root_real);
+                let d_imag = z_imag - Simd::splat(root_imag);
+                let d_2 = d_real * d_real + d_imag * d_imag;
+
+                // mark lanes that have reached this root
+                let near = d_2.simd_lt(radius_2) & !converged;
+                let d_prev_real = z_prev_real - Simd::splat(root_real);
+                let d_prev_imag = z_prev_imag - Simd::splat(root_imag);
+                root_acc = near.select(Simd::splat(k as f64), root_acc);
+                dist_acc = near.select(d_2.sqrt(), dist_acc);
+                dist_prev_acc = near.select(
+                    (d_prev_real * d_prev_real + d_prev_imag * d_prev_imag).sqrt(),
+                    dist_prev_acc,
+                );
+                converged |= near;
+
+                // 1 / d = conj(d) / |d|^2
+                sum_real += d_real / d_2;
+                sum_imag -= d_imag / d_2;
+            }
+
+            // finish if all pixels have converged
+            if converged.all() {
+                break;
+            }
+
+            // p(z) / p'(z) = 1 / sum
+            let sum_2 = sum_real * sum_real + sum_imag * sum_imag;
+            let z_real_tmp = z_real - relaxation * sum_real / sum_2;
+            let z_imag_tmp = z_imag + relaxation * sum_imag / sum_2;
+
+            // conditionally iterate, only if the pixel has not converged
+            z_prev_real = z_real;
+            z_prev_imag = z_imag;
+            z_real = converged.select(z_real, z_real_tmp);
+            z_imag = converged.select(z_imag, z_imag_tmp);
+
+            step_acc = converged.select(mm_zero, mm_ones) + step_acc;
+        }
+
+        // extract values necessary for coloring
+        let extracted_step = step_acc.to_array();
+        let extracted_root = root_acc.to_array();
+        let extracted_dist = dist_acc.to_array();
+        let extracted_dist_prev = dist_prev_acc.to_array();
+
+        [
+            extracted_step,
+            extracted_root,
+            extracted_dist,
+            extracted_dist_prev,
+        ]
+    }
+
+    fn shade_pixel_row(&self, parameters: Self::Intermediate) -> [Pixel; LANES] {
+        let root_count = self.polynomial.roots().len() as f64;
+        let mut row: [Pixel; LANES] = [[0.0; CHANNELS]; LANES];
+        for v in 0..LANES {
+            let step = parameters[0][v];
+            let root = parameters[1][v];
+            let dist = parameters[2][v];
+            let dist_prev = parameters[3][v];
+
+            if root < 0.0 {
+                // points that never converged stay black
+                continue;
+            }
+            // a smoothed version of the iteration count, from how far into the last step
+            // the point crossed the convergence radius (on a log scale)
+            let fraction = (dist_prev / CONVERGENCE_RADIUS).ln() / (dist_prev / dist).ln();
+            let smoothed_step = step - 1.0 + fraction.clamp(0.0, 1.0);
+            let smoothed_step = if smoothed_step.is_finite() {
+                smoothed_step.max(0.0)
+            } else {
+                step
+            };
+
+            row[v] = hsl2rgb(
+                // each root gets an evenly spaced hue
+                (root / root_count + self.color_offset).rem_euclid(1.0),
+                self.saturation.clamp(0.0, 1.0),
+                // points that converge quickly are brighter
+                ((-smoothed_step * self.color_frequency * 0.1).exp() * self.brightness)
+                    .clamp(0.0, 1.0),
+            )
+        }
+        row
+    }
+
+    fn needs_recompute(settings: &Self, old_settings: &Self) -> bool {
+        settings.max_iter != old_settings.max_iter
+            || settings.zoom != old_settings.zoom
+            || settings.offset_y != old_settings.offset_y
+            || settings.offset_x != old_settings.offset_x
+            || settings.polynomial != old_settings.polynomial
+            || settings.relaxation != old_settings.relaxation
+    }
+}
+
+impl Default for NewtonParameters {
+    fn default() -> Self {
+        Self {
+            max_iter: 100,
+            zoom: -2.0,
+            offset_x: 0.0,
+            offset_y: 0.0,
+            polynomial: Polynomial::default(),
+            relaxation: 1.0,
+            saturation: 1.0,
+            color_frequency: 1.0,
+            color_offset: 0.0,
+            brightness: 1.0,
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::f64::consts::PI;
+use std::simd::{Mask, Simd, SimdPartialOrd, StdFloat};
+
+use druid::{Data, Lens};
+use serde::{Deserialize, Serialize};
+
+use super::image_generator::{GeneratorParameters, Pixel, SimdF64, CHANNELS, LANES};
+use super::utilities::{complex_mul, complex_pow, hsl2rgb, lane_coordinates};
+
+/// a point that moves less than this in one step is considered to have converged
+const CONVERGENCE_RADIUS: f64 = 1e-6;
+
+/// the Nova fractal, a relaxed Newton's method for `z^p - 1` with c added to every step:
+/// `z = z - a * (z^p - 1) / (p * z^(p-1)) + c`.
+///
+/// In the parameter plane c is the pixel and z starts at 1, a critical point of the
+/// iteration; in the Julia plane z is the pixel and c is constant.
+#[derive(Clone, PartialEq, Data, Lens, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NovaParameters {
+    // image parameters
+    pub max_iter: usize,
+    pub zoom: f64,
+    pub offset_x: f64,
+    pub offset_y: f64,
+    pub exponent: f64,
+    pub relaxation: f64,
+    pub julia: bool,
+    pub constant_real: f64,
+    pub constant_imag: f64,
+    // colors
+    pub saturation: f64,
+    pub color_frequency: f64,
+    pub color_offset: f64,
+    pub brightness: f64,
+    pub internal_brightness: f64,
+}
+
+impl GeneratorParameters for NovaParameters {
+    type Intermediate = [[f64; LANES]; 3];
+    /// calculates the color for LANES number of pixels from `[i,j]` to `[i,j+LANES]` of the image
+    fn calc_pixel_row(
+        &self,
+        width: usize,
+        height: usize,
+        (i, j): (usize, usize),
+        step: usize,
+    ) -> Self::Intermediate {
+        let mm_ones: SimdF64 = Simd::splat(1.0);
+        let mm_zero: SimdF64 = Simd::splat(0.0);
+        let radius_2 = Simd::splat(CONVERGENCE_RADIUS * CONVERGENCE_RADIUS);
+        let relaxation = Simd::splat(self.relaxation);
+        let exponent = Simd::splat(self.exponent);
+        // initialize values
+        let scale = f64::powf(2.0, -self.zoom);
+
+        // the pixel, as a complex number
+        let pixel_real = lane_coordinates(i, step, width, scale, self.offset_x);
+
+        let pixel_imag = Simd::splat(
+            (j as f64 / height as f64 - 0.5) * scale * (height as f64 / width as f64)
+                + self.offset_y,
+        );
+
+        // c and the starting z: complex numbers
+        let (c_real, c_imag, mut z_real, mut z_imag) = if self.julia {
+            (
+                Simd::splat(self.constant_real),
+                Simd::splat(self.constant_imag),
+                pixel_real,
+                pixel_imag,
+            )
+        } else {
+            (pixel_real, pixel_imag, mm_ones, mm_zero)
+        };
+
+        // value accumulators for coloring
+        let mut step_acc = mm_zero;
+        // the length of the last step, and of the one before it
+        let mut dist_acc = mm_zero;
+        let mut dist_prev_acc = mm_zero;
+        let mut converged: Mask<i64, LANES> = Mask::splat(false);
+
+        for _step in 0..self.max_iter {
+            // iterate values, according to z = z - a * (z^p - 1) / (p * z^(p-1)) + c
+            let (w_real, w_imag) = complex_pow(z_real, z_imag, (self.exponent - 1.0, 0.0));
+            let (zp_real, zp_imag) = complex_mul((w_real, w_imag), (z_real, z_imag));
+
+            // (z^p - 1) / (p * w) = (z^p - 1) * conj(w) / (p * |w|^2)
+            let (num_real, num_imag) = complex_mul((zp_real - mm_ones, zp_imag), (w_real, -w_imag));
+            let denominator = exponent * (w_real * w_real + w_imag * w_imag);
+            let z_real_tmp = z_real - relaxation * num_real / denominator + c_real;
+            let z_imag_tmp = z_imag - relaxation * num_imag / denominator + c_imag;
+
+            // mark lanes that have stopped moving
+            let d_real = z_real_tmp - z_real;
+            let d_imag = z_imag_tmp - z_imag;
+            let dist = (d_real * d_real + d_imag * d_imag).sqrt();
+            let near = (dist * dist).simd_lt(radius_2) & !converged;
+
+            // conditionally iterate, only if the pixel has not converged
+            z_real = converged.select(z_real, z_real_tmp);
+            z_imag = converged.select(z_imag, z_imag_tmp);
+            dist_prev_acc = converged.select(dist_prev_acc, dist_acc);
+            dist_acc = converged.select(dist_acc, dist);
+            converged |= near;
+
+            step_acc = converged.select(mm_zero, mm_ones) + step_acc;
+
+            // finish if all pixels have converged
+            if converged.all() {
+                break;
+            }
+        }
+
+        // extract values necessary for coloring
+        let extracted_step = step_acc.to_array();
+        let extracted_dist = dist_acc.to_array();
+        let extracted_dist_prev = dist_prev_acc.to_array();
+
+        [extracted_step, extracted_dist, extracted_dist_prev]
+    }
+
+    fn shade_pixel_row(&self, parameters: Self::Intermediate) -> [Pixel; LANES] {
+        let mut row: [Pixel; LANES] = [[0.0; CHANNELS]; LANES];
+        for v in 0..LANES {
+            let step = parameters[0][v];
+            let dist = parameters[1][v];
+            let dist_prev = parameters[2][v];
+
+            if step as usize >= self.max_iter {
+                // points that never converged are shaded by how far they still move
+                row[v] = hsl2rgb(
+                    0.0,
+                    0.0,
+                    ((-dist).exp() * self.internal_brightness * 0.1).clamp(0.0, 1.0),
+                );
+                continue;
+            }
+            // a smoothed version of the iteration count, from how far into the last step
+            // the point crossed the convergence radius (on a log scale)
+            let fraction = (dist_prev / CONVERGENCE_RADIUS).ln() / (dist_prev / dist).ln();
+            let smoothed_step = step + fraction.clamp(0.0, 1.0);
+            let smoothed_step = if smoothed_step.is_finite() {
+                smoothed_step.max(1.0)
+            } else {
+                step.max(1.0)
+            };
+
+            row[v] = hsl2rgb(
+                // color hue based on an sinusoidal step counter, offset to a [0,1] range
+                (((smoothed_step.ln() * self.color_frequency - self.color_offset * 2.0 * PI)
+                    .sin())
+                    * 0.5
+                    + 0.5)
+                    .clamp(0.0, 1.0),
+                self.saturation.clamp(0.0, 1.0),
+                // points that take longer to converge are brighter, to highlight the boundary
+                ((1.0 - (-smoothed_step * 0.1).exp()) * self.brightness).clamp(0.0, 1.0),
+            )
+        }
+        row
+    }
+
+    fn needs_recompute(settings: &Self, old_settings: &Self) -> bool {
+        settings.max_iter != old_settings.max_iter
+            || settings.zoom != old_settings.zoom
+            || settings.offset_y != old_settings.offset_y
+            || settings.offset_x != old_settings.offset_x
+            || settings.exponent != old_settings.exponent
+            || settings.relaxation != old_settings.relaxation
+            || settings.julia != old_settings.julia
+            || settings.constant_real != old_settings.constant_real
+            || settings.constant_imag != old_settings.constant_imag
+    }
+}
+
+impl Default for NovaParameters {
+    fn default() -> Self {
+        Self {
+            max_iter: 250,
+            zoom: -1.5,
+            offset_x: -0.3,
+            offset_y: 0.0,
+            exponent: 3.0,
+            relaxation: 1.0,
+            julia: false,
+            constant_real: -0.5,
+            constant_imag: 0.0,
+            saturation: 1.0,
+            color_frequency: 1.0,
+            color_offset: 0.0,
+            brightness: 1.5,
+            internal_brightness: 1.0,
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use std::{fs::File, io::BufWriter, str::FromStr};
+
+use druid::{Data, Lens};
+use image::{
+    error::{EncodingError, ImageFormatHint},
+    ImageBuffer, ImageError, ImageFormat, ImageResult, Rgb, Rgb32FImage, Rgba, Rgba32FImage,
+};
+use serde::{Deserialize, Serialize};
+use tiff::encoder::{colortype, TiffEncoder};
+
+use super::image_generator::Pixel;
+
+/// the samples written to saved images
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Data, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PixelFormat {
+    Rgb8,
+    /// for PNG and TIFF, which avoids banding in smooth gradients
+    Rgb16,
+    /// for OpenEXR and TIFF, which keeps the colors at full precision
+    Rgb32F,
+}
+
+impl FromStr for PixelFormat {
+    type Err = String;
+
+    /// parses the same lowercase names that are used in scene files
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rgb8" => Ok(PixelFormat::Rgb8),
+            "rgb16" => Ok(PixelFormat::Rgb16),
+            "rgb32f" => Ok(PixelFormat::Rgb32F),
+            _ => Err(format!("unknown pixel format '{}'", s)),
+        }
+    }
+}
+
+/// which pixels of saved images are transparent
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Data, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransparencyMode {
+    /// every pixel is opaque, and images are saved without an alpha channel
+    Off,
+    /// the points that reach the iteration limit
+    Interior,
+    /// the points that escape
+    Exterior,
+}
+
+impl FromStr for TransparencyMode {
+    type Err = String;
+
+    /// parses the same lowercase names that are used in scene files
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(TransparencyMode::Off),
+            "interior" => Ok(TransparencyMode::Interior),
+            "exterior" => Ok(TransparencyMode::Exterior),
+            _ => Err(format!("unknown transparency mode '{}'", s)),
+        }
+    }
+}
+
+/// the transparency of an image, see `ImageGenerator::set_transparency`
+#[derive(Clone, Copy, Debug, PartialEq, Data, Lens, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Transparency {
+    pub mode: TransparencyMode,
+    /// whether the opacity of escaping points follows their distance estimate
+    pub soft_edges: bool,
+    /// the distance to the boundary of the fractal, in pixels, over which soft edges fade
+    pub edge_width: f64,
+}
+
+impl Default for Transparency {
+    fn default() -> Self {
+        Transparency {
+            mode: TransparencyMode::Off,
+            soft_edges: false,
+            edge_width: 2.0,
+        }
+    }
+}
+
+impl Transparency {
+    /// the alpha of a point `distance` pixels from the boundary of the fractal,
+    /// from 0 for transparent to 1 for opaque
+    pub fn opacity(&self, interior: bool, distance: f64) -> f32 {
+        // how far an escaping point is from the boundary, as a fraction of the edge
+        let outside = if self.soft_edges {
+            (distance / self.edge_width).clamp(0.0, 1.0) as f32
+        } else {
+            1.0
+        };
+        match (self.mode, interior) {
+            (TransparencyMode::Off, _) => 1.0,
+            (TransparencyMode::Interior, true) => 0.0,
+            (TransparencyMode::Interior, false) => outside,
+            (TransparencyMode::Exterior, true) => 1.0,
+            (TransparencyMode::Exterior, false) => 1.0 - outside,
+        }
+    }
+}
+
+/// a channel from 0 to 1 as an 8-bit sample, for display and 8-bit images
+pub fn to_u8(channel: f32) -> u8 {
+    (channel * 255.0) as u8
+}
+
+fn to_u16(channel: f32) -> u16 {
+    (channel.clamp(0.0, 1.0) * 65535.0).round() as u16
+}
+
+/// checks that the file format chosen by the extension of `filename` can store samples
+/// in the given format, and an alpha channel with `alpha`, so that a render is not
+/// started only to fail when it is saved
+pub fn check_output_format(filename: &str, format: PixelFormat, alpha: bool) -> Result<(), String> {
+    let file_format = ImageFormat::from_path(filename)
+        .map_err(|_| format!("'{}' does not have a known image extension", filename))?;
+    let supported = match (format, file_format) {
+        (_, ImageFormat::OpenExr | ImageFormat::Tiff) => true,
+        (PixelFormat::Rgb8 | PixelFormat::Rgb16, ImageFormat::Png) => true,
+        (
+            PixelFormat::Rgb8,
+            ImageFormat::Jpeg | ImageFormat::Bmp | ImageFormat::Gif | ImageFormat::Tga,
+        ) => !alpha,
+        _ => false,
+    };
+    let name = match format {
+        PixelFormat::Rgb8 => "8-bit",
+        PixelFormat::Rgb16 => "16-bit",
+        PixelFormat::Rgb32F => "32-bit float",
+    };
+    match (supported, alpha) {
+        (true, _) => Ok(()),
+        (false, false) => Err(format!(
+            "{} images can not be saved as '{}'",
+            name, filename
+        )),
+        (false, true) => Err(format!(
+            "{} images with transparency can not be saved as '{}'",
+            name, filename
+        )),
+    }
+}
+
+/// writes the `width` x `height` image `pixels` to `filename` in the given format, with
+/// the file format chosen from the extension. OpenEXR files always store floats.
+/// With `alpha`, the images get an alpha channel, which is premultiplied in OpenEXR
+/// files as usual for them, and straight in all others.
+pub fn save_pixels(
+    filename: &str,
+    pixels: &[Pixel],
+    width: usize,
+    height: usize,
+    format: PixelFormat,
+    alpha: bool,
+) -> ImageResult<()> {
+    let (width, height) = (width as u32, height as u32);
+    let file_format = ImageFormat::from_path(filename).ok();
+    // only OpenEXR files expect premultiplied colors
+    let straight = |pixel: &Pixel| -> Pixel {
+        match pixel[3] {
+            alpha if alpha > 0.0 && file_format != Some(ImageFormat::OpenExr) => {
+                [pixel[0] / alpha, pixel[1] / alpha, pixel[2] / alpha, alpha]
+            }
+            _ => *pixel,
+        }
+    };
+    let pixels: Vec<Pixel> = pixels.iter().map(straight).collect();
+    // RGB or RGBA
+    let count = if alpha { 4 } else { 3 };
+    let expect_size = "the buffer matches the size of the image";
+    match (format, file_format) {
+        (_, Some(ImageFormat::OpenExr)) | (PixelFormat::Rgb32F, _) => {
+            let floats = channels(&pixels, count, |c| c);
+            if file_format == Some(ImageFormat::Tiff) {
+                save_float_tiff(filename, &floats, width, height, alpha)
+            } else if alpha {
+                Rgba32FImage::from_raw(width, height, floats)
+                    .expect(expect_size)
+                    .save(filename)
+            } else {
+                Rgb32FImage::from_raw(width, height, floats)
+                    .expect(expect_size)
+                    .save(filename)
+            }
+        }
+        (PixelFormat::Rgb16, _) => {
+            let samples = channels(&pixels, count, to_u16);
+            if alpha {
+                ImageBuffer::<Rgba<u16>, Vec<u16>>::from_raw(width, height, samples)
+                    .expect(expect_size)
+                    .save(filename)
+            } else {
+                ImageBuffer::<Rgb<u16>, Vec<u16>>::from_raw(width, height, samples)
+                    .expect(expect_size)
+                    .save(filename)
+            }
+        }
+        (PixelFormat::Rgb8, _) => image::save_buffer(
+            filename,
+            &channels(&pixels, count, to_u8),
+            width,
+            height,
+            if alpha {
+                image::ColorType::Rgba8
+            } else {
+                image::ColorType::Rgb8
+            },
+        ),
+    }
+}
+
+/// the first `count` channels of all `pixels`, one after another
+fn channels<T>(pixels: &[Pixel], count: usize, to: impl Fn(f32) -> T) -> Vec<T> {
+    pixels
+        .iter()
+        .flat_map(|pixel| &pixel[..count])
+        .map(|&channel| to(channel))
+        .collect()
+}
+
+/// writes an RGB or, with `alpha`, RGBA float TIFF, which the TIFF encoder of `image`
+/// does not support
+fn save_float_tiff(
+    filename: &str,
+    floats: &[f32],
+    width: u32,
+    height: u32,
+    alpha: bool,
+) -> ImageResult<()> {
+    let encoding_error = |err: tiff::TiffError| {
+        ImageError::Encoding(EncodingError::new(
+            ImageFormatHint::Exact(ImageFormat::Tiff),
+            err,
+        ))
+    };
+    let file = BufWriter::new(File::create(filename)?);
+    let mut encoder = TiffEncoder::new(file).map_err(encoding_error)?;
+    if alpha {
+        encoder.write_image::<colortype::RGBA32Float>(width, height, floats)
+    } else {
+        encoder.write_image::<colortype::RGB32Float>(width, height, floats)
+    }
+    .map_err(encoding_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_formats_match_extensions() {
+        for (filename, format, alpha) in [
+            ("fractal.png", PixelFormat::Rgb8, false),
+            ("fractal.jpg", PixelFormat::Rgb8, false),
+            ("fractal.png", PixelFormat::Rgb16, true),
+            ("fractal.tiff", PixelFormat::Rgb32F, true),
+            ("fractal.exr", PixelFormat::Rgb8, true),
+        ] {
+            assert_eq!(check_output_format(filename, format, alpha), Ok(()));
+        }
+        for (filename, format, alpha) in [
+            ("fractal.png", PixelFormat::Rgb32F, false),
+            ("fractal.jpg", PixelFormat::Rgb16, false),
+            ("fractal.jpg", PixelFormat::Rgb8, true),
+            ("fractal.bmp", PixelFormat::Rgb8, true),
+            ("fractal", PixelFormat::Rgb8, false),
+            ("fractal.txt", PixelFormat::Rgb8, false),
+        ] {
+            assert!(
+                check_output_format(filename, format, alpha).is_err(),
+                "{} accepted {:?}",
+                filename,
+                format
+            );
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use super::big_fixed::BigFixed;
+
+/// extra bits of precision used for the reference orbit, beyond the pixel size
+const GUARD_BITS: u32 = 64;
+
+/// The orbit of a single point (the image center) computed in arbitrary precision.
+///
+/// Pixels are iterated as small deltas from this orbit in plain f64 (perturbation),
+/// so only this one orbit needs to know the exact location.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReferenceOrbit {
+    /// the real parts of `Z_0 ..= Z_n`, rounded to f64
+    pub z_real: Vec<f64>,
+    /// the imaginary parts of `Z_0 ..= Z_n`, rounded to f64
+    pub z_imag: Vec<f64>,
+    /// lets pixels skip the first iterations, if it has been computed for this orbit
+    pub series: Option<SeriesApproximation>,
+}
+
+impl ReferenceOrbit {
+    /// iterates `Z = Z^2 + C` from `Z = 0` until it escapes or reaches `max_iter`,
+    /// with enough precision to resolve pixels at the given zoom
+    pub fn compute(c_real: &BigFixed, c_imag: &BigFixed, zoom: f64, max_iter: usize) -> Self {
+        let bits = zoom.max(0.0).ceil() as u32 + GUARD_BITS;
+        let c_real = c_real.with_bits(bits);
+        let c_imag = c_imag.with_bits(bits);
+
+        let mut z_real = BigFixed::from(0.0).with_bits(bits);
+        let mut z_imag = z_real.clone();
+
+        let mut orbit = ReferenceOrbit {
+            z_real: vec![0.0],
+            z_imag: vec![0.0],
+            series: None,
+        };
+        for _step in 0..max_iter {
+            // z = z^2 + c, using the same squares for the escape check
+            let real_2 = &z_real * &z_real;
+            let imag_2 = &z_imag * &z_imag;
+            let real_imag = &z_real * &z_imag;
+            z_imag = &(&real_imag + &real_imag) + &c_imag;
+            z_real = &(&real_2 - &imag_2) + &c_real;
+
+            let (real, imag) = (z_real.to_f64(), z_imag.to_f64());
+            orbit.z_real.push(real);
+            orbit.z_imag.push(imag);
+            // matches the escape radius of the pixel iteration
+            if real * real + imag * imag > 1000.0 {
+                break;
+            }
+        }
+        orbit
+    }
+}
+
+/// A cubic approximation of every pixel's offset from the reference orbit after `skip`
+/// iterations, `dz = A*dc + B*dc^2 + C*dc^3`, which lets pixels start iterating at `skip`
+/// instead of zero.
+///
+/// The coefficients are stored multiplied by powers of `dc_max` (the largest pixel offset
+/// in the image), so they are evaluated with `u = dc / dc_max` and stay within f64 range.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SeriesApproximation {
+    pub skip: usize,
+    pub dc_max: f64,
+    /// `A * dc_max` as (real, imag)
+    pub a: (f64, f64),
+    /// `B * dc_max^2` as (real, imag)
+    pub b: (f64, f64),
+    /// `C * dc_max^3` as (real, imag)
+    pub c: (f64, f64),
+    /// the smallest `|Z|^2` of the skipped iterations, used for orbit trap coloring
+    pub orbit_min: f64,
+}
+
+impl SeriesApproximation {
+    /// advances the coefficients along the reference orbit for as long as the cubic term
+    /// stays below `tolerance` times the quadratic term
+    ///
+    /// the coefficients follow from `dz = 2*Z*dz + dz^2 + dc`:
+    ///   A = 2*Z*A + 1
+    ///   B = 2*Z*B + A^2
+    ///   C = 2*Z*C + 2*A*B
+    pub fn compute(orbit: &ReferenceOrbit, dc_max: f64, tolerance: f64) -> Self {
+        let mut series = SeriesApproximation {
+            skip: 0,
+            dc_max,
+            a: (0.0, 0.0),
+            b: (0.0, 0.0),
+            c: (0.0, 0.0),
+            orbit_min: 1.0,
+        };
+        let mul =
+            |(ar, ai): (f64, f64), (br, bi): (f64, f64)| (ar * br - ai * bi, ar * bi + ai * br);
+        let norm = |(r, i): (f64, f64)| (r * r + i * i).sqrt();
+
+        // always leave at least one iteration of the reference orbit for the pixels
+        let last = orbit.z_real.len().saturating_sub(2);
+        for n in 0..last {
+            let two_z = (2.0 * orbit.z_real[n], 2.0 * orbit.z_imag[n]);
+            let (a, b, c) = (series.a, series.b, series.c);
+
+            let two_z_a = mul(two_z, a);
+            let a_next = (two_z_a.0 + dc_max, two_z_a.1);
+            let two_z_b = mul(two_z, b);
+            let a_2 = mul(a, a);
+            let b_next = (two_z_b.0 + a_2.0, two_z_b.1 + a_2.1);
+            let two_z_c = mul(two_z, c);
+            let a_b = mul(a, b);
+            let c_next = (two_z_c.0 + 2.0 * a_b.0, two_z_c.1 + 2.0 * a_b.1);
+
+            if norm(c_next) > tolerance * norm(b_next) || !norm(c_next).is_finite() {
+                break;
+            }
+            series.a = a_next;
+            series.b = b_next;
+            series.c = c_next;
+            series.skip = n + 1;
+            let (real, imag) = (orbit.z_real[n + 1], orbit.z_imag[n + 1]);
+            series.orbit_min = series.orbit_min.min(real * real + imag * imag);
+        }
+        series
+    }
+}
@@ -0,0 +1,239 @@
+use std::f64::consts::PI;
+use std::simd::{Simd, SimdFloat, SimdPartialOrd, StdFloat};
+
+use druid::{Data, Lens};
+use serde::{Deserialize, Serialize};
+
+use super::image_generator::{GeneratorParameters, Pixel, SimdF64, CHANNELS, LANES};
+use super::utilities::{hsl2rgb, lane_coordinates, pixel_distances};
+
+/// the Phoenix fractal, a Julia set of `z = z^2 + c + p * z_prev`,
+/// where `z_prev` is the value of z one iteration earlier
+#[derive(Clone, PartialEq, Data, Lens, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PhoenixParameters {
+    // image parameters
+    pub max_iter: usize,
+    pub zoom: f64,
+    pub offset_x: f64,
+    pub offset_y: f64,
+    pub constant_real: f64,
+    pub constant_imag: f64,
+    pub p_real: f64,
+    pub p_imag: f64,
+    // colors
+    pub saturation: f64,
+    pub color_frequency: f64,
+    pub color_offset: f64,
+    pub glow_spread: f64,
+    pub glow_strength: f64,
+    pub brightness: f64,
+    pub internal_brightness: f64,
+}
+
+impl GeneratorParameters for PhoenixParameters {
+    type Intermediate = [[f64; LANES]; 4];
+    /// calculates the color for LANES number of pixels from `[i,j]` to `[i,j+LANES]` of the image
+    fn calc_pixel_row(
+        &self,
+        width: usize,
+        height: usize,
+        (i, j): (usize, usize),
+        step: usize,
+    ) -> Self::Intermediate {
+        let mm_ones: SimdF64 = Simd::splat(1.0);
+        let mm_zero: SimdF64 = Simd::splat(0.0);
+        // initialize values
+        let scale = f64::powf(2.0, -self.zoom);
+
+        // c, p: complex numbers
+        let c_real = Simd::splat(self.constant_real);
+        let c_imag = Simd::splat(self.constant_imag);
+        let p_real = Simd::splat(self.p_real);
+        let p_imag = Simd::splat(self.p_imag);
+
+        // z: complex number
+        let mut z_real = lane_coordinates(i, step, width, scale, self.offset_x);
+
+        let mut z_imag = Simd::splat(
+            (j as f64 / height as f64 - 0.5) * scale * (height as f64 / width as f64)
+                + self.offset_y,
+        );
+
+        // the previous z, which starts at 0
+        let mut z_prev_real = mm_zero;
+        let mut z_prev_imag = mm_zero;
+
+        // z': complex running derivative, and its previous value
+        let mut z_prime_r = mm_ones;
+        let mut z_prime_i = mm_zero;
+        let mut z_prime_prev_r = mm_zero;
+        let mut z_prime_prev_i = mm_zero;
+
+        // z^2: temporary value for optimized computation
+        let mut real_2 = z_real * z_real;
+        let mut imag_2 = z_imag * z_imag;
+
+        // value accumulators for coloring
+        let mut step_acc = mm_zero;
+        let mut orbit_acc = mm_ones;
+
+        for _step in 0..self.max_iter {
+            // iterate values, according to z = z^2 + c + p * z_prev
+            //
+            // z' is calculated with the chain rule through both terms:
+            //   z' = 2 * z * z' + p * z'_prev
+
+            let z_imag_tmp =
+                (z_real + z_real) * z_imag + c_imag + (p_real * z_prev_imag + p_imag * z_prev_real);
+            let z_real_tmp =
+                real_2 - imag_2 + c_real + (p_real * z_prev_real - p_imag * z_prev_imag);
+
+            // intermediate values for z'
+            let ac_bd = z_real * z_prime_r - z_imag * z_prime_i;
+            let bc_da = z_imag * z_prime_r + z_real * z_prime_i;
+
+            let z_prime_r_tmp = ac_bd + ac_bd + (p_real * z_prime_prev_r - p_imag * z_prime_prev_i);
+            let z_prime_i_tmp = bc_da + bc_da + (p_real * z_prime_prev_i + p_imag * z_prime_prev_r);
+
+            let radius_2 = real_2 + imag_2;
+
+            // select lanes which have not escaped
+            // escape of 1000.0 used to smooth distance estimate
+            let mask = radius_2.simd_lt(Simd::splat(1000.0));
+
+            // conditionally iterate, only if the pixel has not escaped
+            z_prev_real = mask.select(z_real, z_prev_real);
+            z_prev_imag = mask.select(z_imag, z_prev_imag);
+            z_prime_prev_r = mask.select(z_prime_r, z_prime_prev_r);
+            z_prime_prev_i = mask.select(z_prime_i, z_prime_prev_i);
+            z_real = mask.select(z_real_tmp, z_real);
+            z_imag = mask.select(z_imag_tmp, z_imag);
+            z_prime_i = mask.select(z_prime_i_tmp, z_prime_i);
+            z_prime_r = mask.select(z_prime_r_tmp, z_prime_r);
+
+            real_2 = z_real * z_real;
+            imag_2 = z_imag * z_imag;
+
+            step_acc = mask.select(mm_ones, mm_zero) + step_acc;
+            orbit_acc = orbit_acc.simd_min(real_2 + imag_2);
+
+            // finish if all pixels have escaped
+            if !mask.any() {
+                break;
+            }
+        }
+
+        // calculate the absolute value (radius) of z for distance estimation
+        let r = (real_2 + imag_2).sqrt();
+        let dr = (z_prime_r * z_prime_r + z_prime_i * z_prime_i).sqrt();
+
+        // extract values necessary for coloring
+        let extracted_step = step_acc.to_array();
+        let extracted_dr = dr.to_array();
+        let extracted_r = r.to_array();
+        let extracted_orbit = orbit_acc.sqrt().to_array();
+
+        [extracted_step, extracted_r, extracted_dr, extracted_orbit]
+    }
+
+    fn shade_pixel_row(&self, parameters: Self::Intermediate) -> [Pixel; LANES] {
+        let scale = f64::powf(2.0, -self.zoom);
+        let mut row: [Pixel; LANES] = [[0.0; CHANNELS]; LANES];
+        for v in 0..LANES {
+            let step = parameters[0][v];
+            let r = parameters[1][v];
+            let dr = parameters[2][v];
+            let orbit = parameters[3][v];
+            // distance estimation: 0.5 * log(r) * r/dr
+            let dist_est = 0.5 * (r).ln() * r / dr;
+            // a 'glow' effect based on distance (manually adjusted to taste and to adjust to zoom level)
+            let glow = (-(dist_est / scale).ln() + self.glow_spread) * self.glow_strength * 0.1;
+            // a smoothed version of the iteration count: i + (1 - ln(ln(r))/ln(2))
+            let smoothed_step = step + (1.0 - ((r).ln()).ln() / f64::ln(2.0));
+
+            if step as usize >= self.max_iter {
+                // color the inside using orbit trap method
+                row[v] = hsl2rgb(
+                    0.0,
+                    0.0,
+                    ((orbit)
+                        * self.brightness
+                        * self.internal_brightness
+                        * self.internal_brightness)
+                        .clamp(0.0, 1.0),
+                )
+            } else {
+                // color the outside
+                row[v] = hsl2rgb(
+                    // color hue based on an sinusoidal step counter, offset to a [0,1] range
+                    (((smoothed_step.ln() * self.color_frequency - self.color_offset * 2.0 * PI)
+                        .sin())
+                        * 0.5
+                        + 0.5)
+                        .clamp(0.0, 1.0),
+                    // saturation decreased when glow is high to hide noise when hue oscillates quickly
+                    (self.saturation * (1.0 - (glow * glow))).clamp(0.0, 1.0),
+                    // use glow around edges for brightness
+                    (glow * self.brightness).clamp(0.0, 1.0),
+                )
+            }
+        }
+        row
+    }
+
+    fn escape_steps(&self, parameters: &Self::Intermediate) -> Option<[f64; LANES]> {
+        Some(parameters[0])
+    }
+
+    fn interior(&self, parameters: &Self::Intermediate) -> Option<[bool; LANES]> {
+        Some(parameters[0].map(|step| step as usize >= self.max_iter))
+    }
+
+    fn boundary_distances(
+        &self,
+        width: usize,
+        parameters: &Self::Intermediate,
+    ) -> Option<[f64; LANES]> {
+        let scale = f64::powf(2.0, -self.zoom);
+        Some(pixel_distances(
+            [parameters[0], parameters[1], parameters[2]],
+            self.max_iter,
+            scale,
+            width,
+        ))
+    }
+
+    fn needs_recompute(settings: &Self, old_settings: &Self) -> bool {
+        settings.max_iter != old_settings.max_iter
+            || settings.zoom != old_settings.zoom
+            || settings.offset_y != old_settings.offset_y
+            || settings.offset_x != old_settings.offset_x
+            || settings.constant_real != old_settings.constant_real
+            || settings.constant_imag != old_settings.constant_imag
+            || settings.p_real != old_settings.p_real
+            || settings.p_imag != old_settings.p_imag
+    }
+}
+
+impl Default for PhoenixParameters {
+    fn default() -> Self {
+        Self {
+            max_iter: 250,
+            zoom: -1.5,
+            offset_x: 0.0,
+            offset_y: 0.0,
+            constant_real: 0.5667,
+            constant_imag: 0.0,
+            p_real: -0.5,
+            p_imag: 0.0,
+            saturation: 1.0,
+            color_frequency: 1.0,
+            color_offset: 0.0,
+            glow_spread: 1.0,
+            glow_strength: 1.0,
+            brightness: 2.0,
+            internal_brightness: 1.0,
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use std::{fmt, str::FromStr, sync::Arc};
+
+use druid::Data;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A complex polynomial `p(z) = (z - r_1)(z - r_2)...(z - r_n)`, described by its roots.
+///
+/// It is written as a comma-separated list of complex numbers, such as
+/// `1, -0.5+0.866i, -0.5-0.866i`, both in the interface and in scene files.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polynomial {
+    roots: Arc<Vec<(f64, f64)>>,
+}
+
+impl Polynomial {
+    /// the roots as (real, imag)
+    pub fn roots(&self) -> &[(f64, f64)] {
+        &self.roots
+    }
+}
+
+impl Default for Polynomial {
+    /// `z^3 - 1`
+    fn default() -> Self {
+        "1, -0.5+0.8660254037844386i, -0.5-0.8660254037844386i"
+            .parse()
+            .unwrap()
+    }
+}
+
+impl Data for Polynomial {
+    fn same(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.roots, &other.roots) || self == other
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsePolynomialError;
+
+impl fmt::Display for ParsePolynomialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected a comma-separated list of complex roots")
+    }
+}
+
+impl std::error::Error for ParsePolynomialError {}
+
+/// parses a complex number such as `2`, `-1.5i`, `i` or `0.5-2e-3i`
+fn parse_complex(s: &str) -> Result<(f64, f64), ParsePolynomialError> {
+    let s: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+    let s = s.as_str();
+    let imag = match s.strip_suffix('i') {
+        Some(imag) => imag,
+        None => return Ok((s.parse().map_err(|_| ParsePolynomialError)?, 0.0)),
+    };
+    // the imaginary part starts at the last sign that is not part of an exponent
+    let split = imag
+        .char_indices()
+        .filter(|&(index, c)| {
+            (c == '+' || c == '-') && index > 0 && !imag[..index].ends_with(['e', 'E'])
+        })
+        .map(|(index, _)| index)
+        .next_back()
+        .unwrap_or(0);
+    let (real, imag) = imag.split_at(split);
+    let real = if real.is_empty() {
+        0.0
+    } else {
+        real.parse().map_err(|_| ParsePolynomialError)?
+    };
+    let imag = match imag {
+        "" | "+" => 1.0,
+        "-" => -1.0,
+        imag => imag.parse().map_err(|_| ParsePolynomialError)?,
+    };
+    Ok((real, imag))
+}
+
+impl FromStr for Polynomial {
+    type Err = ParsePolynomialError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let roots = s
+            .split(',')
+            .map(parse_complex)
+            .collect::<Result<Vec<_>, _>>()?;
+        if roots
+            .iter()
+            .any(|(real, imag)| !real.is_finite() || !imag.is_finite())
+        {
+            return Err(ParsePolynomialError);
+        }
+        Ok(Polynomial {
+            roots: Arc::new(roots),
+        })
+    }
+}
+
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (real, imag)) in self.roots.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            if *imag == 0.0 {
+                write!(f, "{}", real)?;
+            } else if *real == 0.0 {
+                write!(f, "{}i", imag)?;
+            } else if *imag < 0.0 {
+                write!(f, "{}{}i", real, imag)?;
+            } else {
+                write!(f, "{}+{}i", real, imag)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Polynomial {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Polynomial {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complex_numbers() {
+        assert_eq!(parse_complex("2"), Ok((2.0, 0.0)));
+        assert_eq!(parse_complex("-1.5i"), Ok((0.0, -1.5)));
+        assert_eq!(parse_complex("i"), Ok((0.0, 1.0)));
+        assert_eq!(parse_complex("-i"), Ok((0.0, -1.0)));
+        assert_eq!(parse_complex("1+i"), Ok((1.0, 1.0)));
+        assert_eq!(parse_complex(" 0.5 - 2e-3i "), Ok((0.5, -2e-3)));
+        assert_eq!(parse_complex("1e-3+2E+2i"), Ok((1e-3, 200.0)));
+        assert_eq!(parse_complex("-1e-3-i"), Ok((-1e-3, -1.0)));
+    }
+
+    #[test]
+    fn roots() {
+        let polynomial: Polynomial = "1, -0.5+0.866i, -0.5-0.866i".parse().unwrap();
+        assert_eq!(
+            polynomial.roots(),
+            &[(1.0, 0.0), (-0.5, 0.866), (-0.5, -0.866)]
+        );
+        assert_eq!(Polynomial::default().roots().len(), 3);
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let text = "1, -2i, -0.5+0.866i, 3-4i";
+        let polynomial: Polynomial = text.parse().unwrap();
+        assert_eq!(polynomial.to_string(), text);
+        assert_eq!(polynomial.to_string().parse::<Polynomial>(), Ok(polynomial));
+    }
+
+    #[test]
+    fn invalid_roots_are_rejected() {
+        for s in ["", "1,", "z", "1+2j", "1++2i", "1, inf", "NaN"] {
+            assert!(s.parse::<Polynomial>().is_err(), "{:?} parsed", s);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::{
+    collections::VecDeque,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Condvar, Mutex, OnceLock},
+};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// how urgently a job should run, jobs of a higher priority are always started first
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Priority {
+    /// work the user is waiting on, such as the live view
+    Interactive,
+    /// work that may take a while, such as rendering an image to a file
+    Background,
+}
+
+/// a fixed set of long-lived worker threads that run jobs from a shared queue
+#[derive(Clone)]
+pub struct ThreadPool {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    /// the queued jobs, one queue per priority, in the order of the `Priority` variants
+    queues: Mutex<[VecDeque<Job>; 2]>,
+    available: Condvar,
+    threads: usize,
+}
+
+impl ThreadPool {
+    /// starts `threads` workers, which wait for jobs for the rest of the program
+    pub fn new(threads: usize) -> Self {
+        let shared = Arc::new(Shared {
+            queues: Mutex::new([VecDeque::new(), VecDeque::new()]),
+            available: Condvar::new(),
+            threads: threads.max(1),
+        });
+        for _ in 0..shared.threads {
+            let shared = shared.clone();
+            std::thread::spawn(move || shared.run_worker());
+        }
+        ThreadPool { shared }
+    }
+
+    /// the pool shared by the whole application, with a worker for every core
+    pub fn global() -> &'static ThreadPool {
+        static GLOBAL: OnceLock<ThreadPool> = OnceLock::new();
+        GLOBAL.get_or_init(|| ThreadPool::new(num_cpus::get()))
+    }
+
+    pub fn threads(&self) -> usize {
+        self.shared.threads
+    }
+
+    /// queues `job` to run on one of the workers, after the jobs of the same
+    /// or a higher priority that are already queued
+    pub fn spawn(&self, priority: Priority, job: impl FnOnce() + Send + 'static) {
+        let mut queues = self
+            .shared
+            .queues
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        queues[priority as usize].push_back(Box::new(job));
+        self.shared.available.notify_one();
+    }
+}
+
+impl Shared {
+    fn run_worker(&self) {
+        loop {
+            let job = {
+                let mut queues = self
+                    .queues
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                loop {
+                    if let Some(job) = queues.iter_mut().find_map(|queue| queue.pop_front()) {
+                        break job;
+                    }
+                    queues = self
+                        .available
+                        .wait(queues)
+                        .unwrap_or_else(|poisoned| poisoned.into_inner());
+                }
+            };
+            // a panicking job is dropped, so anything waiting on it can notice,
+            // but the worker stays available for the others
+            let _ = panic::catch_unwind(AssertUnwindSafe(job));
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use std::f64::consts::PI;
+use std::simd::{Simd, SimdFloat, SimdPartialOrd, StdFloat};
+
+use druid::{Data, Lens};
+use serde::{Deserialize, Serialize};
+
+use super::image_generator::{GeneratorParameters, Pixel, SimdF64, CHANNELS, LANES};
+use super::utilities::{hsl2rgb, lane_coordinates, pixel_distances};
+
+/// the Tricorn (or Mandelbar) set, iterating `z = conj(z)^2 + c`
+#[derive(Clone, PartialEq, Data, Lens, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TricornParameters {
+    // image parameters
+    pub max_iter: usize,
+    pub zoom: f64,
+    pub offset_x: f64,
+    pub offset_y: f64,
+    // colors
+    pub saturation: f64,
+    pub color_frequency: f64,
+    pub color_offset: f64,
+    pub glow_spread: f64,
+    pub glow_strength: f64,
+    pub brightness: f64,
+    pub internal_brightness: f64,
+}
+
+impl GeneratorParameters for TricornParameters {
+    type Intermediate = [[f64; LANES]; 4];
+    /// calculates the color for LANES number of pixels from `[i,j]` to `[i,j+LANES]` of the image
+    fn calc_pixel_row(
+        &self,
+        width: usize,
+        height: usize,
+        (i, j): (usize, usize),
+        step: usize,
+    ) -> Self::Intermediate {
+        let mm_ones: SimdF64 = Simd::splat(1.0);
+        let mm_zero: SimdF64 = Simd::splat(0.0);
+        let two: SimdF64 = Simd::splat(2.0);
+        // initialize values
+        let scale = f64::powf(2.0, -self.zoom);
+
+        // c: complex number
+        let c_real = lane_coordinates(i, step, width, scale, self.offset_x);
+
+        let c_imag = Simd::splat(
+            (j as f64 / height as f64 - 0.5) * scale * (height as f64 / width as f64)
+                + self.offset_y,
+        );
+
+        // z: complex number
+        let mut z_real = mm_zero;
+        let mut z_imag = mm_zero;
+
+        // a, b: complex running derivatives with respect to c and conj(c)
+        let mut a_r = mm_zero;
+        let mut a_i = mm_zero;
+        let mut b_r = mm_zero;
+        let mut b_i = mm_zero;
+
+        // z^2: temporary value for optimized computation
+        let mut real_2 = mm_zero;
+        let mut imag_2 = mm_zero;
+
+        // value accumulators for coloring
+        let mut step_acc = mm_zero;
+        let mut orbit_acc = mm_ones;
+
+        for _step in 0..self.max_iter {
+            // iterate values, according to z = conj(z)^2 + c
+            //   z.i := -2 × z.r × z.i + c.i
+            //   z.r := r2 - i2 + c.r
+            //
+            // the iteration is not holomorphic, so z has two derivatives (a = dz/dc,
+            // b = dz/dconj(c)) which swap through the conjugate at every step:
+            //   a = 2 * conj(z) * conj(b) + 1
+            //   b = 2 * conj(z) * conj(a)
+
+            let z_imag_tmp = -(z_real + z_real) * z_imag + c_imag;
+            let z_real_tmp = real_2 - imag_2 + c_real;
+
+            // conj(z) * conj(w) = conj(z * w)
+            let a_r_tmp = (z_real * b_r - z_imag * b_i) * two + mm_ones;
+            let a_i_tmp = -(z_real * b_i + z_imag * b_r) * two;
+            let b_r_tmp = (z_real * a_r - z_imag * a_i) * two;
+            let b_i_tmp = -(z_real * a_i + z_imag * a_r) * two;
+
+            let radius_2 = real_2 + imag_2;
+
+            // select lanes which have not escaped
+            // escape of 1000.0 used to smooth distance estimate
+            let mask = radius_2.simd_lt(Simd::splat(1000.0));
+
+            // conditionally iterate, only if the pixel has not escaped
+            z_real = mask.select(z_real_tmp, z_real);
+            z_imag = mask.select(z_imag_tmp, z_imag);
+            a_r = mask.select(a_r_tmp, a_r);
+            a_i = mask.select(a_i_tmp, a_i);
+            b_r = mask.select(b_r_tmp, b_r);
+            b_i = mask.select(b_i_tmp, b_i);
+
+            real_2 = z_real * z_real;
+            imag_2 = z_imag * z_imag;
+
+            step_acc = mask.select(mm_ones, mm_zero) + step_acc;
+            orbit_acc = orbit_acc.simd_min(real_2 + imag_2);
+
+            // finish if all pixels have escaped
+            if !mask.any() {
+                break;
+            }
+        }
+
+        // calculate the absolute value (radius) of z for distance estimation
+        let r = (real_2 + imag_2).sqrt();
+        // the largest stretch of the derivative in any direction, |a| + |b|,
+        // takes the place of |z'| in the distance estimate
+        let dr = (a_r * a_r + a_i * a_i).sqrt() + (b_r * b_r + b_i * b_i).sqrt();
+
+        // extract values necessary for coloring
+        let extracted_step = step_acc.to_array();
+        let extracted_dr = dr.to_array();
+        let extracted_r = r.to_array();
+        let extracted_orbit = orbit_acc.sqrt().to_array();
+
+        [extracted_step, extracted_r, extracted_dr, extracted_orbit]
+    }
+
+    fn shade_pixel_row(&self, parameters: Self::Intermediate) -> [Pixel; LANES] {
+        let scale = f64::powf(2.0, -self.zoom);
+        let mut row: [Pixel; LANES] = [[0.0; CHANNELS]; LANES];
+        for v in 0..LANES {
+            let step = parameters[0][v];
+            let r = parameters[1][v];
+            let dr = parameters[2][v];
+            let orbit = parameters[3][v];
+            // distance estimation: 0.5 * log(r) * r/dr
+            let dist_est = 0.5 * (r).ln() * r / dr;
+            // a 'glow' effect based on distance (manually adjusted to taste and to adjust to zoom level)
+            let glow = (-(dist_est / scale).ln() + self.glow_spread) * self.glow_strength * 0.1;
+            // a smoothed version of the iteration count: i + (1 - ln(ln(r))/ln(2))
+            let smoothed_step = step + (1.0 - ((r).ln()).ln() / f64::ln(2.0));
+
+            if step as usize >= self.max_iter {
+                // color the inside using orbit trap method
+                row[v] = hsl2rgb(
+                    0.0,
+                    0.0,
+                    ((orbit)
+                        * self.brightness
+                        * self.internal_brightness
+                        * self.internal_brightness)
+                        .clamp(0.0, 1.0),
+                )
+            } else {
+                // color the outside
+                row[v] = hsl2rgb(
+                    // color hue based on an sinusoidal step counter, offset to a [0,1] range
+                    (((smoothed_step.ln() * self.color_frequency - self.color_offset * 2.0 * PI)
+                        .sin())
+                        * 0.5
+                        + 0.5)
+                        .clamp(0.0, 1.0),
+                    // saturation decreased when glow is high to hide noise when hue oscillates quickly
+                    (self.saturation * (1.0 - (glow * glow))).clamp(0.0, 1.0),
+                    // use glow around edges for brightness
+                    (glow * self.brightness).clamp(0.0, 1.0),
+                )
+            }
+        }
+        row
+    }
+
+    fn escape_steps(&self, parameters: &Self::Intermediate) -> Option<[f64; LANES]> {
+        Some(parameters[0])
+    }
+
+    fn interior(&self, parameters: &Self::Intermediate) -> Option<[bool; LANES]> {
+        Some(parameters[0].map(|step| step as usize >= self.max_iter))
+    }
+
+    fn boundary_distances(
+        &self,
+        width: usize,
+        parameters: &Self::Intermediate,
+    ) -> Option<[f64; LANES]> {
+        let scale = f64::powf(2.0, -self.zoom);
+        Some(pixel_distances(
+            [parameters[0], parameters[1], parameters[2]],
+            self.max_iter,
+            scale,
+            width,
+        ))
+    }
+
+    fn needs_recompute(settings: &Self, old_settings: &Self) -> bool {
+        settings.max_iter != old_settings.max_iter
+            || settings.zoom != old_settings.zoom
+            || settings.offset_y != old_settings.offset_y
+            || settings.offset_x != old_settings.offset_x
+    }
+}
+
+impl Default for TricornParameters {
+    fn default() -> Self {
+        Self {
+            max_iter: 250,
+            zoom: -2.0,
+            offset_x: -0.3,
+            offset_y: 0.0,
+            saturation: 1.0,
+            color_frequency: 1.0,
+            color_offset: 0.0,
+            glow_spread: 1.0,
+            glow_strength: 1.0,
+            brightness: 2.0,
+            internal_brightness: 1.0,
+        }
+    }
+}
@@ -1,8 +1,11 @@
 use std::hint::unreachable_unchecked;
+use std::simd::{Simd, SimdPartialEq};
 
-/// converts hsl to rgb, modified from
+use super::image_generator::{Pixel, SimdF64, LANES};
+
+/// converts hsl to an opaque rgb pixel, with channels from 0 to `v`, modified from
 /// https://web.archive.org/web/20081227003853/http://mjijackson.com/2008/02/rgb-to-hsl-and-rgb-to-hsv-color-model-conversion-algorithms-in-javascript
-pub fn hsl2rgb(h: f64, s: f64, v: f64) -> [u8; 3] {
+pub fn hsl2rgb(h: f64, s: f64, v: f64) -> Pixel {
     let r;
     let g;
     let b;
@@ -47,5 +50,152 @@ pub fn hsl2rgb(h: f64, s: f64, v: f64) -> [u8; 3] {
         _ => unsafe { unreachable_unchecked() },
     }
 
-    [(r * 255.) as u8, (g * 255.) as u8, (b * 255.) as u8]
+    // saturations above 1 push the smallest channel below zero, and NaN counts as black
+    let [r, g, b] = [r, g, b].map(|channel| channel.max(0.0) as f32);
+    [r, g, b, 1.0]
+}
+
+/// `z^exponent` for every lane, with integer exponents computed exactly by repeated
+/// multiplication and all others through the complex logarithm.
+/// Lanes where z is 0 stay 0 for negative and non-integer exponents, instead of
+/// becoming infinite or undefined.
+pub fn complex_pow(
+    real: SimdF64,
+    imag: SimdF64,
+    (exponent_real, exponent_imag): (f64, f64),
+) -> (SimdF64, SimdF64) {
+    if exponent_imag == 0.0 && exponent_real.fract() == 0.0 && exponent_real.abs() <= 64.0 {
+        return complex_powi(real, imag, exponent_real as i32);
+    }
+    let mut result_real = real.to_array();
+    let mut result_imag = imag.to_array();
+    for (re, im) in result_real.iter_mut().zip(result_imag.iter_mut()) {
+        if *re == 0.0 && *im == 0.0 {
+            continue;
+        }
+        // z^d = exp(d * ln(z)), with ln(z) = ln|z| + i*arg(z)
+        let ln_r = re.hypot(*im).ln();
+        let theta = im.atan2(*re);
+        let modulus = (exponent_real * ln_r - exponent_imag * theta).exp();
+        let angle = exponent_real * theta + exponent_imag * ln_r;
+        *re = modulus * angle.cos();
+        *im = modulus * angle.sin();
+    }
+    (Simd::from_array(result_real), Simd::from_array(result_imag))
+}
+
+/// `z^n` by binary exponentiation
+fn complex_powi(real: SimdF64, imag: SimdF64, n: i32) -> (SimdF64, SimdF64) {
+    let mut result = (Simd::splat(1.0), Simd::splat(0.0));
+    let mut base = (real, imag);
+    let mut remaining = n.unsigned_abs();
+    while remaining > 0 {
+        if remaining & 1 == 1 {
+            result = complex_mul(result, base);
+        }
+        base = complex_mul(base, base);
+        remaining >>= 1;
+    }
+    if n < 0 {
+        // 1/w = conj(w) / |w|^2, keeping 0 at 0
+        let norm_2 = result.0 * result.0 + result.1 * result.1;
+        let zero = norm_2.simd_eq(Simd::splat(0.0));
+        result = (
+            zero.select(Simd::splat(0.0), result.0 / norm_2),
+            zero.select(Simd::splat(0.0), -result.1 / norm_2),
+        );
+    }
+    result
+}
+
+/// the product of two complex numbers given as (real, imag)
+pub fn complex_mul(
+    (a_r, a_i): (SimdF64, SimdF64),
+    (b_r, b_i): (SimdF64, SimdF64),
+) -> (SimdF64, SimdF64) {
+    (a_r * b_r - a_i * b_i, a_r * b_i + a_i * b_r)
+}
+
+/// `ln|d|` for the exponent `d` of `z^d + c`, the base of the smoothed iteration count.
+/// Exponents with `|d| <= 1` do not escape at a regular rate, so they fall back to `ln(2)`.
+pub fn degree_ln(exponent_real: f64, exponent_imag: f64) -> f64 {
+    let degree = exponent_real.hypot(exponent_imag);
+    if degree > 1.0 {
+        degree.ln()
+    } else {
+        f64::ln(2.0)
+    }
+}
+
+/// the x coordinates of the pixels `i + v * step` of a `width` pixel wide image for
+/// every lane v, mapped to `(x / width - 0.5) * scale + offset`
+pub fn lane_coordinates(i: usize, step: usize, width: usize, scale: f64, offset: f64) -> SimdF64 {
+    Simd::from_array(std::array::from_fn(|v| {
+        ((i + v * step) as f64 / width as f64 - 0.5) * scale + offset
+    }))
+}
+
+/// the distance estimate `0.5 * ln(r) * r / dr` of each lane of an escape-time fractal,
+/// in pixels of a `width` pixel wide image. Points which did not escape have no estimate,
+/// and are treated as infinitely far from the boundary.
+pub fn pixel_distances(
+    [step, r, dr]: [[f64; LANES]; 3],
+    max_iter: usize,
+    scale: f64,
+    width: usize,
+) -> [f64; LANES] {
+    std::array::from_fn(|v| {
+        let dist_est = 0.5 * r[v].ln() * r[v] / dr[v];
+        if (step[v] as usize) < max_iter && dist_est.is_finite() {
+            dist_est / scale * width as f64
+        } else {
+            f64::INFINITY
+        }
+    })
+}
+
+/// the difference in hue between interior points of consecutive periods, the golden ratio
+/// conjugate, so that any few nearby periods get clearly different hues
+pub const PERIOD_HUE_STEP: f64 = 0.618_033_988_749_895;
+
+/// the distance below which an orbit counts as having come back to an earlier point,
+/// small compared to the pixels so that orbits which only pass close to a cycle on
+/// their way out are not mistaken for it
+pub fn period_tolerance(scale: f64, width: usize) -> f64 {
+    (scale / width as f64 * 1e-3).min(1e-10)
+}
+
+/// the bookkeeping of Brent's cycle detection, shared by all lanes since they iterate
+/// in step: the orbit is compared to a saved point, which moves up to the current point
+/// after 1, 2, 4, 8, ... iterations, so a cycle of any length is found soon after the
+/// orbit settles into it
+pub struct PeriodCheck {
+    interval: usize,
+    since_saved: usize,
+}
+
+impl PeriodCheck {
+    pub fn new() -> Self {
+        PeriodCheck {
+            interval: 1,
+            since_saved: 0,
+        }
+    }
+
+    /// counts one iteration, returning the number of iterations since the saved point,
+    /// which is the period of an orbit that is back at it
+    pub fn count(&mut self) -> usize {
+        self.since_saved += 1;
+        self.since_saved
+    }
+
+    /// whether the saved point should move up to the current point of the orbit
+    pub fn should_save(&mut self) -> bool {
+        if self.since_saved < self.interval {
+            return false;
+        }
+        self.since_saved = 0;
+        self.interval *= 2;
+        true
+    }
 }
@@ -0,0 +1,690 @@
+use std::{
+    fmt,
+    sync::mpsc::{self, RecvTimeoutError},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    backends::{
+        Antialiasing, BuddhabrotParameters, BurningShipParameters, CustomParameters,
+        ImageGenerator, JuliaParameters, LyapunovParameters, MandelParameters, NewtonParameters,
+        NovaParameters, PhoenixParameters, PixelFormat, Priority, ThreadPool, Transparency,
+        TricornJuliaParameters, TricornParameters, MAX_SAMPLES,
+    },
+    scene::Scene,
+    types::FractalSettings,
+};
+
+pub const USAGE: &str = "\
+Usage: mandel-app --headless [OPTIONS]
+
+Renders a single image without opening a window.
+
+Options:
+    --scene <FILE>             load the fractal and output settings from a scene file,
+                               other options override the values it contains
+    --fractal <TYPE>           fractal type to render (default: mandel), one of mandel,
+                               julia, newton, burningship, tricorn, tricornjulia,
+                               phoenix, lyapunov, nova, buddhabrot or custom
+    --width <PIXELS>           output image width (default: 3840)
+    --height <PIXELS>          output image height (default: 2160)
+    --output <FILE>            output image filename (default: fractal.png)
+    --pixel-format <rgb8|rgb16|rgb32f>
+                               samples of the output image (default: rgb8): 16-bit for
+                               png and tiff, float for tiff, and exr is always float
+    --threads <N>              number of worker threads (default: all cores)
+    --subdivide <true|false>   fill in regions whose border has a single escape step
+                               instead of calculating them, faster but may miss details
+    --antialiasing <off|supersample|adaptive>
+                               take several samples for every pixel, or only for the
+                               pixels at edges and close to the fractal (default: off)
+    --aa-samples <N>           samples along each side of a pixel, 1 to 16 (default: 3)
+    --aa-filter <box|tent|lanczos>
+                               filter combining the samples of supersampling (default: tent)
+    --aa-threshold <T>         color difference from 0 to 1 to a neighboring pixel above
+                               which adaptive anti-aliasing adds samples (default: 0.1)
+    --transparency <off|interior|exterior>
+                               make the points inside or outside the fractal transparent,
+                               saving the image with an alpha channel, which needs
+                               png, tiff or exr (default: off)
+    --soft-edges <true|false>  fade the opacity of the escaping points with their
+                               distance to the fractal (default: false)
+    --edge-width <PIXELS>      distance over which soft edges fade (default: 2)
+    --benchmark <RUNS>         render the image RUNS times and print the timings instead
+                               of saving it
+    --max-iter <N>             maximum iterations
+    --zoom <ZOOM>              zoom level, as a power of two
+    --offset-x <X>             real offset of the image center
+    --offset-y <Y>             imaginary offset of the image center
+    --exponent-real <D>        real part of the exponent d in z^d + c (default: 2)
+    --exponent-imag <D>        imaginary part of the exponent d in z^d + c (default: 0)
+    --precision <auto|double|doubledouble>
+                               arithmetic used for the iterations, auto switches to
+                               double-double once f64 can no longer resolve pixels
+    --deep-zoom <true|false>   use perturbation for zooms beyond f64 precision (mandel only)
+    --series-tolerance <TOL>   error tolerance of the deep zoom series approximation,
+                               0 disables it (mandel only)
+    --period-coloring <true|false>
+                               color the interior of the mandel and julia sets by the
+                               period of the cycle each orbit settles into
+    --constant-real <X>        real part of the julia constant
+    --constant-imag <Y>        imaginary part of the julia constant
+    --polynomial <ROOTS>       comma-separated complex roots of the newton polynomial,
+                               such as \"1, -0.5+0.866i, -0.5-0.866i\"
+    --relaxation <A>           step size of the newton and nova iterations (default: 1)
+    --variant <burningship|celtic|buffalo|perpendicular>
+                               member of the burning ship family (default: burningship)
+    --p-real <X>               real part of the phoenix previous-iterate factor p
+    --p-imag <Y>               imaginary part of the phoenix previous-iterate factor p
+    --sequence <AB...>         order of the lyapunov rates a (x) and b (y), such as AABAB
+    --warmup <N>               lyapunov iterations skipped before measuring (default: 50)
+    --exponent <P>             exponent p of the nova polynomial z^p - 1 (default: 3)
+    --julia <true|false>       render the julia plane of the nova or custom fractal, with the
+                               constant as c, instead of its parameter plane
+    --samples <N>              buddhabrot orbits sampled per pixel (default: 10)
+    --seed <N>                 buddhabrot random seed (default: 0)
+    --anti <true|false>        plot the orbits that do not escape (anti-buddhabrot)
+    --red-iter <N>             iteration limit of the red buddhabrot channel
+    --green-iter <N>           iteration limit of the green buddhabrot channel
+    --blue-iter <N>            iteration limit of the blue buddhabrot channel
+    --formula <EXPR>           iteration of the custom fractal, such as \"z^3 + c*sin(z)\"
+    --start <EXPR>             starting z of the custom fractal, may use c (default: 0)
+    --bailout <COND>           when a custom fractal orbit escapes (default: \"|z| > 32\")
+    --help                     print this message
+";
+
+#[derive(Debug)]
+pub enum CliError {
+    Help,
+    Usage(String),
+    Input(String),
+    Render(String),
+}
+
+impl CliError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Help => 0,
+            CliError::Render(_) => 1,
+            CliError::Usage(_) | CliError::Input(_) => 2,
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Help => write!(f, "{}", USAGE),
+            CliError::Usage(message) => write!(f, "{}\n\n{}", message, USAGE),
+            CliError::Input(message) | CliError::Render(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+struct HeadlessOptions {
+    settings: FractalSettings,
+    width: usize,
+    height: usize,
+    filename: String,
+    pixel_format: PixelFormat,
+    threads: usize,
+    subdivide: bool,
+    antialiasing: Antialiasing,
+    transparency: Transparency,
+    benchmark_runs: usize,
+}
+
+impl Default for HeadlessOptions {
+    fn default() -> Self {
+        HeadlessOptions {
+            settings: FractalSettings::Mandel(MandelParameters::default()),
+            width: 3840,
+            height: 2160,
+            filename: String::from("fractal.png"),
+            pixel_format: PixelFormat::Rgb8,
+            threads: num_cpus::get(),
+            subdivide: false,
+            antialiasing: Antialiasing::default(),
+            transparency: Transparency::default(),
+            benchmark_runs: 0,
+        }
+    }
+}
+
+fn parse_value<T>(flag: &str, value: &str) -> Result<T, CliError>
+where
+    T: std::str::FromStr,
+    T::Err: fmt::Display,
+{
+    value
+        .parse()
+        .map_err(|err| CliError::Usage(format!("invalid value '{}' for {}: {}", value, flag, err)))
+}
+
+fn parse_args(args: &[String]) -> Result<HeadlessOptions, CliError> {
+    let mut options = HeadlessOptions::default();
+    // parameter flags are applied after the fractal type is known
+    let mut parameters = vec![];
+
+    let mut flags = vec![];
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        if flag == "--help" {
+            return Err(CliError::Help);
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| CliError::Usage(format!("missing value for {}", flag)))?;
+        flags.push((flag.as_str(), value.as_str()));
+    }
+
+    // the scene is loaded before any other flag, so that they override the values it contains
+    for (_, value) in flags.iter().filter(|(flag, _)| *flag == "--scene") {
+        let scene = Scene::load(value)
+            .map_err(|err| CliError::Input(format!("could not load scene {}: {}", value, err)))?;
+        options.settings = scene.fractal;
+        options.width = scene.render.output_width;
+        options.height = scene.render.output_height;
+        options.filename = scene.render.filename;
+        options.pixel_format = scene.render.pixel_format;
+        options.subdivide = scene.render.subdivide;
+        options.antialiasing = scene.render.antialiasing;
+        options.transparency = scene.render.transparency;
+    }
+
+    for (flag, value) in flags {
+        match flag {
+            "--scene" => {}
+            "--fractal" => {
+                options.settings = match value {
+                    "mandel" => FractalSettings::Mandel(MandelParameters::default()),
+                    "julia" => FractalSettings::Julia(JuliaParameters::default()),
+                    "newton" => FractalSettings::Newton(NewtonParameters::default()),
+                    "burningship" => FractalSettings::BurningShip(BurningShipParameters::default()),
+                    "tricorn" => FractalSettings::Tricorn(TricornParameters::default()),
+                    "tricornjulia" => {
+                        FractalSettings::TricornJulia(TricornJuliaParameters::default())
+                    }
+                    "phoenix" => FractalSettings::Phoenix(PhoenixParameters::default()),
+                    "lyapunov" => FractalSettings::Lyapunov(LyapunovParameters::default()),
+                    "nova" => FractalSettings::Nova(NovaParameters::default()),
+                    "buddhabrot" => FractalSettings::Buddhabrot(BuddhabrotParameters::default()),
+                    "custom" => FractalSettings::Custom(CustomParameters::default()),
+                    _ => return Err(CliError::Usage(format!("unknown fractal '{}'", value))),
+                }
+            }
+            "--width" => options.width = parse_value(flag, value)?,
+            "--height" => options.height = parse_value(flag, value)?,
+            "--output" => options.filename = String::from(value),
+            "--pixel-format" => options.pixel_format = parse_value(flag, value)?,
+            "--threads" => options.threads = parse_value(flag, value)?,
+            "--subdivide" => options.subdivide = parse_value(flag, value)?,
+            "--antialiasing" => options.antialiasing.mode = parse_value(flag, value)?,
+            "--aa-samples" => {
+                options.antialiasing.samples =
+                    parse_value::<usize>(flag, value)?.clamp(1, MAX_SAMPLES)
+            }
+            "--aa-filter" => options.antialiasing.filter = parse_value(flag, value)?,
+            "--aa-threshold" => options.antialiasing.threshold = parse_value(flag, value)?,
+            "--transparency" => options.transparency.mode = parse_value(flag, value)?,
+            "--soft-edges" => options.transparency.soft_edges = parse_value(flag, value)?,
+            "--edge-width" => options.transparency.edge_width = parse_value(flag, value)?,
+            "--benchmark" => options.benchmark_runs = parse_value(flag, value)?,
+            _ => parameters.push((flag, value)),
+        }
+    }
+
+    for (flag, value) in parameters {
+        match (&mut options.settings, flag) {
+            (FractalSettings::Mandel(inner), "--max-iter") => {
+                inner.max_iter = parse_value(flag, value)?
+            }
+            (FractalSettings::Mandel(inner), "--zoom") => inner.zoom = parse_value(flag, value)?,
+            (FractalSettings::Mandel(inner), "--offset-x") => {
+                inner.offset_x = parse_value(flag, value)?
+            }
+            (FractalSettings::Mandel(inner), "--offset-y") => {
+                inner.offset_y = parse_value(flag, value)?
+            }
+            (FractalSettings::Mandel(inner), "--exponent-real") => {
+                inner.exponent_real = parse_value(flag, value)?
+            }
+            (FractalSettings::Mandel(inner), "--exponent-imag") => {
+                inner.exponent_imag = parse_value(flag, value)?
+            }
+            (FractalSettings::Mandel(inner), "--period-coloring") => {
+                inner.period_coloring = parse_value(flag, value)?
+            }
+            (FractalSettings::Mandel(inner), "--precision") => {
+                inner.precision = parse_value(flag, value)?
+            }
+            (FractalSettings::Mandel(inner), "--deep-zoom") => {
+                inner.deep_zoom = parse_value(flag, value)?
+            }
+            (FractalSettings::Mandel(inner), "--series-tolerance") => {
+                inner.series_tolerance = parse_value(flag, value)?
+            }
+            (FractalSettings::Julia(inner), "--max-iter") => {
+                inner.max_iter = parse_value(flag, value)?
+            }
+            (FractalSettings::Julia(inner), "--zoom") => inner.zoom = parse_value(flag, value)?,
+            (FractalSettings::Julia(inner), "--offset-x") => {
+                inner.offset_x = parse_value(flag, value)?
+            }
+            (FractalSettings::Julia(inner), "--offset-y") => {
+                inner.offset_y = parse_value(flag, value)?
+            }
+            (FractalSettings::Julia(inner), "--exponent-real") => {
+                inner.exponent_real = parse_value(flag, value)?
+            }
+            (FractalSettings::Julia(inner), "--exponent-imag") => {
+                inner.exponent_imag = parse_value(flag, value)?
+            }
+            (FractalSettings::Julia(inner), "--period-coloring") => {
+                inner.period_coloring = parse_value(flag, value)?
+            }
+            (FractalSettings::Julia(inner), "--precision") => {
+                inner.precision = parse_value(flag, value)?
+            }
+            (FractalSettings::Julia(inner), "--constant-real") => {
+                inner.constant_real = parse_value(flag, value)?
+            }
+            (FractalSettings::Julia(inner), "--constant-imag") => {
+                inner.constant_imag = parse_value(flag, value)?
+            }
+            (FractalSettings::Newton(inner), "--max-iter") => {
+                inner.max_iter = parse_value(flag, value)?
+            }
+            (FractalSettings::Newton(inner), "--zoom") => inner.zoom = parse_value(flag, value)?,
+            (FractalSettings::Newton(inner), "--offset-x") => {
+                inner.offset_x = parse_value(flag, value)?
+            }
+            (FractalSettings::Newton(inner), "--offset-y") => {
+                inner.offset_y = parse_value(flag, value)?
+            }
+            (FractalSettings::Newton(inner), "--polynomial") => {
+                inner.polynomial = parse_value(flag, value)?
+            }
+            (FractalSettings::Newton(inner), "--relaxation") => {
+                inner.relaxation = parse_value(flag, value)?
+            }
+            (FractalSettings::BurningShip(inner), "--max-iter") => {
+                inner.max_iter = parse_value(flag, value)?
+            }
+            (FractalSettings::BurningShip(inner), "--zoom") => {
+                inner.zoom = parse_value(flag, value)?
+            }
+            (FractalSettings::BurningShip(inner), "--offset-x") => {
+                inner.offset_x = parse_value(flag, value)?
+            }
+            (FractalSettings::BurningShip(inner), "--offset-y") => {
+                inner.offset_y = parse_value(flag, value)?
+            }
+            (FractalSettings::BurningShip(inner), "--variant") => {
+                inner.variant = parse_value(flag, value)?
+            }
+            (FractalSettings::Tricorn(inner), "--max-iter") => {
+                inner.max_iter = parse_value(flag, value)?
+            }
+            (FractalSettings::Tricorn(inner), "--zoom") => inner.zoom = parse_value(flag, value)?,
+            (FractalSettings::Tricorn(inner), "--offset-x") => {
+                inner.offset_x = parse_value(flag, value)?
+            }
+            (FractalSettings::Tricorn(inner), "--offset-y") => {
+                inner.offset_y = parse_value(flag, value)?
+            }
+            (FractalSettings::TricornJulia(inner), "--max-iter") => {
+                inner.max_iter = parse_value(flag, value)?
+            }
+            (FractalSettings::TricornJulia(inner), "--zoom") => {
+                inner.zoom = parse_value(flag, value)?
+            }
+            (FractalSettings::TricornJulia(inner), "--offset-x") => {
+                inner.offset_x = parse_value(flag, value)?
+            }
+            (FractalSettings::TricornJulia(inner), "--offset-y") => {
+                inner.offset_y = parse_value(flag, value)?
+            }
+            (FractalSettings::TricornJulia(inner), "--constant-real") => {
+                inner.constant_real = parse_value(flag, value)?
+            }
+            (FractalSettings::TricornJulia(inner), "--constant-imag") => {
+                inner.constant_imag = parse_value(flag, value)?
+            }
+            (FractalSettings::Phoenix(inner), "--max-iter") => {
+                inner.max_iter = parse_value(flag, value)?
+            }
+            (FractalSettings::Phoenix(inner), "--zoom") => inner.zoom = parse_value(flag, value)?,
+            (FractalSettings::Phoenix(inner), "--offset-x") => {
+                inner.offset_x = parse_value(flag, value)?
+            }
+            (FractalSettings::Phoenix(inner), "--offset-y") => {
+                inner.offset_y = parse_value(flag, value)?
+            }
+            (FractalSettings::Phoenix(inner), "--constant-real") => {
+                inner.constant_real = parse_value(flag, value)?
+            }
+            (FractalSettings::Phoenix(inner), "--constant-imag") => {
+                inner.constant_imag = parse_value(flag, value)?
+            }
+            (FractalSettings::Phoenix(inner), "--p-real") => {
+                inner.p_real = parse_value(flag, value)?
+            }
+            (FractalSettings::Phoenix(inner), "--p-imag") => {
+                inner.p_imag = parse_value(flag, value)?
+            }
+            (FractalSettings::Lyapunov(inner), "--max-iter") => {
+                inner.max_iter = parse_value(flag, value)?
+            }
+            (FractalSettings::Lyapunov(inner), "--warmup") => {
+                inner.warmup = parse_value(flag, value)?
+            }
+            (FractalSettings::Lyapunov(inner), "--zoom") => inner.zoom = parse_value(flag, value)?,
+            (FractalSettings::Lyapunov(inner), "--offset-x") => {
+                inner.offset_x = parse_value(flag, value)?
+            }
+            (FractalSettings::Lyapunov(inner), "--offset-y") => {
+                inner.offset_y = parse_value(flag, value)?
+            }
+            (FractalSettings::Lyapunov(inner), "--sequence") => {
+                inner.sequence = parse_value(flag, value)?
+            }
+            (FractalSettings::Nova(inner), "--max-iter") => {
+                inner.max_iter = parse_value(flag, value)?
+            }
+            (FractalSettings::Nova(inner), "--zoom") => inner.zoom = parse_value(flag, value)?,
+            (FractalSettings::Nova(inner), "--offset-x") => {
+                inner.offset_x = parse_value(flag, value)?
+            }
+            (FractalSettings::Nova(inner), "--offset-y") => {
+                inner.offset_y = parse_value(flag, value)?
+            }
+            (FractalSettings::Nova(inner), "--exponent") => {
+                inner.exponent = parse_value(flag, value)?
+            }
+            (FractalSettings::Nova(inner), "--relaxation") => {
+                inner.relaxation = parse_value(flag, value)?
+            }
+            (FractalSettings::Nova(inner), "--julia") => inner.julia = parse_value(flag, value)?,
+            (FractalSettings::Nova(inner), "--constant-real") => {
+                inner.constant_real = parse_value(flag, value)?
+            }
+            (FractalSettings::Nova(inner), "--constant-imag") => {
+                inner.constant_imag = parse_value(flag, value)?
+            }
+            (FractalSettings::Buddhabrot(inner), "--zoom") => {
+                inner.zoom = parse_value(flag, value)?
+            }
+            (FractalSettings::Buddhabrot(inner), "--offset-x") => {
+                inner.offset_x = parse_value(flag, value)?
+            }
+            (FractalSettings::Buddhabrot(inner), "--offset-y") => {
+                inner.offset_y = parse_value(flag, value)?
+            }
+            (FractalSettings::Buddhabrot(inner), "--samples") => {
+                inner.samples = parse_value(flag, value)?
+            }
+            (FractalSettings::Buddhabrot(inner), "--seed") => {
+                inner.seed = parse_value(flag, value)?
+            }
+            (FractalSettings::Buddhabrot(inner), "--anti") => {
+                inner.anti = parse_value(flag, value)?
+            }
+            (FractalSettings::Buddhabrot(inner), "--red-iter") => {
+                inner.red_iter = parse_value(flag, value)?
+            }
+            (FractalSettings::Buddhabrot(inner), "--green-iter") => {
+                inner.green_iter = parse_value(flag, value)?
+            }
+            (FractalSettings::Buddhabrot(inner), "--blue-iter") => {
+                inner.blue_iter = parse_value(flag, value)?
+            }
+            (FractalSettings::Custom(inner), "--max-iter") => {
+                inner.max_iter = parse_value(flag, value)?
+            }
+            (FractalSettings::Custom(inner), "--zoom") => inner.zoom = parse_value(flag, value)?,
+            (FractalSettings::Custom(inner), "--offset-x") => {
+                inner.offset_x = parse_value(flag, value)?
+            }
+            (FractalSettings::Custom(inner), "--offset-y") => {
+                inner.offset_y = parse_value(flag, value)?
+            }
+            (FractalSettings::Custom(inner), "--formula") => {
+                inner.formula = parse_value(flag, value)?
+            }
+            (FractalSettings::Custom(inner), "--start") => inner.start = parse_value(flag, value)?,
+            (FractalSettings::Custom(inner), "--bailout") => {
+                inner.bailout = parse_value(flag, value)?
+            }
+            (FractalSettings::Custom(inner), "--julia") => inner.julia = parse_value(flag, value)?,
+            (FractalSettings::Custom(inner), "--constant-real") => {
+                inner.constant_real = parse_value(flag, value)?
+            }
+            (FractalSettings::Custom(inner), "--constant-imag") => {
+                inner.constant_imag = parse_value(flag, value)?
+            }
+            _ => {
+                return Err(CliError::Usage(format!(
+                    "unknown option {} for the selected fractal",
+                    flag
+                )))
+            }
+        }
+    }
+
+    if options.width == 0 || options.height == 0 || options.threads == 0 {
+        return Err(CliError::Usage(String::from(
+            "width, height and threads must be greater than zero",
+        )));
+    }
+    Ok(options)
+}
+
+/// renders one image from the command line arguments (excluding `--headless`),
+/// reporting progress on stderr
+pub fn run(args: &[String]) -> Result<(), CliError> {
+    let HeadlessOptions {
+        settings,
+        width,
+        height,
+        filename,
+        pixel_format,
+        threads,
+        subdivide,
+        antialiasing,
+        transparency,
+        benchmark_runs,
+    } = parse_args(args)?;
+    let pool = ThreadPool::new(threads);
+    if benchmark_runs > 0 {
+        return benchmark(
+            settings,
+            width,
+            height,
+            subdivide,
+            antialiasing,
+            &pool,
+            benchmark_runs,
+        );
+    }
+    let mut image = ImageGenerator::new(width, height);
+    image.set_subdivide(subdivide);
+    image.set_antialiasing(antialiasing);
+    image.set_transparency(transparency);
+    image
+        .check_save_format(&filename, pixel_format)
+        .map_err(CliError::Usage)?;
+    render(settings, &image, &pool, true)?;
+
+    eprintln!("saving {}", filename);
+    image
+        .save_image(filename.as_str(), pixel_format)
+        .map_err(|err| CliError::Render(format!("failed to save image: {}", err)))
+}
+
+/// computes `image` on `pool` and waits for it to finish,
+/// optionally reporting progress on stderr
+fn render(
+    settings: FractalSettings,
+    image: &ImageGenerator,
+    pool: &ThreadPool,
+    report_progress: bool,
+) -> Result<(), CliError> {
+    let (sender, receiver) = mpsc::channel();
+    settings.start_compute(image, pool, Priority::Background, move || {
+        let _ = sender.send(());
+    });
+
+    loop {
+        match receiver.recv_timeout(Duration::from_millis(250)) {
+            Ok(()) => break,
+            Err(RecvTimeoutError::Timeout) if report_progress => {
+                eprint!("\rrendering: {:5.1}%", image.get_progress())
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            // the computation was dropped without finishing, which only happens on a panic
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(CliError::Render(String::from("render panicked")))
+            }
+        }
+    }
+    if report_progress {
+        eprintln!("\rrendering: done  ");
+    }
+    Ok(())
+}
+
+/// renders the same image `runs` times, printing the wall-clock time of each render
+/// and a summary on stdout
+fn benchmark(
+    settings: FractalSettings,
+    width: usize,
+    height: usize,
+    subdivide: bool,
+    antialiasing: Antialiasing,
+    pool: &ThreadPool,
+    runs: usize,
+) -> Result<(), CliError> {
+    let mut times = Vec::with_capacity(runs);
+    for run in 0..runs {
+        let mut image = ImageGenerator::new(width, height);
+        image.set_subdivide(subdivide);
+        image.set_antialiasing(antialiasing);
+        let start = Instant::now();
+        render(settings.clone(), &image, pool, false)?;
+        let time = start.elapsed().as_secs_f64();
+        println!("run {}: {:.3}s", run + 1, time);
+        times.push(time);
+    }
+    times.sort_by(f64::total_cmp);
+    println!(
+        "{}x{} with {} threads: min {:.3}s, median {:.3}s, max {:.3}s",
+        width,
+        height,
+        pool.threads(),
+        times[0],
+        times[runs / 2],
+        times[runs - 1]
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::{RenderSettings, SCENE_VERSION};
+
+    /// renders a small Mandelbrot set to `name` in the temporary directory,
+    /// with a width that is not a multiple of the lanes so that its rows are padded
+    fn render_file(name: &str, options: &[&str]) -> String {
+        let filename = std::env::temp_dir()
+            .join(format!("mandel-app-{}-{}", std::process::id(), name))
+            .to_string_lossy()
+            .into_owned();
+        let mut args = vec!["--width", "37", "--height", "21", "--max-iter", "50"];
+        args.extend_from_slice(&["--threads", "2", "--output", &filename]);
+        args.extend_from_slice(options);
+        run(&args.into_iter().map(String::from).collect::<Vec<_>>()).unwrap();
+        filename
+    }
+
+    #[test]
+    fn saved_image_has_the_requested_size() {
+        let filename = render_file("size.png", &[]);
+        assert_eq!(image::image_dimensions(&filename).unwrap(), (37, 21));
+        std::fs::remove_file(filename).unwrap();
+    }
+
+    #[test]
+    fn deep_images_have_the_requested_size() {
+        for (name, options) in [
+            ("size16.png", &["--pixel-format", "rgb16"][..]),
+            (
+                "size16a.png",
+                &["--pixel-format", "rgb16", "--transparency", "exterior"],
+            ),
+            ("size16.tiff", &["--pixel-format", "rgb16"]),
+            ("size.exr", &["--pixel-format", "rgb32f"]),
+        ] {
+            let filename = render_file(name, options);
+            assert_eq!(
+                image::image_dimensions(&filename).unwrap(),
+                (37, 21),
+                "{}",
+                name
+            );
+            std::fs::remove_file(filename).unwrap();
+        }
+        // the image crate does not decode float TIFF
+        let filename = render_file("size32.tiff", &["--pixel-format", "rgb32f"]);
+        let file = std::fs::File::open(&filename).unwrap();
+        let dimensions = tiff::decoder::Decoder::new(file).unwrap().dimensions();
+        assert_eq!(dimensions.unwrap(), (37, 21));
+        std::fs::remove_file(filename).unwrap();
+    }
+
+    #[test]
+    fn options_override_the_scene_wherever_they_are() {
+        let path =
+            std::env::temp_dir().join(format!("mandel-app-{}-override.toml", std::process::id()));
+        let scene = Scene {
+            version: SCENE_VERSION,
+            fractal: FractalSettings::Julia(JuliaParameters::default()),
+            render: RenderSettings {
+                output_width: 1920,
+                output_height: 1080,
+                filename: String::from("scene.png"),
+                ..RenderSettings::default()
+            },
+        };
+        scene.save(&path).unwrap();
+        let path = path.to_string_lossy().into_owned();
+        let args: Vec<String> = ["--width", "800", "--scene", &path, "--output", "x.png"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let options = parse_args(&args);
+        std::fs::remove_file(&path).unwrap();
+        let options = options.unwrap();
+        assert_eq!((options.width, options.height), (800, 1080));
+        assert_eq!(options.filename, "x.png");
+        assert!(matches!(options.settings, FractalSettings::Julia(_)));
+    }
+
+    #[test]
+    fn unsupported_formats_are_rejected_before_rendering() {
+        for options in [
+            &["--output", "float.png", "--pixel-format", "rgb32f"][..],
+            &["--output", "deep.jpg", "--pixel-format", "rgb16"],
+            &["--output", "alpha.jpg", "--transparency", "interior"],
+        ] {
+            let args: Vec<String> = options.iter().map(|&arg| String::from(arg)).collect();
+            assert!(
+                matches!(run(&args), Err(CliError::Usage(_))),
+                "{:?}",
+                options
+            );
+            assert!(!std::path::Path::new(options[1]).exists());
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use druid::{
+    commands, AppDelegate, Command, DelegateCtx, Env, FileDialogOptions, FileSpec, Handled,
+    Selector, Target,
+};
+
+use crate::{scene::Scene, AppData};
+
+const SCENE_FILE: FileSpec = FileSpec::new("Fractal Scene", &["toml"]);
+
+/// sent by a full render once it is saved, with the message to show in the log
+pub const RENDER_FINISHED: Selector<String> = Selector::new("mandel-app.render-finished");
+
+pub fn scene_dialog_options() -> FileDialogOptions {
+    FileDialogOptions::new()
+        .allowed_types(vec![SCENE_FILE])
+        .default_type(SCENE_FILE)
+        .default_name("scene.toml")
+}
+
+/// handles the application-wide commands sent by the file dialogs and full renders
+pub struct Delegate;
+
+impl AppDelegate<AppData> for Delegate {
+    fn command(
+        &mut self,
+        _ctx: &mut DelegateCtx,
+        _target: Target,
+        cmd: &Command,
+        data: &mut AppData,
+        _env: &Env,
+    ) -> Handled {
+        if let Some(file_info) = cmd.get(commands::OPEN_FILE) {
+            data.log_text = match Scene::load(file_info.path()) {
+                Ok(scene) => {
+                    scene.apply(data);
+                    format!("Opened {}", file_info.path().display())
+                }
+                Err(err) => format!("Failed to open scene: {}", err),
+            };
+            return Handled::Yes;
+        }
+        if let Some(file_info) = cmd.get(commands::SAVE_FILE_AS) {
+            data.log_text = match Scene::from_app_data(data).save(file_info.path()) {
+                Ok(()) => format!("Saved {}", file_info.path().display()),
+                Err(err) => format!("Failed to save scene: {}", err),
+            };
+            return Handled::Yes;
+        }
+        if let Some(message) = cmd.get(RENDER_FINISHED) {
+            data.log_text = message.clone();
+            return Handled::Yes;
+        }
+        Handled::No
+    }
+}
@@ -1,7 +1,11 @@
 use druid::{Data, Lens};
 
 use crate::{
-    backends::{JuliaParameters, MandelParameters},
+    backends::{
+        BigFixed, BuddhabrotParameters, BurningShipParameters, CustomParameters, JuliaParameters,
+        LyapunovParameters, MandelParameters, NewtonParameters, NovaParameters, PhoenixParameters,
+        TricornJuliaParameters, TricornParameters,
+    },
     AppData, FractalSettings,
 };
 
@@ -9,50 +13,119 @@ use crate::{
 pub enum FractalType {
     Mandel,
     Julia,
+    Newton,
+    BurningShip,
+    Tricorn,
+    TricornJulia,
+    Phoenix,
+    Lyapunov,
+    Nova,
+    Buddhabrot,
+    Custom,
+}
+
+impl FractalType {
+    pub fn of(settings: &FractalSettings) -> Self {
+        match settings {
+            FractalSettings::Mandel(_) => FractalType::Mandel,
+            FractalSettings::Julia(_) => FractalType::Julia,
+            FractalSettings::Newton(_) => FractalType::Newton,
+            FractalSettings::BurningShip(_) => FractalType::BurningShip,
+            FractalSettings::Tricorn(_) => FractalType::Tricorn,
+            FractalSettings::TricornJulia(_) => FractalType::TricornJulia,
+            FractalSettings::Phoenix(_) => FractalType::Phoenix,
+            FractalSettings::Lyapunov(_) => FractalType::Lyapunov,
+            FractalSettings::Nova(_) => FractalType::Nova,
+            FractalSettings::Buddhabrot(_) => FractalType::Buddhabrot,
+            FractalSettings::Custom(_) => FractalType::Custom,
+        }
+    }
 }
 
 pub struct RadioLens {}
 pub struct AppDataToJulia {}
 pub struct AppDataToMandel {}
+pub struct AppDataToNewton {}
+pub struct AppDataToBurningShip {}
+pub struct AppDataToTricorn {}
+pub struct AppDataToTricornJulia {}
+pub struct AppDataToPhoenix {}
+pub struct AppDataToLyapunov {}
+pub struct AppDataToNova {}
+pub struct AppDataToBuddhabrot {}
+pub struct AppDataToCustom {}
 
 impl Lens<AppData, FractalType> for RadioLens {
     fn with<V, F: FnOnce(&FractalType) -> V>(&self, data: &AppData, f: F) -> V {
-        match &data.settings {
-            FractalSettings::Mandel(_) => f(&FractalType::Mandel),
-            FractalSettings::Julia(_) => f(&FractalType::Julia),
-        }
+        f(&FractalType::of(&data.settings))
     }
 
     fn with_mut<V, F: FnOnce(&mut FractalType) -> V>(&self, data: &mut AppData, f: F) -> V {
-        match &data.settings {
-            FractalSettings::Mandel(mandel_settings) => {
-                let mut frac = FractalType::Mandel;
-                let out = f(&mut frac);
-                if frac != FractalType::Mandel {
-                    let mut new_julia = JuliaParameters::default();
-                    new_julia.constant_real = mandel_settings.offset_x;
-                    new_julia.constant_imag = mandel_settings.offset_y;
-                    new_julia.zoom = mandel_settings.zoom / 2.0;
-                    new_julia.max_iter = (f64::powf(2.0, new_julia.zoom / 10.0) * 1000.0) as usize;
-                    data.settings = FractalSettings::Julia(new_julia);
-                }
-                out
+        let old_frac = FractalType::of(&data.settings);
+        let mut frac = old_frac;
+        let out = f(&mut frac);
+        if frac == old_frac {
+            return out;
+        }
+        data.settings = match (&data.settings, frac) {
+            // the julia set of the point at the center of the mandelbrot view
+            (FractalSettings::Mandel(mandel_settings), FractalType::Julia) => {
+                let mut new_julia = JuliaParameters::default();
+                new_julia.constant_real = mandel_settings.offset_x.to_f64();
+                new_julia.constant_imag = mandel_settings.offset_y.to_f64();
+                new_julia.exponent_real = mandel_settings.exponent_real;
+                new_julia.exponent_imag = mandel_settings.exponent_imag;
+                new_julia.zoom = mandel_settings.zoom / 2.0;
+                new_julia.max_iter = (f64::powf(2.0, new_julia.zoom / 10.0) * 1000.0) as usize;
+                FractalSettings::Julia(new_julia)
             }
-            FractalSettings::Julia(julia_settings) => {
-                let mut frac = FractalType::Julia;
-                let out = f(&mut frac);
-                if frac != FractalType::Julia {
-                    let mut new_mandel = MandelParameters::default();
-                    new_mandel.offset_x = julia_settings.constant_real;
-                    new_mandel.offset_y = julia_settings.constant_imag;
-                    new_mandel.zoom = julia_settings.zoom * 2.0;
-                    new_mandel.max_iter =
-                        (f64::powf(2.0, new_mandel.zoom / 10.0) * 1000.0) as usize;
-                    data.settings = FractalSettings::Mandel(new_mandel);
-                }
-                out
+            // the mandelbrot set centered on the julia constant
+            (FractalSettings::Julia(julia_settings), FractalType::Mandel) => {
+                let mut new_mandel = MandelParameters::default();
+                new_mandel.offset_x = BigFixed::from(julia_settings.constant_real);
+                new_mandel.offset_y = BigFixed::from(julia_settings.constant_imag);
+                new_mandel.exponent_real = julia_settings.exponent_real;
+                new_mandel.exponent_imag = julia_settings.exponent_imag;
+                new_mandel.zoom = julia_settings.zoom * 2.0;
+                new_mandel.max_iter = (f64::powf(2.0, new_mandel.zoom / 10.0) * 1000.0) as usize;
+                FractalSettings::Mandel(new_mandel)
             }
-        }
+            // the same pair of conversions between the tricorn and its julia sets
+            (FractalSettings::Tricorn(tricorn_settings), FractalType::TricornJulia) => {
+                let mut new_julia = TricornJuliaParameters::default();
+                new_julia.constant_real = tricorn_settings.offset_x;
+                new_julia.constant_imag = tricorn_settings.offset_y;
+                new_julia.zoom = tricorn_settings.zoom / 2.0;
+                new_julia.max_iter = (f64::powf(2.0, new_julia.zoom / 10.0) * 1000.0) as usize;
+                FractalSettings::TricornJulia(new_julia)
+            }
+            (FractalSettings::TricornJulia(julia_settings), FractalType::Tricorn) => {
+                let mut new_tricorn = TricornParameters::default();
+                new_tricorn.offset_x = julia_settings.constant_real;
+                new_tricorn.offset_y = julia_settings.constant_imag;
+                new_tricorn.zoom = julia_settings.zoom * 2.0;
+                new_tricorn.max_iter = (f64::powf(2.0, new_tricorn.zoom / 10.0) * 1000.0) as usize;
+                FractalSettings::Tricorn(new_tricorn)
+            }
+            (_, FractalType::Mandel) => FractalSettings::Mandel(MandelParameters::default()),
+            (_, FractalType::Julia) => FractalSettings::Julia(JuliaParameters::default()),
+            (_, FractalType::Newton) => FractalSettings::Newton(NewtonParameters::default()),
+            (_, FractalType::BurningShip) => {
+                FractalSettings::BurningShip(BurningShipParameters::default())
+            }
+            (_, FractalType::Tricorn) => FractalSettings::Tricorn(TricornParameters::default()),
+            (_, FractalType::TricornJulia) => {
+                FractalSettings::TricornJulia(TricornJuliaParameters::default())
+            }
+            (_, FractalType::Phoenix) => FractalSettings::Phoenix(PhoenixParameters::default()),
+            (_, FractalType::Lyapunov) => FractalSettings::Lyapunov(LyapunovParameters::default()),
+            (_, FractalType::Nova) => FractalSettings::Nova(NovaParameters::default()),
+            (_, FractalType::Buddhabrot) => {
+                FractalSettings::Buddhabrot(BuddhabrotParameters::default())
+            }
+            (_, FractalType::Custom) => FractalSettings::Custom(CustomParameters::default()),
+        };
+        out
     }
 }
 
@@ -91,3 +164,177 @@ impl Lens<AppData, JuliaParameters> for AppDataToJulia {
         }
     }
 }
+
+impl Lens<AppData, NewtonParameters> for AppDataToNewton {
+    fn with<V, F: FnOnce(&NewtonParameters) -> V>(&self, data: &AppData, f: F) -> V {
+        if let FractalSettings::Newton(settings) = &data.settings {
+            f(settings)
+        } else {
+            panic! {};
+        }
+    }
+
+    fn with_mut<V, F: FnOnce(&mut NewtonParameters) -> V>(&self, data: &mut AppData, f: F) -> V {
+        if let FractalSettings::Newton(settings) = &mut data.settings {
+            f(settings)
+        } else {
+            panic! {};
+        }
+    }
+}
+
+impl Lens<AppData, BurningShipParameters> for AppDataToBurningShip {
+    fn with<V, F: FnOnce(&BurningShipParameters) -> V>(&self, data: &AppData, f: F) -> V {
+        if let FractalSettings::BurningShip(settings) = &data.settings {
+            f(settings)
+        } else {
+            panic! {};
+        }
+    }
+
+    fn with_mut<V, F: FnOnce(&mut BurningShipParameters) -> V>(
+        &self,
+        data: &mut AppData,
+        f: F,
+    ) -> V {
+        if let FractalSettings::BurningShip(settings) = &mut data.settings {
+            f(settings)
+        } else {
+            panic! {};
+        }
+    }
+}
+
+impl Lens<AppData, TricornParameters> for AppDataToTricorn {
+    fn with<V, F: FnOnce(&TricornParameters) -> V>(&self, data: &AppData, f: F) -> V {
+        if let FractalSettings::Tricorn(settings) = &data.settings {
+            f(settings)
+        } else {
+            panic! {};
+        }
+    }
+
+    fn with_mut<V, F: FnOnce(&mut TricornParameters) -> V>(&self, data: &mut AppData, f: F) -> V {
+        if let FractalSettings::Tricorn(settings) = &mut data.settings {
+            f(settings)
+        } else {
+            panic! {};
+        }
+    }
+}
+
+impl Lens<AppData, TricornJuliaParameters> for AppDataToTricornJulia {
+    fn with<V, F: FnOnce(&TricornJuliaParameters) -> V>(&self, data: &AppData, f: F) -> V {
+        if let FractalSettings::TricornJulia(settings) = &data.settings {
+            f(settings)
+        } else {
+            panic! {};
+        }
+    }
+
+    fn with_mut<V, F: FnOnce(&mut TricornJuliaParameters) -> V>(
+        &self,
+        data: &mut AppData,
+        f: F,
+    ) -> V {
+        if let FractalSettings::TricornJulia(settings) = &mut data.settings {
+            f(settings)
+        } else {
+            panic! {};
+        }
+    }
+}
+
+impl Lens<AppData, PhoenixParameters> for AppDataToPhoenix {
+    fn with<V, F: FnOnce(&PhoenixParameters) -> V>(&self, data: &AppData, f: F) -> V {
+        if let FractalSettings::Phoenix(settings) = &data.settings {
+            f(settings)
+        } else {
+            panic! {};
+        }
+    }
+
+    fn with_mut<V, F: FnOnce(&mut PhoenixParameters) -> V>(&self, data: &mut AppData, f: F) -> V {
+        if let FractalSettings::Phoenix(settings) = &mut data.settings {
+            f(settings)
+        } else {
+            panic! {};
+        }
+    }
+}
+
+impl Lens<AppData, LyapunovParameters> for AppDataToLyapunov {
+    fn with<V, F: FnOnce(&LyapunovParameters) -> V>(&self, data: &AppData, f: F) -> V {
+        if let FractalSettings::Lyapunov(settings) = &data.settings {
+            f(settings)
+        } else {
+            panic! {};
+        }
+    }
+
+    fn with_mut<V, F: FnOnce(&mut LyapunovParameters) -> V>(&self, data: &mut AppData, f: F) -> V {
+        if let FractalSettings::Lyapunov(settings) = &mut data.settings {
+            f(settings)
+        } else {
+            panic! {};
+        }
+    }
+}
+
+impl Lens<AppData, NovaParameters> for AppDataToNova {
+    fn with<V, F: FnOnce(&NovaParameters) -> V>(&self, data: &AppData, f: F) -> V {
+        if let FractalSettings::Nova(settings) = &data.settings {
+            f(settings)
+        } else {
+            panic! {};
+        }
+    }
+
+    fn with_mut<V, F: FnOnce(&mut NovaParameters) -> V>(&self, data: &mut AppData, f: F) -> V {
+        if let FractalSettings::Nova(settings) = &mut data.settings {
+            f(settings)
+        } else {
+            panic! {};
+        }
+    }
+}
+
+impl Lens<AppData, BuddhabrotParameters> for AppDataToBuddhabrot {
+    fn with<V, F: FnOnce(&BuddhabrotParameters) -> V>(&self, data: &AppData, f: F) -> V {
+        if let FractalSettings::Buddhabrot(settings) = &data.settings {
+            f(settings)
+        } else {
+            panic! {};
+        }
+    }
+
+    fn with_mut<V, F: FnOnce(&mut BuddhabrotParameters) -> V>(
+        &self,
+        data: &mut AppData,
+        f: F,
+    ) -> V {
+        if let FractalSettings::Buddhabrot(settings) = &mut data.settings {
+            f(settings)
+        } else {
+            panic! {};
+        }
+    }
+}
+
+impl Lens<AppData, CustomParameters> for AppDataToCustom {
+    fn with<V, F: FnOnce(&CustomParameters) -> V>(&self, data: &AppData, f: F) -> V {
+        if let FractalSettings::Custom(settings) = &data.settings {
+            f(settings)
+        } else {
+            panic! {};
+        }
+    }
+
+    fn with_mut<V, F: FnOnce(&mut CustomParameters) -> V>(&self, data: &mut AppData, f: F) -> V {
+        if let FractalSettings::Custom(settings) = &mut data.settings {
+            f(settings)
+        } else {
+            panic! {};
+        }
+    }
+}
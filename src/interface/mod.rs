@@ -1,21 +1,32 @@
 use druid::{
+    commands,
     text::format::ParseFormatter,
     widget::{
-        Axis, Button, Flex, Label, LineBreaking, MainAxisAlignment, RadioGroup, Scroll, Slider,
-        Tabs, TabsTransition, TextBox, ValueTextBox, ViewSwitcher,
+        Axis, Button, Checkbox, Flex, Label, LineBreaking, MainAxisAlignment, RadioGroup, Scroll,
+        Slider, Tabs, TabsTransition, TextBox, ValueTextBox, ViewSwitcher,
     },
-    Color, Env, EventCtx, FontDescriptor, FontFamily, FontWeight, TextAlignment, Widget, WidgetExt,
+    Color, Env, EventCtx, FontDescriptor, FontFamily, FontWeight, Target, TextAlignment, Widget,
+    WidgetExt,
 };
 
 use crate::{
-    backends::{ImageGenerator, JuliaParameters, MandelParameters},
-    AppData, FractalSettings,
+    backends::{
+        Antialiasing, AntialiasingMode, BuddhabrotParameters, BurningShipParameters,
+        CustomParameters, DownsampleFilter, ImageGenerator, JuliaParameters, LyapunovParameters,
+        MandelParameters, NewtonParameters, NovaParameters, PhoenixParameters, PixelFormat,
+        Precision, Priority, ShipVariant, ThreadPool, Transparency, TransparencyMode,
+        TricornJuliaParameters, TricornParameters, MAX_SAMPLES,
+    },
+    AppData,
 };
 
+mod delegate;
 mod lenses;
 mod renderview;
 mod view_controllers;
 
+pub use delegate::Delegate;
+use delegate::{scene_dialog_options, RENDER_FINISHED};
 use lenses::*;
 pub use renderview::RenderView;
 use view_controllers::ViewDragController;
@@ -71,6 +82,20 @@ macro parameters_to_interface {
                 .lens(<$struct>::$member))
                 .padding((0.0, 10.0, 0.0, 0.0))
     },
+    (_inner; $struct:ty, ($member:ident: { $( $label:literal => $value:expr ),+ } $name:literal)) => {
+        Flex::column()
+            .with_child(
+                parameters_to_interface!{_inner_label; $name}
+            )
+            .with_child(
+                RadioGroup::new(vec![ $( ($label, $value) ),+ ])
+                    .env_scope(|env: &mut Env, _data: &_| {
+                        env.set(druid::theme::BACKGROUND_DARK, Color::from_rgba32_u32(0x212733FF));
+                    })
+                    .lens(<$struct>::$member),
+            )
+            .expand_width()
+    },
     (_inner_label; $name:literal) => {
         Label::new($name)
             .with_font(
@@ -96,6 +121,21 @@ pub fn build_ui() -> impl Widget<AppData> {
             fractal_switcher(|fractal_settings| match fractal_settings {
                 FractalType::Mandel => Box::new(ViewDragController::<MandelParameters>::new()),
                 FractalType::Julia => Box::new(ViewDragController::<JuliaParameters>::new()),
+                FractalType::Newton => Box::new(ViewDragController::<NewtonParameters>::new()),
+                FractalType::BurningShip => {
+                    Box::new(ViewDragController::<BurningShipParameters>::new())
+                }
+                FractalType::Tricorn => Box::new(ViewDragController::<TricornParameters>::new()),
+                FractalType::TricornJulia => {
+                    Box::new(ViewDragController::<TricornJuliaParameters>::new())
+                }
+                FractalType::Phoenix => Box::new(ViewDragController::<PhoenixParameters>::new()),
+                FractalType::Lyapunov => Box::new(ViewDragController::<LyapunovParameters>::new()),
+                FractalType::Nova => Box::new(ViewDragController::<NovaParameters>::new()),
+                FractalType::Buddhabrot => {
+                    Box::new(ViewDragController::<BuddhabrotParameters>::new())
+                }
+                FractalType::Custom => Box::new(ViewDragController::<CustomParameters>::new()),
             }),
             0.75,
         )
@@ -153,14 +193,79 @@ fn create_rendering_tab() -> impl Widget<AppData> {
             [
                 (output_width: [ ] "Output Image Width" align_left),
                 (output_height: [ ] "Output Image Height" align_left),
-                (filename: [ ] "Output Image Filename" align_left)
+                (filename: [ ] "Output Image Filename" align_left),
+                (pixel_format: {
+                    "8-bit" => PixelFormat::Rgb8,
+                    "16-bit (PNG, TIFF)" => PixelFormat::Rgb16,
+                    "32-bit float (EXR, TIFF)" => PixelFormat::Rgb32F
+                } "Output Pixel Format"),
+                (subdivide: [ x ] "Fill Solid Regions (faster, may miss details)")
             ]
         })
+        .with_child(
+            parameters_to_interface! {
+                Antialiasing
+                [
+                    (mode: {
+                        "Off" => AntialiasingMode::Off,
+                        "Supersampling" => AntialiasingMode::Supersample,
+                        "Adaptive" => AntialiasingMode::Adaptive
+                    } "Anti-aliasing"),
+                    (samples: [ ] "Samples per Pixel Side" align_left),
+                    (filter: {
+                        "Box" => DownsampleFilter::Box,
+                        "Tent" => DownsampleFilter::Tent,
+                        "Lanczos" => DownsampleFilter::Lanczos
+                    } "Downsample Filter (supersampling)"),
+                    (threshold: [0.0 to 1.0] "Adaptive Threshold")
+                ]
+            }
+            .lens(AppData::antialiasing),
+        )
+        .with_child(
+            parameters_to_interface! {
+                Transparency
+                [
+                    (mode: {
+                        "Opaque" => TransparencyMode::Off,
+                        "Transparent Interior" => TransparencyMode::Interior,
+                        "Transparent Exterior" => TransparencyMode::Exterior
+                    } "Transparency"),
+                    (soft_edges: [ x ] "Soft Edges (distance estimate)"),
+                    (edge_width: [0.5 to 20.0] "Soft Edge Width (pixels)")
+                ]
+            }
+            .lens(AppData::transparency),
+        )
         .with_child(
             Button::new("Render")
                 .on_click(render_full)
                 .padding((0.0, 7.0)),
         )
+        .with_child(
+            Flex::row()
+                .with_flex_child(
+                    Button::new("Open Scene")
+                        .on_click(|ctx, _data, _env| {
+                            ctx.submit_command(
+                                commands::SHOW_OPEN_PANEL.with(scene_dialog_options()),
+                            )
+                        })
+                        .expand_width(),
+                    1.0,
+                )
+                .with_spacer(7.0)
+                .with_flex_child(
+                    Button::new("Save Scene")
+                        .on_click(|ctx, _data, _env| {
+                            ctx.submit_command(
+                                commands::SHOW_SAVE_PANEL.with(scene_dialog_options()),
+                            )
+                        })
+                        .expand_width(),
+                    1.0,
+                ),
+        )
         // .with_child(RenderView::<MandelParameters>::new(100, 100).lens(AppDataToMandel {}))
         .main_axis_alignment(MainAxisAlignment::Start)
 }
@@ -183,6 +288,15 @@ fn create_generation_tab() -> impl Widget<AppData> {
                     RadioGroup::new(vec![
                         ("Mandelbrot set", FractalType::Mandel),
                         ("Julia set", FractalType::Julia),
+                        ("Newton fractal", FractalType::Newton),
+                        ("Burning Ship family", FractalType::BurningShip),
+                        ("Tricorn", FractalType::Tricorn),
+                        ("Tricorn Julia set", FractalType::TricornJulia),
+                        ("Phoenix", FractalType::Phoenix),
+                        ("Lyapunov", FractalType::Lyapunov),
+                        ("Nova", FractalType::Nova),
+                        ("Buddhabrot", FractalType::Buddhabrot),
+                        ("Custom formula", FractalType::Custom),
                     ])
                     .env_scope(|env: &mut Env, _data: &_| {
                         env.set(
@@ -199,9 +313,18 @@ fn create_generation_tab() -> impl Widget<AppData> {
                                 MandelParameters
                                 [
                                     (max_iter: [ ] "Maximum Iterations" align_left),
-                                    (zoom: [-10.0 to 50.0] "Zoom"),
+                                    (zoom: [-10.0 to 350.0] "Zoom"),
                                     (offset_x: [ ] "Real Offset (x)" center),
-                                    (offset_y: [ ] "Real Offset (y)" center)
+                                    (offset_y: [ ] "Real Offset (y)" center),
+                                    (exponent_real: [1.0 to 8.0] "Exponent (real)"),
+                                    (exponent_imag: [-2.0 to 2.0] "Exponent (imaginary)"),
+                                    (precision: {
+                                        "Automatic" => Precision::Auto,
+                                        "Double" => Precision::Double,
+                                        "Double-double" => Precision::DoubleDouble
+                                    } "Precision"),
+                                    (deep_zoom: [ x ] "Deep Zoom (perturbation)"),
+                                    (series_tolerance: [0.0 to 0.01] "Series Approximation Tolerance")
                                 ]
                             }
                             .lens(AppDataToMandel {}),
@@ -209,6 +332,71 @@ fn create_generation_tab() -> impl Widget<AppData> {
                         FractalType::Julia => Box::new(
                             parameters_to_interface! {
                                 JuliaParameters
+                                [
+                                    (max_iter: [ ] "Maximum Iterations" align_left),
+                                    (zoom: [-10.0 to 110.0] "Zoom"),
+                                    (offset_x: [ ] "Real Offset (x)" center),
+                                    (offset_y: [ ] "Real Offset (y)" center),
+                                    (precision: {
+                                        "Automatic" => Precision::Auto,
+                                        "Double" => Precision::Double,
+                                        "Double-double" => Precision::DoubleDouble
+                                    } "Precision"),
+                                    (constant_real: [-2.0 to 2.0] "Real value (x)"),
+                                    (constant_imag: [-2.0 to 2.0] "Imaginary value (y)"),
+                                    (exponent_real: [1.0 to 8.0] "Exponent (real)"),
+                                    (exponent_imag: [-2.0 to 2.0] "Exponent (imaginary)")
+                                ]
+                            }
+                            .lens(AppDataToJulia {}),
+                        ),
+                        FractalType::Newton => Box::new(
+                            parameters_to_interface! {
+                                NewtonParameters
+                                [
+                                    (max_iter: [ ] "Maximum Iterations" align_left),
+                                    (zoom: [-10.0 to 50.0] "Zoom"),
+                                    (offset_x: [ ] "Real Offset (x)" center),
+                                    (offset_y: [ ] "Real Offset (y)" center),
+                                    (polynomial: [ ] "Polynomial Roots" align_left),
+                                    (relaxation: [0.1 to 2.0] "Relaxation")
+                                ]
+                            }
+                            .lens(AppDataToNewton {}),
+                        ),
+                        FractalType::BurningShip => Box::new(
+                            parameters_to_interface! {
+                                BurningShipParameters
+                                [
+                                    (max_iter: [ ] "Maximum Iterations" align_left),
+                                    (zoom: [-10.0 to 50.0] "Zoom"),
+                                    (offset_x: [ ] "Real Offset (x)" center),
+                                    (offset_y: [ ] "Real Offset (y)" center),
+                                    (variant: {
+                                        "Burning Ship" => ShipVariant::BurningShip,
+                                        "Celtic" => ShipVariant::Celtic,
+                                        "Buffalo" => ShipVariant::Buffalo,
+                                        "Perpendicular" => ShipVariant::Perpendicular
+                                    } "Variant")
+                                ]
+                            }
+                            .lens(AppDataToBurningShip {}),
+                        ),
+                        FractalType::Tricorn => Box::new(
+                            parameters_to_interface! {
+                                TricornParameters
+                                [
+                                    (max_iter: [ ] "Maximum Iterations" align_left),
+                                    (zoom: [-10.0 to 50.0] "Zoom"),
+                                    (offset_x: [ ] "Real Offset (x)" center),
+                                    (offset_y: [ ] "Real Offset (y)" center)
+                                ]
+                            }
+                            .lens(AppDataToTricorn {}),
+                        ),
+                        FractalType::TricornJulia => Box::new(
+                            parameters_to_interface! {
+                                TricornJuliaParameters
                                 [
                                     (max_iter: [ ] "Maximum Iterations" align_left),
                                     (zoom: [-10.0 to 50.0] "Zoom"),
@@ -218,7 +406,89 @@ fn create_generation_tab() -> impl Widget<AppData> {
                                     (constant_imag: [-2.0 to 2.0] "Imaginary value (y)")
                                 ]
                             }
-                            .lens(AppDataToJulia {}),
+                            .lens(AppDataToTricornJulia {}),
+                        ),
+                        FractalType::Phoenix => Box::new(
+                            parameters_to_interface! {
+                                PhoenixParameters
+                                [
+                                    (max_iter: [ ] "Maximum Iterations" align_left),
+                                    (zoom: [-10.0 to 50.0] "Zoom"),
+                                    (offset_x: [ ] "Real Offset (x)" center),
+                                    (offset_y: [ ] "Real Offset (y)" center),
+                                    (constant_real: [-2.0 to 2.0] "Real value (x)"),
+                                    (constant_imag: [-2.0 to 2.0] "Imaginary value (y)"),
+                                    (p_real: [-2.0 to 2.0] "Previous Iterate Factor p (x)"),
+                                    (p_imag: [-2.0 to 2.0] "Previous Iterate Factor p (y)")
+                                ]
+                            }
+                            .lens(AppDataToPhoenix {}),
+                        ),
+                        FractalType::Lyapunov => Box::new(
+                            parameters_to_interface! {
+                                LyapunovParameters
+                                [
+                                    (max_iter: [ ] "Iterations" align_left),
+                                    (warmup: [ ] "Warmup Iterations" align_left),
+                                    (zoom: [-10.0 to 50.0] "Zoom"),
+                                    (offset_x: [ ] "Rate A Offset (x)" center),
+                                    (offset_y: [ ] "Rate B Offset (y)" center),
+                                    (sequence: [ ] "Sequence" center)
+                                ]
+                            }
+                            .lens(AppDataToLyapunov {}),
+                        ),
+                        FractalType::Nova => Box::new(
+                            parameters_to_interface! {
+                                NovaParameters
+                                [
+                                    (max_iter: [ ] "Maximum Iterations" align_left),
+                                    (zoom: [-10.0 to 50.0] "Zoom"),
+                                    (offset_x: [ ] "Real Offset (x)" center),
+                                    (offset_y: [ ] "Real Offset (y)" center),
+                                    (exponent: [1.5 to 8.0] "Exponent (p)"),
+                                    (relaxation: [0.01 to 2.0] "Relaxation"),
+                                    (julia: [ x ] "Julia Plane"),
+                                    (constant_real: [-2.0 to 2.0] "Real value (x)"),
+                                    (constant_imag: [-2.0 to 2.0] "Imaginary value (y)")
+                                ]
+                            }
+                            .lens(AppDataToNova {}),
+                        ),
+                        FractalType::Buddhabrot => Box::new(
+                            parameters_to_interface! {
+                                BuddhabrotParameters
+                                [
+                                    (zoom: [-10.0 to 50.0] "Zoom"),
+                                    (offset_x: [ ] "Real Offset (x)" center),
+                                    (offset_y: [ ] "Real Offset (y)" center),
+                                    (samples: [0.1 to 100.0] "Samples per Pixel"),
+                                    (seed: [ ] "Random Seed" align_left),
+                                    (anti: [ x ] "Anti-Buddhabrot"),
+                                    (red_iter: [ ] "Red Iterations" align_left),
+                                    (green_iter: [ ] "Green Iterations" align_left),
+                                    (blue_iter: [ ] "Blue Iterations" align_left)
+                                ]
+                            }
+                            .lens(AppDataToBuddhabrot {}),
+                        ),
+                        FractalType::Custom => Box::new(
+                            parameters_to_interface! {
+                                CustomParameters
+                                [
+                                    (max_iter: [ ] "Maximum Iterations" align_left),
+                                    (zoom: [-10.0 to 50.0] "Zoom"),
+                                    (offset_x: [ ] "Real Offset (x)" center),
+                                    (offset_y: [ ] "Real Offset (y)" center),
+                                    (formula: [ ] "Formula z = f(z, c)" center),
+                                    (start: [ ] "Starting z" center),
+                                    (bailout: [ ] "Bailout Condition" center),
+                                    (julia: [ x ] "Julia Plane"),
+                                    (constant_real: [-2.0 to 2.0] "Real value (x)"),
+                                    (constant_imag: [-2.0 to 2.0] "Imaginary value (y)")
+                                ]
+                            }
+                            .lens(AppDataToCustom {}),
                         ),
                     },
                 )),
@@ -250,7 +520,8 @@ fn create_coloring_tab() -> impl Widget<AppData> {
                             (glow_spread: [-10.0 to 10.0] "Glow Spread"),
                             (glow_strength: [0.01 to 10.0] "Glow Strength"),
                             (brightness: [0.01 to 10.0] "Brightness"),
-                            (internal_brightness: [0.01 to 100.0] "Internal Brightness")
+                            (internal_brightness: [0.01 to 100.0] "Internal Brightness"),
+                            (period_coloring: [ x ] "Color Interior by Period")
                         ]
                     }
                     .lens(AppDataToMandel {}),
@@ -265,11 +536,134 @@ fn create_coloring_tab() -> impl Widget<AppData> {
                             (glow_spread: [-10.0 to 10.0] "Glow Spread"),
                             (glow_strength: [0.01 to 10.0] "Glow Strength"),
                             (brightness: [0.01 to 10.0] "Brightness"),
-                            (internal_brightness: [0.01 to 100.0] "Internal Brightness")
+                            (internal_brightness: [0.01 to 100.0] "Internal Brightness"),
+                            (period_coloring: [ x ] "Color Interior by Period")
                         ]
                     }
                     .lens(AppDataToJulia {}),
                 ),
+                FractalType::Newton => Box::new(
+                    parameters_to_interface! {
+                        NewtonParameters
+                        [
+                            (saturation: [0.0 to 1.0] "Saturation"),
+                            (color_frequency: [0.01 to 10.0] "Shading Frequency"),
+                            (color_offset: [0.0 to 1.0] "Color Offset"),
+                            (brightness: [0.01 to 10.0] "Brightness")
+                        ]
+                    }
+                    .lens(AppDataToNewton {}),
+                ),
+                FractalType::BurningShip => Box::new(
+                    parameters_to_interface! {
+                        BurningShipParameters
+                        [
+                            (saturation: [0.0 to 2.0] "Saturation"),
+                            (color_frequency: [0.01 to 10.0] "Color Frequency"),
+                            (color_offset: [0.0 to 1.0] "Color Offset"),
+                            (glow_spread: [-10.0 to 10.0] "Glow Spread"),
+                            (glow_strength: [0.01 to 10.0] "Glow Strength"),
+                            (brightness: [0.01 to 10.0] "Brightness"),
+                            (internal_brightness: [0.01 to 100.0] "Internal Brightness")
+                        ]
+                    }
+                    .lens(AppDataToBurningShip {}),
+                ),
+                FractalType::Tricorn => Box::new(
+                    parameters_to_interface! {
+                        TricornParameters
+                        [
+                            (saturation: [0.0 to 2.0] "Saturation"),
+                            (color_frequency: [0.01 to 10.0] "Color Frequency"),
+                            (color_offset: [0.0 to 1.0] "Color Offset"),
+                            (glow_spread: [-10.0 to 10.0] "Glow Spread"),
+                            (glow_strength: [0.01 to 10.0] "Glow Strength"),
+                            (brightness: [0.01 to 10.0] "Brightness"),
+                            (internal_brightness: [0.01 to 100.0] "Internal Brightness")
+                        ]
+                    }
+                    .lens(AppDataToTricorn {}),
+                ),
+                FractalType::TricornJulia => Box::new(
+                    parameters_to_interface! {
+                        TricornJuliaParameters
+                        [
+                            (saturation: [0.0 to 2.0] "Saturation"),
+                            (color_frequency: [0.01 to 10.0] "Color Frequency"),
+                            (color_offset: [0.0 to 1.0] "Color Offset"),
+                            (glow_spread: [-10.0 to 10.0] "Glow Spread"),
+                            (glow_strength: [0.01 to 10.0] "Glow Strength"),
+                            (brightness: [0.01 to 10.0] "Brightness"),
+                            (internal_brightness: [0.01 to 100.0] "Internal Brightness")
+                        ]
+                    }
+                    .lens(AppDataToTricornJulia {}),
+                ),
+                FractalType::Phoenix => Box::new(
+                    parameters_to_interface! {
+                        PhoenixParameters
+                        [
+                            (saturation: [0.0 to 2.0] "Saturation"),
+                            (color_frequency: [0.01 to 10.0] "Color Frequency"),
+                            (color_offset: [0.0 to 1.0] "Color Offset"),
+                            (glow_spread: [-10.0 to 10.0] "Glow Spread"),
+                            (glow_strength: [0.01 to 10.0] "Glow Strength"),
+                            (brightness: [0.01 to 10.0] "Brightness"),
+                            (internal_brightness: [0.01 to 100.0] "Internal Brightness")
+                        ]
+                    }
+                    .lens(AppDataToPhoenix {}),
+                ),
+                FractalType::Lyapunov => Box::new(
+                    parameters_to_interface! {
+                        LyapunovParameters
+                        [
+                            (saturation: [0.0 to 1.0] "Saturation"),
+                            (color_offset: [0.0 to 1.0] "Color Offset"),
+                            (contrast: [0.01 to 10.0] "Contrast"),
+                            (brightness: [0.01 to 10.0] "Brightness")
+                        ]
+                    }
+                    .lens(AppDataToLyapunov {}),
+                ),
+                FractalType::Nova => Box::new(
+                    parameters_to_interface! {
+                        NovaParameters
+                        [
+                            (saturation: [0.0 to 1.0] "Saturation"),
+                            (color_frequency: [0.01 to 10.0] "Color Frequency"),
+                            (color_offset: [0.0 to 1.0] "Color Offset"),
+                            (brightness: [0.01 to 10.0] "Brightness"),
+                            (internal_brightness: [0.01 to 100.0] "Internal Brightness")
+                        ]
+                    }
+                    .lens(AppDataToNova {}),
+                ),
+                FractalType::Buddhabrot => Box::new(
+                    parameters_to_interface! {
+                        BuddhabrotParameters
+                        [
+                            (exposure: [0.01 to 10.0] "Exposure"),
+                            (gamma: [0.1 to 5.0] "Gamma")
+                        ]
+                    }
+                    .lens(AppDataToBuddhabrot {}),
+                ),
+                FractalType::Custom => Box::new(
+                    parameters_to_interface! {
+                        CustomParameters
+                        [
+                            (saturation: [0.0 to 2.0] "Saturation"),
+                            (color_frequency: [0.01 to 10.0] "Color Frequency"),
+                            (color_offset: [0.0 to 1.0] "Color Offset"),
+                            (glow_spread: [-10.0 to 10.0] "Glow Spread"),
+                            (glow_strength: [0.01 to 10.0] "Glow Strength"),
+                            (brightness: [0.01 to 10.0] "Brightness"),
+                            (internal_brightness: [0.01 to 100.0] "Internal Brightness")
+                        ]
+                    }
+                    .lens(AppDataToCustom {}),
+                ),
             },
         ))
         .main_axis_alignment(MainAxisAlignment::Start)
@@ -279,38 +673,39 @@ fn fractal_switcher(
     switcher: impl Fn(&FractalType) -> Box<dyn Widget<AppData>> + 'static,
 ) -> impl Widget<AppData> {
     ViewSwitcher::new(
-        |data: &AppData, _env| match data.settings {
-            FractalSettings::Mandel(_) => FractalType::Mandel,
-            FractalSettings::Julia(_) => FractalType::Julia,
-        },
+        |data: &AppData, _env| FractalType::of(&data.settings),
         move |fractal_settings, _data, _env| switcher(fractal_settings),
     )
 }
 
-fn render_full(_ctx: &mut EventCtx, data: &mut AppData, _env: &Env) {
+fn render_full(ctx: &mut EventCtx, data: &mut AppData, _env: &Env) {
+    // shows the number of samples that is used, as larger ones would run out of memory
+    data.antialiasing.samples = data.antialiasing.samples.clamp(1, MAX_SAMPLES);
     let mut render_image = ImageGenerator::new(data.output_width, data.output_height);
-    let passable = data.clone();
+    render_image.set_subdivide(data.subdivide);
+    render_image.set_antialiasing(data.antialiasing);
+    render_image.set_transparency(data.transparency);
+    if let Err(err) = render_image.check_save_format(&data.filename, data.pixel_format) {
+        data.log_text = format!("Can not render: {}", err);
+        return;
+    }
+    let saved_image = render_image.clone();
+    let filename = data.filename.clone();
+    let pixel_format = data.pixel_format;
+    let sink = ctx.get_external_handle();
     data.log_text = String::from("Render Started (done soon)");
-    let _ = std::thread::spawn(move || {
-        let filename = passable.filename.clone();
-        match passable.settings {
-            FractalSettings::Mandel(settings) => {
-                render_image.do_compute::<MandelParameters>(settings, num_cpus::get())
-            }
-            FractalSettings::Julia(settings) => {
-                render_image.do_compute::<JuliaParameters>(settings, num_cpus::get())
-            }
-        }
-        let image_ref = render_image.image_ref().lock();
-        if let Ok(image) = image_ref {
-            image::save_buffer(
-                filename.as_str(),
-                image.data.as_ref(),
-                image.width as u32,
-                image.height as u32,
-                image::ColorType::Rgb8,
-            )
-            .unwrap()
-        }
-    });
+    // runs behind the live view, which keeps priority while the full render is running
+    data.settings.clone().start_compute(
+        &render_image,
+        ThreadPool::global(),
+        Priority::Background,
+        move || {
+            let message = match saved_image.save_image(filename.as_str(), pixel_format) {
+                Ok(()) => format!("Saved {}", filename),
+                Err(err) => format!("Failed to save image: {}", err),
+            };
+            // only fails once the app is closing, when there is no log to show it in
+            let _ = sink.submit_command(RENDER_FINISHED, message, Target::Auto);
+        },
+    );
 }
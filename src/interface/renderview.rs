@@ -6,7 +6,7 @@ use druid::{
     Rect,
 };
 
-use crate::backends::{GeneratorParameters, ImageGenerator};
+use crate::backends::{GeneratorParameters, ImageGenerator, Priority, ThreadPool};
 
 #[derive(Debug, PartialEq)]
 enum RenderState {
@@ -28,13 +28,21 @@ use RenderState::*;
 impl RenderView {
     pub fn new(width: usize, height: usize) -> Self {
         RenderView {
-            image: ImageGenerator::new(width, height),
+            image: Self::new_image(width, height),
             state: NotStarted,
             should_render: true,
             should_resize: false,
         }
     }
 
+    /// an image which keeps its intermediates, so that changing only the colors
+    /// reshades the view instead of calculating it again
+    fn new_image(width: usize, height: usize) -> ImageGenerator {
+        let mut image = ImageGenerator::new(width, height);
+        image.set_cache_intermediates(true);
+        image
+    }
+
     fn finish(&mut self) {
         self.image.cancel_compute();
         self.state = Canceled;
@@ -43,9 +51,12 @@ impl RenderView {
     /// Precondition: Requires self state to not be InProgress or Canceled
     fn render_new<GP: GeneratorParameters>(&mut self, settings: &GP) {
         debug_assert_matches!(self.state, NotStarted | Finished);
-        let sent_settings = settings.clone();
-        let mut sent_image = self.image.clone();
-        let _ = std::thread::spawn(move || sent_image.do_compute(sent_settings, num_cpus::get()));
+        self.image.start_compute(
+            settings.clone(),
+            ThreadPool::global(),
+            Priority::Interactive,
+            || {},
+        );
 
         self.state = InProgress(0.0);
         self.should_render = false;
@@ -55,7 +66,7 @@ impl RenderView {
     fn resize(&mut self, new_size: &Size) {
         debug_assert_matches!(self.state, NotStarted | Finished);
         let &Size { width, height } = new_size;
-        self.image = ImageGenerator::new(width as usize, height as usize);
+        self.image = Self::new_image(width as usize, height as usize);
 
         self.should_resize = false;
         self.should_render = true;
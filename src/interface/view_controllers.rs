@@ -4,6 +4,10 @@ use crate::{backends::GeneratorParameters, types::FractalSettings, AppData};
 
 use super::RenderView;
 
+/// the highest iteration count chosen automatically while zooming,
+/// which keeps deep zooms from allocating enormous reference orbits
+const MAX_AUTO_ITERATIONS: usize = 1_000_000;
+
 trait OffsetZoomMovement {
     fn offset(&mut self, offset: Vec2);
     fn get_zoom(&self) -> f64;
@@ -21,23 +25,110 @@ impl OffsetZoomMovement for AppData {
                 inner.offset_x -= offset.x;
                 inner.offset_y -= offset.y;
             }
+            FractalSettings::Newton(inner) => {
+                inner.offset_x -= offset.x;
+                inner.offset_y -= offset.y;
+            }
+            FractalSettings::BurningShip(inner) => {
+                inner.offset_x -= offset.x;
+                inner.offset_y -= offset.y;
+            }
+            FractalSettings::Tricorn(inner) => {
+                inner.offset_x -= offset.x;
+                inner.offset_y -= offset.y;
+            }
+            FractalSettings::TricornJulia(inner) => {
+                inner.offset_x -= offset.x;
+                inner.offset_y -= offset.y;
+            }
+            FractalSettings::Phoenix(inner) => {
+                inner.offset_x -= offset.x;
+                inner.offset_y -= offset.y;
+            }
+            FractalSettings::Lyapunov(inner) => {
+                inner.offset_x -= offset.x;
+                inner.offset_y -= offset.y;
+            }
+            FractalSettings::Nova(inner) => {
+                inner.offset_x -= offset.x;
+                inner.offset_y -= offset.y;
+            }
+            FractalSettings::Buddhabrot(inner) => {
+                inner.offset_x -= offset.x;
+                inner.offset_y -= offset.y;
+            }
+            FractalSettings::Custom(inner) => {
+                inner.offset_x -= offset.x;
+                inner.offset_y -= offset.y;
+            }
         }
     }
     fn get_zoom(&self) -> f64 {
         match &self.settings {
             FractalSettings::Mandel(inner) => inner.zoom,
             FractalSettings::Julia(inner) => inner.zoom,
+            FractalSettings::Newton(inner) => inner.zoom,
+            FractalSettings::BurningShip(inner) => inner.zoom,
+            FractalSettings::Tricorn(inner) => inner.zoom,
+            FractalSettings::TricornJulia(inner) => inner.zoom,
+            FractalSettings::Phoenix(inner) => inner.zoom,
+            FractalSettings::Lyapunov(inner) => inner.zoom,
+            FractalSettings::Nova(inner) => inner.zoom,
+            FractalSettings::Buddhabrot(inner) => inner.zoom,
+            FractalSettings::Custom(inner) => inner.zoom,
         }
     }
     fn offset_zoom(&mut self, offset: f64) {
         match &mut self.settings {
             FractalSettings::Mandel(inner) => {
                 inner.zoom -= offset;
-                inner.max_iter = (f64::powf(2.0, inner.zoom / 10.0) * 1000.0) as usize;
+                inner.max_iter = ((f64::powf(2.0, inner.zoom / 10.0) * 1000.0) as usize)
+                    .min(MAX_AUTO_ITERATIONS);
             }
             FractalSettings::Julia(inner) => {
                 inner.zoom -= offset;
-                inner.max_iter = (f64::powf(2.0, inner.zoom / 10.0) * 1000.0) as usize;
+                inner.max_iter = ((f64::powf(2.0, inner.zoom / 10.0) * 1000.0) as usize)
+                    .min(MAX_AUTO_ITERATIONS);
+            }
+            // newton iterations converge quickly at any zoom, so max_iter is left alone
+            FractalSettings::Newton(inner) => {
+                inner.zoom -= offset;
+            }
+            FractalSettings::BurningShip(inner) => {
+                inner.zoom -= offset;
+                inner.max_iter = ((f64::powf(2.0, inner.zoom / 10.0) * 1000.0) as usize)
+                    .min(MAX_AUTO_ITERATIONS);
+            }
+            FractalSettings::Tricorn(inner) => {
+                inner.zoom -= offset;
+                inner.max_iter = ((f64::powf(2.0, inner.zoom / 10.0) * 1000.0) as usize)
+                    .min(MAX_AUTO_ITERATIONS);
+            }
+            FractalSettings::TricornJulia(inner) => {
+                inner.zoom -= offset;
+                inner.max_iter = ((f64::powf(2.0, inner.zoom / 10.0) * 1000.0) as usize)
+                    .min(MAX_AUTO_ITERATIONS);
+            }
+            FractalSettings::Phoenix(inner) => {
+                inner.zoom -= offset;
+                inner.max_iter = ((f64::powf(2.0, inner.zoom / 10.0) * 1000.0) as usize)
+                    .min(MAX_AUTO_ITERATIONS);
+            }
+            FractalSettings::Lyapunov(inner) => {
+                inner.zoom -= offset;
+            }
+            FractalSettings::Nova(inner) => {
+                inner.zoom -= offset;
+                inner.max_iter = ((f64::powf(2.0, inner.zoom / 10.0) * 1000.0) as usize)
+                    .min(MAX_AUTO_ITERATIONS);
+            }
+            FractalSettings::Buddhabrot(inner) => {
+                inner.zoom -= offset;
+            }
+            FractalSettings::Custom(inner) => {
+                inner.zoom -= offset;
+                inner.max_iter = ((f64::powf(2.0, inner.zoom / 10.0) * 1000.0) as usize)
+                    .min(MAX_AUTO_ITERATIONS);
             }
         }
     }
@@ -4,26 +4,42 @@
 #![feature(assert_matches)]
 
 mod backends;
+mod cli;
 mod interface;
+mod scene;
 mod types;
 
 use druid::{
     theme, AppLauncher, Color, Env, FontDescriptor, FontFamily, PlatformError, WindowDesc,
 };
 
-use backends::MandelParameters;
+use backends::{Antialiasing, MandelParameters, PixelFormat, Transparency};
 use types::{AppData, FractalSettings};
 
 fn main() -> Result<(), PlatformError> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--headless") {
+        if let Err(err) = cli::run(&args[1..]) {
+            eprintln!("{}", err);
+            std::process::exit(err.exit_code());
+        }
+        return Ok(());
+    }
+
     let main_window = WindowDesc::new(interface::build_ui);
     let data = AppData {
         settings: FractalSettings::Mandel(MandelParameters::default()),
         output_width: 3840,
         output_height: 2160,
         filename: String::from("fractal.png"),
+        pixel_format: PixelFormat::Rgb8,
+        subdivide: false,
+        antialiasing: Antialiasing::default(),
+        transparency: Transparency::default(),
         log_text: String::new(),
     };
     AppLauncher::with_window(main_window)
+        .delegate(interface::Delegate)
         .configure_env(configure)
         .use_simple_logger()
         .launch(data)
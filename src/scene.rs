@@ -0,0 +1,192 @@
+use std::{fmt, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backends::{Antialiasing, PixelFormat, Transparency},
+    types::{AppData, FractalSettings},
+};
+
+/// the newest scene format this build can read and the one it writes.
+/// Bump this when a change would make older builds misread a scene.
+pub const SCENE_VERSION: u32 = 4;
+
+/// a complete, human-editable description of an image: the fractal,
+/// its location and colors, and the output settings of the full render
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Scene {
+    pub version: u32,
+    pub fractal: FractalSettings,
+    pub render: RenderSettings,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderSettings {
+    pub output_width: usize,
+    pub output_height: usize,
+    pub filename: String,
+    pub pixel_format: PixelFormat,
+    /// see `ImageGenerator::set_subdivide`
+    pub subdivide: bool,
+    /// see `ImageGenerator::set_antialiasing`
+    pub antialiasing: Antialiasing,
+    /// see `ImageGenerator::set_transparency`
+    pub transparency: Transparency,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            output_width: 3840,
+            output_height: 2160,
+            filename: String::from("fractal.png"),
+            pixel_format: PixelFormat::Rgb8,
+            subdivide: false,
+            antialiasing: Antialiasing::default(),
+            transparency: Transparency::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(err) => write!(f, "{}", err),
+            SceneError::Parse(err) => write!(f, "invalid scene file: {}", err),
+            SceneError::Serialize(err) => write!(f, "could not write scene: {}", err),
+            SceneError::UnsupportedVersion(version) => write!(
+                f,
+                "scene version {} is newer than the supported version {}",
+                version, SCENE_VERSION
+            ),
+        }
+    }
+}
+
+impl From<io::Error> for SceneError {
+    fn from(err: io::Error) -> Self {
+        SceneError::Io(err)
+    }
+}
+
+impl Scene {
+    pub fn from_app_data(data: &AppData) -> Self {
+        Scene {
+            version: SCENE_VERSION,
+            fractal: data.settings.clone(),
+            render: RenderSettings {
+                output_width: data.output_width,
+                output_height: data.output_height,
+                filename: data.filename.clone(),
+                pixel_format: data.pixel_format,
+                subdivide: data.subdivide,
+                antialiasing: data.antialiasing,
+                transparency: data.transparency,
+            },
+        }
+    }
+
+    /// replaces everything in `data` that is described by the scene
+    pub fn apply(self, data: &mut AppData) {
+        data.settings = self.fractal;
+        data.output_width = self.render.output_width;
+        data.output_height = self.render.output_height;
+        data.filename = self.render.filename;
+        data.pixel_format = self.render.pixel_format;
+        data.subdivide = self.render.subdivide;
+        data.antialiasing = self.render.antialiasing;
+        data.transparency = self.render.transparency;
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let text = fs::read_to_string(path)?;
+        let scene: Scene = toml::from_str(&text).map_err(SceneError::Parse)?;
+        if scene.version > SCENE_VERSION {
+            return Err(SceneError::UnsupportedVersion(scene.version));
+        }
+        Ok(scene)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SceneError> {
+        let text = toml::to_string_pretty(self).map_err(SceneError::Serialize)?;
+        fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::{AntialiasingMode, JuliaParameters};
+
+    fn scene_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("mandel-app-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let mut julia = JuliaParameters {
+            max_iter: 1234,
+            zoom: 42.5,
+            constant_real: -0.8,
+            constant_imag: 0.156,
+            ..JuliaParameters::default()
+        };
+        julia.offset_x = "-0.743643887037158704752191506114774".parse().unwrap();
+        let scene = Scene {
+            version: SCENE_VERSION,
+            fractal: FractalSettings::Julia(julia),
+            render: RenderSettings {
+                output_width: 1920,
+                output_height: 1080,
+                filename: String::from("julia.exr"),
+                pixel_format: PixelFormat::Rgb32F,
+                subdivide: true,
+                antialiasing: Antialiasing {
+                    mode: AntialiasingMode::Adaptive,
+                    ..Antialiasing::default()
+                },
+                ..RenderSettings::default()
+            },
+        };
+        let path = scene_path("round-trip.toml");
+        scene.save(&path).unwrap();
+        let loaded = Scene::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.version, SCENE_VERSION);
+        assert!(loaded.fractal == scene.fractal);
+        assert_eq!(loaded.render.output_width, 1920);
+        assert_eq!(loaded.render.output_height, 1080);
+        assert_eq!(loaded.render.filename, "julia.exr");
+        assert_eq!(loaded.render.pixel_format, PixelFormat::Rgb32F);
+        assert!(loaded.render.subdivide);
+        assert_eq!(loaded.render.antialiasing, scene.render.antialiasing);
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        let path = scene_path("newer.toml");
+        let scene = Scene {
+            version: SCENE_VERSION + 1,
+            fractal: FractalSettings::Julia(JuliaParameters::default()),
+            render: RenderSettings::default(),
+        };
+        scene.save(&path).unwrap();
+        let result = Scene::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            result,
+            Err(SceneError::UnsupportedVersion(version)) if version == SCENE_VERSION + 1
+        ));
+    }
+}
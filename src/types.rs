@@ -1,11 +1,75 @@
 use druid::{Data, Lens};
+use serde::{Deserialize, Serialize};
 
-use crate::backends::{JuliaParameters, MandelParameters};
+use crate::backends::{
+    Antialiasing, BuddhabrotParameters, BurningShipParameters, CustomParameters, ImageGenerator,
+    JuliaParameters, LyapunovParameters, MandelParameters, NewtonParameters, NovaParameters,
+    PhoenixParameters, PixelFormat, Priority, ThreadPool, Transparency, TricornJuliaParameters,
+    TricornParameters,
+};
 
-#[derive(Clone, Data, PartialEq)]
+#[derive(Clone, Data, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
 pub enum FractalSettings {
     Mandel(MandelParameters),
     Julia(JuliaParameters),
+    Newton(NewtonParameters),
+    BurningShip(BurningShipParameters),
+    Tricorn(TricornParameters),
+    TricornJulia(TricornJuliaParameters),
+    Phoenix(PhoenixParameters),
+    Lyapunov(LyapunovParameters),
+    Nova(NovaParameters),
+    Buddhabrot(BuddhabrotParameters),
+    Custom(CustomParameters),
+}
+
+impl FractalSettings {
+    /// starts the computation for these settings on `pool`,
+    /// see `ImageGenerator::start_compute`
+    pub fn start_compute(
+        self,
+        image: &ImageGenerator,
+        pool: &ThreadPool,
+        priority: Priority,
+        on_finish: impl FnOnce() + Send + 'static,
+    ) {
+        match self {
+            FractalSettings::Mandel(settings) => {
+                image.start_compute::<MandelParameters>(settings, pool, priority, on_finish)
+            }
+            FractalSettings::Julia(settings) => {
+                image.start_compute::<JuliaParameters>(settings, pool, priority, on_finish)
+            }
+            FractalSettings::Newton(settings) => {
+                image.start_compute::<NewtonParameters>(settings, pool, priority, on_finish)
+            }
+            FractalSettings::BurningShip(settings) => {
+                image.start_compute::<BurningShipParameters>(settings, pool, priority, on_finish)
+            }
+            FractalSettings::Tricorn(settings) => {
+                image.start_compute::<TricornParameters>(settings, pool, priority, on_finish)
+            }
+            FractalSettings::TricornJulia(settings) => {
+                image.start_compute::<TricornJuliaParameters>(settings, pool, priority, on_finish)
+            }
+            FractalSettings::Phoenix(settings) => {
+                image.start_compute::<PhoenixParameters>(settings, pool, priority, on_finish)
+            }
+            FractalSettings::Lyapunov(settings) => {
+                image.start_compute::<LyapunovParameters>(settings, pool, priority, on_finish)
+            }
+            FractalSettings::Nova(settings) => {
+                image.start_compute::<NovaParameters>(settings, pool, priority, on_finish)
+            }
+            FractalSettings::Buddhabrot(settings) => {
+                image.start_compute::<BuddhabrotParameters>(settings, pool, priority, on_finish)
+            }
+            FractalSettings::Custom(settings) => {
+                image.start_compute::<CustomParameters>(settings, pool, priority, on_finish)
+            }
+        }
+    }
 }
 
 #[derive(Clone, Data, Lens)]
@@ -14,6 +78,10 @@ pub struct AppData {
     pub output_width: usize,
     pub output_height: usize,
     pub filename: String,
+    pub pixel_format: PixelFormat,
+    pub subdivide: bool,
+    pub antialiasing: Antialiasing,
+    pub transparency: Transparency,
     pub log_text: String,
 }
 
@@ -37,3 +105,93 @@ impl TryFrom<AppData> for JuliaParameters {
         }
     }
 }
+impl TryFrom<AppData> for NewtonParameters {
+    type Error = ();
+    fn try_from(val: AppData) -> Result<Self, Self::Error> {
+        if let FractalSettings::Newton(settings) = val.settings {
+            Ok(settings.clone())
+        } else {
+            Err(())
+        }
+    }
+}
+impl TryFrom<AppData> for BurningShipParameters {
+    type Error = ();
+    fn try_from(val: AppData) -> Result<Self, Self::Error> {
+        if let FractalSettings::BurningShip(settings) = val.settings {
+            Ok(settings.clone())
+        } else {
+            Err(())
+        }
+    }
+}
+impl TryFrom<AppData> for TricornParameters {
+    type Error = ();
+    fn try_from(val: AppData) -> Result<Self, Self::Error> {
+        if let FractalSettings::Tricorn(settings) = val.settings {
+            Ok(settings.clone())
+        } else {
+            Err(())
+        }
+    }
+}
+impl TryFrom<AppData> for TricornJuliaParameters {
+    type Error = ();
+    fn try_from(val: AppData) -> Result<Self, Self::Error> {
+        if let FractalSettings::TricornJulia(settings) = val.settings {
+            Ok(settings.clone())
+        } else {
+            Err(())
+        }
+    }
+}
+impl TryFrom<AppData> for PhoenixParameters {
+    type Error = ();
+    fn try_from(val: AppData) -> Result<Self, Self::Error> {
+        if let FractalSettings::Phoenix(settings) = val.settings {
+            Ok(settings.clone())
+        } else {
+            Err(())
+        }
+    }
+}
+impl TryFrom<AppData> for LyapunovParameters {
+    type Error = ();
+    fn try_from(val: AppData) -> Result<Self, Self::Error> {
+        if let FractalSettings::Lyapunov(settings) = val.settings {
+            Ok(settings.clone())
+        } else {
+            Err(())
+        }
+    }
+}
+impl TryFrom<AppData> for NovaParameters {
+    type Error = ();
+    fn try_from(val: AppData) -> Result<Self, Self::Error> {
+        if let FractalSettings::Nova(settings) = val.settings {
+            Ok(settings.clone())
+        } else {
+            Err(())
+        }
+    }
+}
+impl TryFrom<AppData> for BuddhabrotParameters {
+    type Error = ();
+    fn try_from(val: AppData) -> Result<Self, Self::Error> {
+        if let FractalSettings::Buddhabrot(settings) = val.settings {
+            Ok(settings.clone())
+        } else {
+            Err(())
+        }
+    }
+}
+impl TryFrom<AppData> for CustomParameters {
+    type Error = ();
+    fn try_from(val: AppData) -> Result<Self, Self::Error> {
+        if let FractalSettings::Custom(settings) = val.settings {
+            Ok(settings.clone())
+        } else {
+            Err(())
+        }
+    }
+}